@@ -367,16 +367,16 @@ fn main() {
                 }
                 "extract_pages" => {
                     if let Some(pages) = args.get_one("pages") {
-                        let page_numbers = compute_page_numbers(pages);
+                        let page_numbers = compute_page_numbers(&doc, pages);
                         let total = *doc.get_pages().keys().max().unwrap_or(&0);
                         let page_numbers = complement_page_numbers(&page_numbers, total);
-                        doc.delete_pages(&page_numbers);
+                        doc.delete_pages(&page_numbers).unwrap();
                     }
                 }
                 "delete_pages" => {
                     if let Some(pages) = args.get_one("pages") {
-                        let page_numbers = compute_page_numbers(pages);
-                        doc.delete_pages(&page_numbers);
+                        let page_numbers = compute_page_numbers(&doc, pages);
+                        doc.delete_pages(&page_numbers).unwrap();
                     }
                 }
                 "delete_objects" => {
@@ -393,7 +393,7 @@ fn main() {
                 }
                 "extract_text" => {
                     if let Some(pages) = args.get_one("pages") {
-                        let page_numbers = compute_page_numbers(pages);
+                        let page_numbers = compute_page_numbers(&doc, pages);
                         let text = doc.extract_text(&page_numbers);
                         info!("{}", text.unwrap());
                     }
@@ -458,14 +458,23 @@ fn apply_operation(doc: &mut Document, operation: &str) {
         }
     }
 
-    fn compute_page_numbers(pages: &str) -> Vec<u32> {
+    /// Parse a page range list such as `1,3-5` or `ii-iv,A-3`.
+    /// Numbers refer to physical pages, anything else is looked up as a page label.
+    fn compute_page_numbers(doc: &Document, pages: &str) -> Vec<u32> {
+        let resolve = |page: &str| u32::from_str(page).ok().or_else(|| doc.get_page_number_by_label(page));
         let mut page_numbers = vec![];
         for page in pages.split(',') {
-            let nums: Vec<u32> = page.split('-').map(|num| u32::from_str(num).unwrap()).collect();
-            match nums.len() {
-                1 => page_numbers.push(nums[0]),
-                2 => page_numbers.append(&mut (nums[0]..nums[1] + 1).collect()),
-                _ => {}
+            if let Some(number) = resolve(page) {
+                page_numbers.push(number);
+                continue;
+            }
+            // Labels may contain '-' themselves, so try every split position.
+            let range = page
+                .match_indices('-')
+                .find_map(|(index, _)| Some((resolve(&page[..index])?, resolve(&page[index + 1..])?)));
+            match range {
+                Some((start, end)) => page_numbers.append(&mut (start..end + 1).collect()),
+                None => panic!("invalid page number or label: {}", page),
             }
         }
         page_numbers
@@ -0,0 +1,1012 @@
+use log::warn;
+
+use crate::content::{Content, Operation};
+use crate::encodings::WIN_ANSI_ENCODING;
+use crate::{
+    Dictionary, Document, Error, Object, ObjectId, Permissions, Rect, Result, Stream, StringFormat, decode_text_string,
+    text_string, xobject,
+};
+
+/// The Print annotation flag.
+pub(crate) const PRINT_FLAG: u32 = 1 << 2;
+
+/// Properties shared by all annotation types.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationCommon {
+    /// Location of the annotation on the page.
+    pub rect: Rect,
+    /// Text displayed for the annotation (`/Contents`).
+    pub contents: Option<String>,
+    /// Author of the annotation (`/T`).
+    pub author: Option<String>,
+    /// Color with 1 (gray), 3 (RGB) or 4 (CMYK) components (`/C`).
+    pub color: Option<Vec<f32>>,
+    /// Annotation flags (`/F`), ISO 32000-1 Table 165. New annotations are printed.
+    pub flags: u32,
+}
+
+impl AnnotationCommon {
+    pub fn new(rect: Rect) -> Self {
+        AnnotationCommon {
+            rect,
+            contents: None,
+            author: None,
+            color: None,
+            flags: PRINT_FLAG,
+        }
+    }
+}
+
+/// A typed annotation, covering the commonly used annotation subtypes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Link {
+        common: AnnotationCommon,
+        /// Target URI, written as a URI action.
+        uri: Option<String>,
+        /// Target destination (`/Dest`), either a name or an explicit destination array.
+        destination: Option<Object>,
+    },
+    /// A sticky note.
+    Text {
+        common: AnnotationCommon,
+        /// Icon name, e.g. `Comment`, `Note` or `Help`.
+        icon: String,
+        open: bool,
+    },
+    FreeText {
+        common: AnnotationCommon,
+        /// Default appearance string (`/DA`) used to render the text.
+        default_appearance: String,
+    },
+    Highlight {
+        common: AnnotationCommon,
+        /// Four points per marked area in the order upper-left, upper-right, lower-left, lower-right.
+        quad_points: Vec<f32>,
+    },
+    Underline {
+        common: AnnotationCommon,
+        quad_points: Vec<f32>,
+    },
+    StrikeOut {
+        common: AnnotationCommon,
+        quad_points: Vec<f32>,
+    },
+    Squiggly {
+        common: AnnotationCommon,
+        quad_points: Vec<f32>,
+    },
+    Square {
+        common: AnnotationCommon,
+        /// Fill color (`/IC`).
+        interior_color: Option<Vec<f32>>,
+        border_width: f32,
+    },
+    Circle {
+        common: AnnotationCommon,
+        interior_color: Option<Vec<f32>>,
+        border_width: f32,
+    },
+    Line {
+        common: AnnotationCommon,
+        /// Start and end point `[x1 y1 x2 y2]`.
+        line: [f32; 4],
+        border_width: f32,
+    },
+    Ink {
+        common: AnnotationCommon,
+        /// Stroked paths, each given as alternating x and y coordinates.
+        ink_list: Vec<Vec<f32>>,
+        border_width: f32,
+    },
+    Stamp {
+        common: AnnotationCommon,
+        /// Stamp name, e.g. `Approved` or `Draft`.
+        name: String,
+    },
+    FileAttachment {
+        common: AnnotationCommon,
+        /// File specification (`/FS`).
+        file_spec: Object,
+        icon: String,
+    },
+    Popup {
+        common: AnnotationCommon,
+        /// The annotation this popup belongs to.
+        parent: Option<ObjectId>,
+        open: bool,
+    },
+}
+
+impl Annotation {
+    /// Create a sticky note with a comment.
+    pub fn text(rect: Rect, contents: &str) -> Self {
+        Annotation::Text {
+            common: AnnotationCommon {
+                contents: Some(contents.to_string()),
+                color: Some(vec![1.0, 1.0, 0.0]),
+                ..AnnotationCommon::new(rect)
+            },
+            icon: "Comment".to_string(),
+            open: false,
+        }
+    }
+
+    /// Create a yellow highlight over the given quadrilaterals.
+    pub fn highlight(quad_points: Vec<f32>) -> Self {
+        Annotation::Highlight {
+            common: AnnotationCommon {
+                color: Some(vec![1.0, 1.0, 0.0]),
+                ..AnnotationCommon::new(bounding_rect(&quad_points))
+            },
+            quad_points,
+        }
+    }
+
+    /// Create a link opening an URI.
+    pub fn link_to_uri(rect: Rect, uri: &str) -> Self {
+        Annotation::Link {
+            common: AnnotationCommon::new(rect),
+            uri: Some(uri.to_string()),
+            destination: None,
+        }
+    }
+
+    pub fn subtype(&self) -> &'static str {
+        match self {
+            Annotation::Link { .. } => "Link",
+            Annotation::Text { .. } => "Text",
+            Annotation::FreeText { .. } => "FreeText",
+            Annotation::Highlight { .. } => "Highlight",
+            Annotation::Underline { .. } => "Underline",
+            Annotation::StrikeOut { .. } => "StrikeOut",
+            Annotation::Squiggly { .. } => "Squiggly",
+            Annotation::Square { .. } => "Square",
+            Annotation::Circle { .. } => "Circle",
+            Annotation::Line { .. } => "Line",
+            Annotation::Ink { .. } => "Ink",
+            Annotation::Stamp { .. } => "Stamp",
+            Annotation::FileAttachment { .. } => "FileAttachment",
+            Annotation::Popup { .. } => "Popup",
+        }
+    }
+
+    pub fn common(&self) -> &AnnotationCommon {
+        match self {
+            Annotation::Link { common, .. }
+            | Annotation::Text { common, .. }
+            | Annotation::FreeText { common, .. }
+            | Annotation::Highlight { common, .. }
+            | Annotation::Underline { common, .. }
+            | Annotation::StrikeOut { common, .. }
+            | Annotation::Squiggly { common, .. }
+            | Annotation::Square { common, .. }
+            | Annotation::Circle { common, .. }
+            | Annotation::Line { common, .. }
+            | Annotation::Ink { common, .. }
+            | Annotation::Stamp { common, .. }
+            | Annotation::FileAttachment { common, .. }
+            | Annotation::Popup { common, .. } => common,
+        }
+    }
+
+    pub fn common_mut(&mut self) -> &mut AnnotationCommon {
+        match self {
+            Annotation::Link { common, .. }
+            | Annotation::Text { common, .. }
+            | Annotation::FreeText { common, .. }
+            | Annotation::Highlight { common, .. }
+            | Annotation::Underline { common, .. }
+            | Annotation::StrikeOut { common, .. }
+            | Annotation::Squiggly { common, .. }
+            | Annotation::Square { common, .. }
+            | Annotation::Circle { common, .. }
+            | Annotation::Line { common, .. }
+            | Annotation::Ink { common, .. }
+            | Annotation::Stamp { common, .. }
+            | Annotation::FileAttachment { common, .. }
+            | Annotation::Popup { common, .. } => common,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.common().rect
+    }
+
+    pub fn contents(&self) -> Option<&str> {
+        self.common().contents.as_deref()
+    }
+
+    /// Parse an annotation dictionary. Fails for subtypes not covered by [`Annotation`], like widgets.
+    pub fn from_dict(dict: &Dictionary) -> Result<Self> {
+        let common = AnnotationCommon {
+            rect: Rect::from_object(dict.get(b"Rect")?)?,
+            contents: dict.get(b"Contents").and_then(decode_text_string).ok(),
+            author: dict.get(b"T").and_then(decode_text_string).ok(),
+            color: dict.get(b"C").ok().and_then(numbers),
+            flags: dict.get(b"F").and_then(Object::as_i64).unwrap_or(0) as u32,
+        };
+        let name = |key: &[u8], default: &str| {
+            dict.get(key)
+                .and_then(Object::as_name)
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_else(|_| default.to_string())
+        };
+        let border_width = border_width(dict);
+        let quad_points = || dict.get(b"QuadPoints").ok().and_then(numbers).unwrap_or_default();
+        let open = dict.get(b"Open").and_then(Object::as_bool).unwrap_or(false);
+
+        let annotation = match dict.get(b"Subtype").and_then(Object::as_name)? {
+            b"Link" => Annotation::Link {
+                common,
+                uri: dict
+                    .get(b"A")
+                    .and_then(Object::as_dict)
+                    .and_then(|action| action.get(b"URI"))
+                    .and_then(Object::as_str)
+                    .map(|uri| String::from_utf8_lossy(uri).into_owned())
+                    .ok(),
+                destination: dict.get(b"Dest").ok().cloned(),
+            },
+            b"Text" => Annotation::Text {
+                common,
+                icon: name(b"Name", "Note"),
+                open,
+            },
+            b"FreeText" => Annotation::FreeText {
+                common,
+                default_appearance: dict
+                    .get(b"DA")
+                    .and_then(Object::as_str)
+                    .map(|da| String::from_utf8_lossy(da).into_owned())
+                    .unwrap_or_default(),
+            },
+            b"Highlight" => Annotation::Highlight {
+                common,
+                quad_points: quad_points(),
+            },
+            b"Underline" => Annotation::Underline {
+                common,
+                quad_points: quad_points(),
+            },
+            b"StrikeOut" => Annotation::StrikeOut {
+                common,
+                quad_points: quad_points(),
+            },
+            b"Squiggly" => Annotation::Squiggly {
+                common,
+                quad_points: quad_points(),
+            },
+            b"Square" => Annotation::Square {
+                common,
+                interior_color: dict.get(b"IC").ok().and_then(numbers),
+                border_width,
+            },
+            b"Circle" => Annotation::Circle {
+                common,
+                interior_color: dict.get(b"IC").ok().and_then(numbers),
+                border_width,
+            },
+            b"Line" => {
+                let points = dict.get(b"L").ok().and_then(numbers).unwrap_or_default();
+                let line = points.try_into().map_err(|_| Error::DictKey("L".to_string()))?;
+                Annotation::Line {
+                    common,
+                    line,
+                    border_width,
+                }
+            }
+            b"Ink" => Annotation::Ink {
+                common,
+                ink_list: dict
+                    .get(b"InkList")
+                    .and_then(Object::as_array)
+                    .map(|paths| paths.iter().filter_map(numbers).collect())
+                    .unwrap_or_default(),
+                border_width,
+            },
+            b"Stamp" => Annotation::Stamp {
+                common,
+                name: name(b"Name", "Draft"),
+            },
+            b"FileAttachment" => Annotation::FileAttachment {
+                common,
+                file_spec: dict.get(b"FS")?.clone(),
+                icon: name(b"Name", "PushPin"),
+            },
+            b"Popup" => Annotation::Popup {
+                common,
+                parent: dict.get(b"Parent").and_then(Object::as_reference).ok(),
+                open,
+            },
+            _ => return Err(Error::Unimplemented("annotation subtype")),
+        };
+        Ok(annotation)
+    }
+
+    /// Build the annotation dictionary, without appearance stream.
+    pub fn to_dict(&self) -> Dictionary {
+        let common = self.common();
+        let mut dict = dictionary! {
+            "Type" => "Annot",
+            "Subtype" => self.subtype(),
+            "Rect" => common.rect,
+        };
+        if let Some(contents) = &common.contents {
+            dict.set("Contents", text_string(contents));
+        }
+        if let Some(author) = &common.author {
+            dict.set("T", text_string(author));
+        }
+        if let Some(color) = &common.color {
+            dict.set("C", real_array(color));
+        }
+        if common.flags != 0 {
+            dict.set("F", common.flags as i64);
+        }
+        match self {
+            Annotation::Link { uri, destination, .. } => {
+                dict.set("Border", vec![0.into(), 0.into(), 0.into()]);
+                if let Some(uri) = uri {
+                    dict.set(
+                        "A",
+                        dictionary! {
+                            "S" => "URI",
+                            "URI" => Object::string_literal(uri.as_str()),
+                        },
+                    );
+                }
+                if let Some(destination) = destination {
+                    dict.set("Dest", destination.clone());
+                }
+            }
+            Annotation::Text { icon, open, .. } => {
+                dict.set("Name", Object::Name(icon.as_bytes().to_vec()));
+                dict.set("Open", *open);
+            }
+            Annotation::FreeText { default_appearance, .. } => {
+                dict.set("DA", Object::string_literal(default_appearance.as_str()));
+            }
+            Annotation::Highlight { quad_points, .. }
+            | Annotation::Underline { quad_points, .. }
+            | Annotation::StrikeOut { quad_points, .. }
+            | Annotation::Squiggly { quad_points, .. } => {
+                dict.set("QuadPoints", real_array(quad_points));
+            }
+            Annotation::Square {
+                interior_color,
+                border_width,
+                ..
+            }
+            | Annotation::Circle {
+                interior_color,
+                border_width,
+                ..
+            } => {
+                dict.set("BS", dictionary! { "W" => *border_width });
+                if let Some(interior_color) = interior_color {
+                    dict.set("IC", real_array(interior_color));
+                }
+            }
+            Annotation::Line { line, border_width, .. } => {
+                dict.set("L", real_array(line));
+                dict.set("BS", dictionary! { "W" => *border_width });
+            }
+            Annotation::Ink {
+                ink_list, border_width, ..
+            } => {
+                dict.set(
+                    "InkList",
+                    ink_list.iter().map(|path| real_array(path)).collect::<Vec<_>>(),
+                );
+                dict.set("BS", dictionary! { "W" => *border_width });
+            }
+            Annotation::Stamp { name, .. } => {
+                dict.set("Name", Object::Name(name.as_bytes().to_vec()));
+            }
+            Annotation::FileAttachment { file_spec, icon, .. } => {
+                dict.set("FS", file_spec.clone());
+                dict.set("Name", Object::Name(icon.as_bytes().to_vec()));
+            }
+            Annotation::Popup { parent, open, .. } => {
+                if let Some(parent) = parent {
+                    dict.set("Parent", *parent);
+                }
+                dict.set("Open", *open);
+            }
+        }
+        dict
+    }
+
+    /// Generate the normal appearance stream of the annotation.
+    ///
+    /// Returns `None` for annotations that are not drawn (links and popups) or whose look is up to
+    /// the viewer (file attachments).
+    pub fn appearance(&self) -> Option<Stream> {
+        let rect = self.rect();
+        // Appearance streams are drawn in a coordinate system with the origin at the lower-left
+        // corner of the annotation rectangle.
+        let (dx, dy) = (-rect.llx, -rect.lly);
+        let (width, height) = (rect.width(), rect.height());
+        let color = self.common().color.as_deref();
+        let mut ops = vec![];
+        let mut resources = Dictionary::new();
+
+        match self {
+            Annotation::Link { .. } | Annotation::Popup { .. } | Annotation::FileAttachment { .. } => return None,
+            Annotation::Text { .. } => {
+                ops.extend(color_operation(color.unwrap_or(&[1.0, 1.0, 0.0]), false));
+                ops.push(Operation::new("G", vec![0.into()]));
+                ops.push(rectangle(0.5, 0.5, width - 1.0, height - 1.0));
+                ops.push(Operation::new("B", vec![]));
+                for i in 1..4 {
+                    let y = height * i as f32 / 4.0;
+                    ops.push(Operation::new("m", vec![(width * 0.2).into(), y.into()]));
+                    ops.push(Operation::new("l", vec![(width * 0.8).into(), y.into()]));
+                }
+                ops.push(Operation::new("S", vec![]));
+            }
+            Annotation::FreeText {
+                common,
+                default_appearance,
+            } => {
+                let appearance = DefaultAppearance::parse(default_appearance);
+                let font_size = if appearance.font_size > 0.0 {
+                    appearance.font_size
+                } else {
+                    12.0
+                };
+                let font_name = appearance.font_name.clone().unwrap_or_else(|| "Helv".to_string());
+                resources.set("Font", dictionary! { font_name.as_str() => helvetica() });
+                ops.push(Operation::new("BT", vec![]));
+                ops.extend(appearance.color_operations);
+                ops.push(Operation::new(
+                    "Tf",
+                    vec![Object::Name(font_name.into_bytes()), font_size.into()],
+                ));
+                ops.push(Operation::new("TL", vec![(font_size * 1.2).into()]));
+                ops.push(Operation::new("Td", vec![2.into(), (height - font_size - 2.0).into()]));
+                for line in common.contents.as_deref().unwrap_or_default().lines() {
+                    ops.push(Operation::new("Tj", vec![win_ansi_string(line)]));
+                    ops.push(Operation::new("T*", vec![]));
+                }
+                ops.push(Operation::new("ET", vec![]));
+            }
+            Annotation::Highlight { quad_points, .. } => {
+                resources.set("ExtGState", dictionary! { "GS0" => dictionary! { "BM" => "Multiply" } });
+                ops.push(Operation::new("gs", vec!["GS0".into()]));
+                ops.extend(color_operation(color.unwrap_or(&[1.0, 1.0, 0.0]), false));
+                for quad in quad_points.chunks_exact(8) {
+                    // Upper-left, upper-right, lower-right, lower-left.
+                    ops.push(Operation::new("m", vec![(quad[0] + dx).into(), (quad[1] + dy).into()]));
+                    ops.push(Operation::new("l", vec![(quad[2] + dx).into(), (quad[3] + dy).into()]));
+                    ops.push(Operation::new("l", vec![(quad[6] + dx).into(), (quad[7] + dy).into()]));
+                    ops.push(Operation::new("l", vec![(quad[4] + dx).into(), (quad[5] + dy).into()]));
+                    ops.push(Operation::new("h", vec![]));
+                }
+                ops.push(Operation::new("f", vec![]));
+            }
+            Annotation::Underline { quad_points, .. }
+            | Annotation::StrikeOut { quad_points, .. }
+            | Annotation::Squiggly { quad_points, .. } => {
+                ops.extend(color_operation(color.unwrap_or(&[0.0]), true));
+                for quad in quad_points.chunks_exact(8) {
+                    let (x1, x2) = (quad[4] + dx, quad[6] + dx);
+                    let (bottom, top) = (quad[5] + dy, quad[1] + dy);
+                    let line_width = ((top - bottom) / 14.0).max(1.0);
+                    ops.push(Operation::new("w", vec![line_width.into()]));
+                    match self {
+                        Annotation::Squiggly { .. } => {
+                            let step = line_width * 2.0;
+                            let mut x = x1;
+                            let mut up = false;
+                            ops.push(Operation::new("m", vec![x.into(), (bottom + line_width).into()]));
+                            while x < x2 {
+                                x = (x + step).min(x2);
+                                up = !up;
+                                let y = if up {
+                                    bottom + 2.0 * line_width
+                                } else {
+                                    bottom + line_width
+                                };
+                                ops.push(Operation::new("l", vec![x.into(), y.into()]));
+                            }
+                        }
+                        _ => {
+                            let y = match self {
+                                Annotation::StrikeOut { .. } => (bottom + top) / 2.0,
+                                _ => bottom + line_width,
+                            };
+                            ops.push(Operation::new("m", vec![x1.into(), y.into()]));
+                            ops.push(Operation::new("l", vec![x2.into(), y.into()]));
+                        }
+                    }
+                }
+                ops.push(Operation::new("S", vec![]));
+            }
+            Annotation::Square {
+                interior_color,
+                border_width,
+                ..
+            }
+            | Annotation::Circle {
+                interior_color,
+                border_width,
+                ..
+            } => {
+                ops.push(Operation::new("w", vec![(*border_width).into()]));
+                ops.extend(color_operation(color.unwrap_or(&[0.0]), true));
+                if let Some(interior_color) = interior_color {
+                    ops.extend(color_operation(interior_color, false));
+                }
+                let inset = border_width / 2.0;
+                let area = Rect::new(inset, inset, width - inset, height - inset);
+                if let Annotation::Square { .. } = self {
+                    ops.push(rectangle(area.llx, area.lly, area.width(), area.height()));
+                } else {
+                    ellipse(&mut ops, area);
+                }
+                ops.push(Operation::new(if interior_color.is_some() { "B" } else { "S" }, vec![]));
+            }
+            Annotation::Line { line, border_width, .. } => {
+                ops.push(Operation::new("w", vec![(*border_width).into()]));
+                ops.extend(color_operation(color.unwrap_or(&[0.0]), true));
+                ops.push(Operation::new("m", vec![(line[0] + dx).into(), (line[1] + dy).into()]));
+                ops.push(Operation::new("l", vec![(line[2] + dx).into(), (line[3] + dy).into()]));
+                ops.push(Operation::new("S", vec![]));
+            }
+            Annotation::Ink {
+                ink_list, border_width, ..
+            } => {
+                ops.push(Operation::new("w", vec![(*border_width).into()]));
+                ops.push(Operation::new("J", vec![1.into()]));
+                ops.push(Operation::new("j", vec![1.into()]));
+                ops.extend(color_operation(color.unwrap_or(&[0.0]), true));
+                for path in ink_list {
+                    for (i, point) in path.chunks_exact(2).enumerate() {
+                        let operator = if i == 0 { "m" } else { "l" };
+                        ops.push(Operation::new(
+                            operator,
+                            vec![(point[0] + dx).into(), (point[1] + dy).into()],
+                        ));
+                    }
+                }
+                ops.push(Operation::new("S", vec![]));
+            }
+            Annotation::Stamp { name, .. } => {
+                let font_size = (height * 0.5).min(width / (name.len().max(1) as f32 * 0.6));
+                resources.set("Font", dictionary! { "Helv" => helvetica() });
+                ops.push(Operation::new("w", vec![2.into()]));
+                ops.extend(color_operation(color.unwrap_or(&[0.8, 0.0, 0.0]), true));
+                ops.extend(color_operation(color.unwrap_or(&[0.8, 0.0, 0.0]), false));
+                ops.push(rectangle(1.0, 1.0, width - 2.0, height - 2.0));
+                ops.push(Operation::new("S", vec![]));
+                ops.push(Operation::new("BT", vec![]));
+                ops.push(Operation::new("Tf", vec!["Helv".into(), font_size.into()]));
+                let text_width = name.len() as f32 * font_size * 0.6;
+                let x = (width - text_width) / 2.0;
+                let y = (height - font_size * 0.7) / 2.0;
+                ops.push(Operation::new("Td", vec![x.into(), y.into()]));
+                ops.push(Operation::new("Tj", vec![win_ansi_string(&name.to_uppercase())]));
+                ops.push(Operation::new("ET", vec![]));
+            }
+        }
+
+        let content = Content { operations: ops }.encode().ok()?;
+        let mut form = xobject::form(
+            vec![0.0, 0.0, width, height],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            content,
+        );
+        if !resources.is_empty() {
+            form.dict.set("Resources", resources);
+        }
+        Some(form)
+    }
+}
+
+/// Components of a default appearance string (`/DA`), e.g. `/Helv 12 Tf 0 g`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DefaultAppearance {
+    pub font_name: Option<String>,
+    /// Font size; 0 means auto size.
+    pub font_size: f32,
+    /// Color operators (`g`, `rg` or `k`) in the string.
+    pub color_operations: Vec<Operation>,
+}
+
+impl DefaultAppearance {
+    pub fn parse(da: &str) -> Self {
+        let mut appearance = DefaultAppearance::default();
+        let Ok(content) = Content::decode(da.as_bytes()) else {
+            return appearance;
+        };
+        for operation in content.operations {
+            match operation.operator.as_str() {
+                "Tf" if operation.operands.len() == 2 => {
+                    appearance.font_name = operation.operands[0]
+                        .as_name()
+                        .map(|name| String::from_utf8_lossy(name).into_owned())
+                        .ok();
+                    appearance.font_size = operation.operands[1].as_float().unwrap_or(0.0);
+                }
+                "g" | "rg" | "k" => appearance.color_operations.push(operation),
+                _ => {}
+            }
+        }
+        appearance
+    }
+}
+
+/// Standard Helvetica font dictionary with WinAnsiEncoding.
+pub(crate) fn helvetica() -> Dictionary {
+    dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    }
+}
+
+/// Whether text encoded by [`win_ansi_string`] shows correctly with a font: simple fonts with
+/// WinAnsiEncoding, and the standard Latin fonts with their built-in encoding.
+pub(crate) fn is_win_ansi_font(doc: &Document, font: &Dictionary) -> bool {
+    let simple = matches!(
+        font.get(b"Subtype").and_then(Object::as_name),
+        Ok(b"Type1" | b"MMType1" | b"TrueType")
+    );
+    let encoding = match font.get(b"Encoding") {
+        Ok(encoding) => doc.dereference(encoding).map(|(_, encoding)| encoding).ok(),
+        Err(_) => None,
+    };
+    let win_ansi = match encoding {
+        Some(Object::Name(name)) => name == b"WinAnsiEncoding",
+        Some(Object::Dictionary(encoding)) => {
+            !encoding.has(b"Differences")
+                && encoding.get(b"BaseEncoding").and_then(Object::as_name).ok() == Some(b"WinAnsiEncoding")
+        }
+        Some(_) => false,
+        None => font.get(b"BaseFont").and_then(Object::as_name).is_ok_and(|name| {
+            [b"Helvetica".as_slice(), b"Times", b"Courier"]
+                .iter()
+                .any(|family| name.starts_with(family))
+        }),
+    };
+    simple && win_ansi
+}
+
+/// Encode text for a simple font with WinAnsiEncoding, replacing characters it can't represent
+/// with `?`.
+pub(crate) fn win_ansi_string(text: &str) -> Object {
+    let mut unsupported = false;
+    let bytes = text
+        .chars()
+        .map(|ch| {
+            let mut utf16 = [0; 2];
+            let code = match *ch.encode_utf16(&mut utf16) {
+                [unit] => WIN_ANSI_ENCODING.iter().position(|&code| code == Some(unit)),
+                _ => None,
+            };
+            code.map(|code| code as u8).unwrap_or_else(|| {
+                unsupported = true;
+                b'?'
+            })
+        })
+        .collect();
+    if unsupported {
+        warn!("Characters of {text:?} can't be encoded with WinAnsiEncoding and were replaced");
+    }
+    Object::String(bytes, StringFormat::Literal)
+}
+
+/// Color operator for a color with 1, 3 or 4 components.
+pub(crate) fn color_operation(color: &[f32], stroke: bool) -> Option<Operation> {
+    let operator = match (color.len(), stroke) {
+        (1, false) => "g",
+        (1, true) => "G",
+        (3, false) => "rg",
+        (3, true) => "RG",
+        (4, false) => "k",
+        (4, true) => "K",
+        _ => return None,
+    };
+    Some(Operation::new(
+        operator,
+        color.iter().map(|c| Object::Real(*c)).collect(),
+    ))
+}
+
+fn rectangle(x: f32, y: f32, width: f32, height: f32) -> Operation {
+    Operation::new("re", vec![x.into(), y.into(), width.into(), height.into()])
+}
+
+/// Append a path approximating the ellipse inscribed in `area` with four Bézier curves.
+fn ellipse(ops: &mut Vec<Operation>, area: Rect) {
+    const KAPPA: f32 = 0.552_284_8;
+    let (cx, cy) = ((area.llx + area.urx) / 2.0, (area.lly + area.ury) / 2.0);
+    let (rx, ry) = (area.width() / 2.0, area.height() / 2.0);
+    let (ox, oy) = (rx * KAPPA, ry * KAPPA);
+    let curve = |points: [f32; 6]| Operation::new("c", points.iter().map(|p| Object::Real(*p)).collect());
+    ops.push(Operation::new("m", vec![(cx + rx).into(), cy.into()]));
+    ops.push(curve([cx + rx, cy + oy, cx + ox, cy + ry, cx, cy + ry]));
+    ops.push(curve([cx - ox, cy + ry, cx - rx, cy + oy, cx - rx, cy]));
+    ops.push(curve([cx - rx, cy - oy, cx - ox, cy - ry, cx, cy - ry]));
+    ops.push(curve([cx + ox, cy - ry, cx + rx, cy - oy, cx + rx, cy]));
+    ops.push(Operation::new("h", vec![]));
+}
+
+fn real_array(values: &[f32]) -> Object {
+    Object::Array(values.iter().map(|v| Object::Real(*v)).collect())
+}
+
+fn numbers(object: &Object) -> Option<Vec<f32>> {
+    object.as_array().ok()?.iter().map(|v| v.as_float().ok()).collect()
+}
+
+fn border_width(dict: &Dictionary) -> f32 {
+    let from_style = dict
+        .get(b"BS")
+        .and_then(Object::as_dict)
+        .and_then(|bs| bs.get(b"W"))
+        .and_then(Object::as_float);
+    let from_border = || {
+        let border = dict.get(b"Border").ok()?.as_array().ok()?;
+        border.get(2)?.as_float().ok()
+    };
+    from_style.ok().or_else(from_border).unwrap_or(1.0)
+}
+
+fn bounding_rect(quad_points: &[f32]) -> Rect {
+    let xs = quad_points.iter().step_by(2);
+    let ys = quad_points.iter().skip(1).step_by(2);
+    let (min_x, max_x) = xs.fold((f32::MAX, f32::MIN), |(min, max), x| (min.min(*x), max.max(*x)));
+    let (min_y, max_y) = ys.fold((f32::MAX, f32::MIN), |(min, max), y| (min.min(*y), max.max(*y)));
+    if min_x > max_x {
+        return Rect::new(0.0, 0.0, 0.0, 0.0);
+    }
+    Rect::new(min_x, min_y, max_x, max_y)
+}
+
+impl Document {
+    /// Get the annotations of a page that can be represented as [`Annotation`], together with
+    /// their object IDs. Other annotations, like form field widgets, are skipped.
+    pub fn get_annotations(&self, page_id: ObjectId) -> Result<Vec<(ObjectId, Annotation)>> {
+        let page = self.get_dictionary(page_id)?;
+        let annots = match page.get(b"Annots") {
+            Ok(annots) => self.dereference(annots)?.1.as_array()?,
+            Err(_) => return Ok(vec![]),
+        };
+        Ok(annots
+            .iter()
+            .filter_map(|annot| annot.as_reference().ok())
+            .filter_map(|id| {
+                let dict = self.get_dictionary(id).ok()?;
+                Some((id, Annotation::from_dict(dict).ok()?))
+            })
+            .collect())
+    }
+
+    /// Add an annotation to the page's `/Annots`, generating its appearance stream.
+    ///
+    /// A popup annotation with a parent is also registered as `/Popup` of its parent.
+    pub fn add_annotation(&mut self, page_id: ObjectId, annotation: &Annotation) -> Result<ObjectId> {
+        self.check_permissions(Permissions::ANNOTABLE)?;
+        let mut dict = annotation.to_dict();
+        dict.set("P", page_id);
+        if let Some(appearance) = self.annotation_appearance(annotation) {
+            let appearance_id = self.add_object(appearance);
+            dict.set("AP", dictionary! { "N" => appearance_id });
+        }
+        let annotation_id = self.add_object(dict);
+
+        let page = self.get_dictionary_mut(page_id)?;
+        match page.get_mut(b"Annots") {
+            Ok(Object::Array(annots)) => annots.push(annotation_id.into()),
+            Ok(Object::Reference(annots_id)) => {
+                let annots_id = *annots_id;
+                self.get_object_mut(annots_id)?
+                    .as_array_mut()?
+                    .push(annotation_id.into());
+            }
+            _ => page.set("Annots", vec![annotation_id.into()]),
+        }
+
+        if let Annotation::Popup {
+            parent: Some(parent), ..
+        } = annotation
+        {
+            self.get_dictionary_mut(*parent)?.set("Popup", annotation_id);
+        }
+        Ok(annotation_id)
+    }
+
+    /// Replace the properties of an existing annotation and regenerate its appearance stream.
+    ///
+    /// Entries not described by [`Annotation`], like `/P`, `/NM` or `/Popup`, are kept.
+    pub fn update_annotation(&mut self, annotation_id: ObjectId, annotation: &Annotation) -> Result<()> {
+        self.check_permissions(Permissions::ANNOTABLE)?;
+        let appearance_id = self
+            .annotation_appearance(annotation)
+            .map(|appearance| self.add_object(appearance));
+        let dict = self.get_dictionary_mut(annotation_id)?;
+        for key in [
+            b"Contents".as_slice(),
+            b"T",
+            b"C",
+            b"IC",
+            b"A",
+            b"Dest",
+            b"Border",
+            b"BS",
+            b"DA",
+            b"QuadPoints",
+            b"L",
+            b"InkList",
+            b"Name",
+            b"FS",
+            b"Open",
+            b"AP",
+        ] {
+            dict.remove(key);
+        }
+        for (key, value) in annotation.to_dict() {
+            dict.set(key, value);
+        }
+        if let Some(appearance_id) = appearance_id {
+            dict.set("AP", dictionary! { "N" => appearance_id });
+        }
+        Ok(())
+    }
+
+    /// Generate the appearance stream of an annotation. Free text is drawn with the `/DA` font
+    /// from the AcroForm's default resources when it is there, and with Helvetica otherwise.
+    fn annotation_appearance(&self, annotation: &Annotation) -> Option<Stream> {
+        let mut appearance = annotation.appearance()?;
+        if let Annotation::FreeText { default_appearance, .. } = annotation {
+            if let Some(name) = DefaultAppearance::parse(default_appearance).font_name {
+                // Other fonts can't show the WinAnsi encoded text; the default Helvetica is kept.
+                if let Some((font, _)) = self.form_font(&name).filter(|(_, font)| is_win_ansi_font(self, font)) {
+                    appearance
+                        .dict
+                        .set("Resources", dictionary! { "Font" => dictionary! { name => font } });
+                }
+            }
+        }
+        Some(appearance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Annotation, AnnotationCommon};
+    use crate::creator::tests::create_document;
+    use crate::{Object, Rect};
+
+    #[test]
+    fn add_and_read_annotations() {
+        let mut doc = create_document();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+
+        let highlight = Annotation::highlight(vec![100.0, 650.0, 300.0, 650.0, 100.0, 600.0, 300.0, 600.0]);
+        assert_eq!(highlight.rect(), Rect::new(100.0, 600.0, 300.0, 650.0));
+        let highlight_id = doc.add_annotation(page_id, &highlight).unwrap();
+        let note = Annotation::text(Rect::new(310.0, 630.0, 330.0, 650.0), "Check this");
+        let note_id = doc.add_annotation(page_id, &note).unwrap();
+        let popup = Annotation::Popup {
+            common: AnnotationCommon::new(Rect::new(330.0, 550.0, 480.0, 650.0)),
+            parent: Some(note_id),
+            open: true,
+        };
+        let popup_id = doc.add_annotation(page_id, &popup).unwrap();
+
+        let annotations = doc.get_annotations(page_id).unwrap();
+        assert_eq!(
+            annotations,
+            vec![(highlight_id, highlight), (note_id, note), (popup_id, popup)]
+        );
+        assert_eq!(doc.get_page_annotations(page_id).unwrap().len(), 3);
+
+        let note = doc.get_dictionary(note_id).unwrap();
+        assert_eq!(note.get(b"Popup").unwrap().as_reference().unwrap(), popup_id);
+        assert_eq!(note.get(b"F").unwrap().as_i64().unwrap(), 4);
+        assert!(note.get(b"AP").is_ok());
+        assert!(doc.get_dictionary(popup_id).unwrap().get(b"AP").is_err());
+    }
+
+    #[test]
+    fn update_annotation() {
+        let mut doc = create_document();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let ink = Annotation::Ink {
+            common: AnnotationCommon::new(Rect::new(0.0, 0.0, 100.0, 100.0)),
+            ink_list: vec![vec![10.0, 10.0, 50.0, 90.0, 90.0, 10.0]],
+            border_width: 2.0,
+        };
+        let ink_id = doc.add_annotation(page_id, &ink).unwrap();
+
+        let mut circle = Annotation::Circle {
+            common: AnnotationCommon::new(Rect::new(0.0, 0.0, 100.0, 50.0)),
+            interior_color: Some(vec![0.0, 0.0, 1.0]),
+            border_width: 1.0,
+        };
+        circle.common_mut().contents = Some("Ünïcode".to_string());
+        doc.update_annotation(ink_id, &circle).unwrap();
+        assert_eq!(doc.get_annotations(page_id).unwrap(), vec![(ink_id, circle)]);
+        assert!(doc.get_dictionary(ink_id).unwrap().get(b"InkList").is_err());
+
+        doc.remove_annot(&ink_id).unwrap();
+        assert!(doc.get_annotations(page_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn win_ansi_replaces_unsupported_characters() {
+        assert_eq!(super::win_ansi_string("a€b→😀").as_str().unwrap(), b"a\x80b??");
+    }
+
+    #[test]
+    fn free_text_uses_default_resources_font() {
+        let mut doc = create_document();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Times-Roman",
+        });
+        let type0_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => "NotoSans",
+            "Encoding" => "Identity-H",
+        });
+        let acro_form_id = doc.add_object(dictionary! {
+            "Fields" => vec![],
+            "DR" => dictionary! { "Font" => dictionary! { "TiRo" => font_id, "Noto" => type0_id } },
+        });
+        doc.catalog_mut().unwrap().set("AcroForm", acro_form_id);
+        let font = |doc: &crate::Document, annotation_id| {
+            let annotation = doc.get_dictionary(annotation_id).unwrap();
+            let appearance_id = annotation
+                .get_deref(b"AP", doc)
+                .and_then(|ap| ap.as_dict().unwrap().get(b"N"))
+                .and_then(Object::as_reference)
+                .unwrap();
+            let appearance = doc.get_object(appearance_id).and_then(Object::as_stream).unwrap();
+            let fonts = appearance
+                .dict
+                .get(b"Resources")
+                .and_then(|resources| resources.as_dict().unwrap().get(b"Font"))
+                .and_then(Object::as_dict)
+                .unwrap();
+            fonts
+                .iter()
+                .next()
+                .map(|(name, font)| (name.clone(), font.clone()))
+                .unwrap()
+        };
+
+        let mut free_text = Annotation::FreeText {
+            common: AnnotationCommon::new(Rect::new(0.0, 0.0, 100.0, 50.0)),
+            default_appearance: "/Helv 10 Tf 0 g".to_string(),
+        };
+        let free_text_id = doc.add_annotation(page_id, &free_text).unwrap();
+        assert_eq!(font(&doc, free_text_id).0, b"Helv");
+
+        if let Annotation::FreeText { default_appearance, .. } = &mut free_text {
+            *default_appearance = "/TiRo 10 Tf 0 g".to_string();
+        }
+        doc.update_annotation(free_text_id, &free_text).unwrap();
+        assert_eq!(font(&doc, free_text_id), (b"TiRo".to_vec(), Object::Reference(font_id)));
+
+        // The WinAnsi encoded text can't be shown with a composite font.
+        if let Annotation::FreeText { default_appearance, .. } = &mut free_text {
+            *default_appearance = "/Noto 10 Tf 0 g".to_string();
+        }
+        doc.update_annotation(free_text_id, &free_text).unwrap();
+        let (name, font) = font(&doc, free_text_id);
+        assert_eq!(name, b"Noto");
+        assert_eq!(
+            font.as_dict().unwrap().get(b"BaseFont").unwrap().as_name().unwrap(),
+            b"Helvetica"
+        );
+    }
+}
@@ -1,3 +1,8 @@
+mod rectangle;
+mod tree;
+pub use rectangle::Rect;
+pub use tree::{NameTree, NumberTree, Tree, TreeKey};
+
 use crate::{
     encodings::{self, bytes_to_string},
     Error, Object, Result, StringFormat,
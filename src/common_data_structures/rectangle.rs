@@ -0,0 +1,123 @@
+use crate::{Error, Object, Result};
+
+/// A rectangle in default user space units.
+///
+/// PDF rectangles are written as arrays of four numbers giving the coordinates of a pair of
+/// diagonally opposite corners. They are usually, but not always, given as lower-left and
+/// upper-right corner; `Rect` always stores the normalized form.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub llx: f32,
+    pub lly: f32,
+    pub urx: f32,
+    pub ury: f32,
+}
+
+impl Rect {
+    /// Create a rectangle from two diagonally opposite corners.
+    pub fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Rect {
+            llx: x1.min(x2),
+            lly: y1.min(y2),
+            urx: x1.max(x2),
+            ury: y1.max(y2),
+        }
+    }
+
+    /// Create a rectangle with its lower-left corner at the origin.
+    pub fn from_size(width: f32, height: f32) -> Self {
+        Rect::new(0.0, 0.0, width, height)
+    }
+
+    /// Parse a rectangle from a PDF array of four numbers.
+    pub fn from_object(object: &Object) -> Result<Self> {
+        let array = object.as_array()?;
+        if array.len() != 4 {
+            return Err(Error::InvalidRectangle(array.len()));
+        }
+        let mut values = [0.0; 4];
+        for (value, item) in values.iter_mut().zip(array) {
+            *value = item.as_float()?;
+        }
+        Ok(Rect::new(values[0], values[1], values[2], values[3]))
+    }
+
+    pub fn width(&self) -> f32 {
+        self.urx - self.llx
+    }
+
+    pub fn height(&self) -> f32 {
+        self.ury - self.lly
+    }
+
+    /// Intersection of two rectangles, or `None` if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let rect = Rect {
+            llx: self.llx.max(other.llx),
+            lly: self.lly.max(other.lly),
+            urx: self.urx.min(other.urx),
+            ury: self.ury.min(other.ury),
+        };
+        (rect.llx < rect.urx && rect.lly < rect.ury).then_some(rect)
+    }
+
+    /// Whether `other` lies completely inside this rectangle.
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.llx <= other.llx && self.lly <= other.lly && self.urx >= other.urx && self.ury >= other.ury
+    }
+
+    /// Convert into a PDF array `[llx lly urx ury]`.
+    pub fn to_object(&self) -> Object {
+        Object::Array(vec![
+            Object::Real(self.llx),
+            Object::Real(self.lly),
+            Object::Real(self.urx),
+            Object::Real(self.ury),
+        ])
+    }
+}
+
+impl From<Rect> for Object {
+    fn from(rect: Rect) -> Self {
+        rect.to_object()
+    }
+}
+
+impl TryFrom<&Object> for Rect {
+    type Error = Error;
+
+    fn try_from(object: &Object) -> Result<Self> {
+        Rect::from_object(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rect;
+    use crate::Object;
+
+    #[test]
+    fn parse_reversed_rectangle() {
+        let object = Object::Array(vec![612.into(), 792.into(), 0.into(), 0.5.into()]);
+        let rect = Rect::from_object(&object).unwrap();
+        assert_eq!(rect, Rect::new(0.0, 0.5, 612.0, 792.0));
+        assert_eq!(rect.width(), 612.0);
+        assert_eq!(rect.height(), 791.5);
+    }
+
+    #[test]
+    fn intersect_rectangles() {
+        let media = Rect::new(0.0, 0.0, 612.0, 792.0);
+        let crop = Rect::new(-10.0, 20.0, 300.0, 900.0);
+        assert_eq!(media.intersection(&crop), Some(Rect::new(0.0, 20.0, 300.0, 792.0)));
+        assert_eq!(media.intersection(&Rect::new(700.0, 0.0, 800.0, 10.0)), None);
+        assert!(media.contains(&Rect::new(10.0, 10.0, 20.0, 20.0)));
+        assert!(!media.contains(&crop));
+    }
+
+    #[test]
+    fn reject_short_array() {
+        let object = Object::Array(vec![0.into(), 0.into(), 10.into()]);
+        assert!(Rect::from_object(&object).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, HashSet};
+
+use log::warn;
+
+use crate::{Dictionary, Document, Object, ObjectId, Result, StringFormat};
+
+/// Maximum number of entries in a leaf, and of kids in an intermediate node, when writing a tree.
+const NODE_SIZE: usize = 32;
+
+/// Key type of a [`Tree`]: byte strings for name trees, integers for number trees.
+pub trait TreeKey: Ord + Clone {
+    /// Dictionary key holding the key-value pairs of a leaf (`Names` or `Nums`).
+    const ENTRIES_KEY: &'static str;
+
+    fn from_object(object: &Object) -> Result<Self>;
+
+    fn to_object(&self) -> Object;
+}
+
+impl TreeKey for Vec<u8> {
+    const ENTRIES_KEY: &'static str = "Names";
+
+    fn from_object(object: &Object) -> Result<Self> {
+        object.as_str().map(<[u8]>::to_vec)
+    }
+
+    fn to_object(&self) -> Object {
+        Object::String(self.clone(), StringFormat::Literal)
+    }
+}
+
+impl TreeKey for i64 {
+    const ENTRIES_KEY: &'static str = "Nums";
+
+    fn from_object(object: &Object) -> Result<Self> {
+        object.as_i64()
+    }
+
+    fn to_object(&self) -> Object {
+        Object::Integer(*self)
+    }
+}
+
+/// A name tree or number tree (ISO 32000-1 §7.9.6 and §7.9.7), held in memory as an ordered map.
+///
+/// Values are kept as they appear in the tree, so indirect values stay references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tree<K: TreeKey> {
+    entries: BTreeMap<K, Object>,
+}
+
+/// Tree keyed by byte strings, e.g. `/Dests` or `/EmbeddedFiles` in the names dictionary.
+pub type NameTree = Tree<Vec<u8>>;
+
+/// Tree keyed by integers, e.g. `/PageLabels` or the structure tree's `/ParentTree`.
+pub type NumberTree = Tree<i64>;
+
+impl<K: TreeKey> Default for Tree<K> {
+    fn default() -> Self {
+        Tree {
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: TreeKey> Tree<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read all entries of the tree with the given root node. Nodes and entries that can't be read
+    /// are skipped with a warning.
+    pub fn load(doc: &Document, root: &Dictionary) -> Result<Self> {
+        let mut tree = Tree::new();
+        Self::walk(doc, root, |key, value| {
+            tree.entries.insert(key, value.clone());
+        });
+        Ok(tree)
+    }
+
+    /// Visit the entries of the tree with the given root node in document order. Nodes and entries
+    /// that can't be read are skipped with a warning.
+    pub(crate) fn walk<'a>(doc: &'a Document, root: &'a Dictionary, mut visit: impl FnMut(K, &'a Object)) {
+        Self::walk_node(doc, root, &mut visit, &mut HashSet::new());
+    }
+
+    fn walk_node<'a>(
+        doc: &'a Document, node: &'a Dictionary, visit: &mut impl FnMut(K, &'a Object), seen: &mut HashSet<ObjectId>,
+    ) {
+        for (kid_id, kid) in Self::kids(doc, node) {
+            if seen.insert(kid_id) {
+                Self::walk_node(doc, kid, visit, seen);
+            } else {
+                warn!("Tree node {kid_id:?} is referenced more than once");
+            }
+        }
+        for (key, value) in Self::entries(doc, node) {
+            visit(key, value);
+        }
+    }
+
+    /// The kids of a node, skipping invalid ones.
+    fn kids<'a>(doc: &'a Document, node: &'a Dictionary) -> Vec<(ObjectId, &'a Dictionary)> {
+        let Ok(kids) = node.get(b"Kids") else {
+            return vec![];
+        };
+        let Ok(kids) = doc.dereference(kids).and_then(|(_, kids)| kids.as_array()) else {
+            warn!("Skipping invalid kids of a tree node");
+            return vec![];
+        };
+        kids.iter()
+            .filter_map(
+                |kid| match kid.as_reference().and_then(|id| Ok((id, doc.get_dictionary(id)?))) {
+                    Ok(kid) => Some(kid),
+                    Err(err) => {
+                        warn!("Skipping invalid tree node {kid:?}: {err}");
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+
+    /// The key-value pairs of a node, skipping invalid keys.
+    fn entries<'a>(doc: &'a Document, node: &'a Dictionary) -> Vec<(K, &'a Object)> {
+        let Ok(entries) = node.get(K::ENTRIES_KEY.as_bytes()) else {
+            return vec![];
+        };
+        let Ok(entries) = doc.dereference(entries).and_then(|(_, entries)| entries.as_array()) else {
+            warn!("Skipping invalid {} of a tree node", K::ENTRIES_KEY);
+            return vec![];
+        };
+        entries
+            .chunks_exact(2)
+            .filter_map(|pair| match K::from_object(&pair[0]) {
+                Ok(key) => Some((key, &pair[1])),
+                Err(_) => {
+                    warn!("Skipping tree entry with invalid key {:?}", pair[0]);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Look up a single key in the tree with the given root node, skipping the kids whose
+    /// `Limits` exclude the key.
+    pub fn lookup<'a>(doc: &'a Document, root: &'a Dictionary, key: &K) -> Result<Option<&'a Object>> {
+        Ok(Self::lookup_node(doc, root, key, &mut HashSet::new()))
+    }
+
+    fn lookup_node<'a>(
+        doc: &'a Document, node: &'a Dictionary, key: &K, seen: &mut HashSet<ObjectId>,
+    ) -> Option<&'a Object> {
+        if let Some((_, value)) = Self::entries(doc, node).into_iter().find(|(entry, _)| entry == key) {
+            return Some(value);
+        }
+        for (kid_id, kid) in Self::kids(doc, node) {
+            let excludes_key = Self::limits(doc, kid).is_some_and(|(low, high)| *key < low || high < *key);
+            if excludes_key || !seen.insert(kid_id) {
+                continue;
+            }
+            if let Some(value) = Self::lookup_node(doc, kid, key, seen) {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn limits(doc: &Document, node: &Dictionary) -> Option<(K, K)> {
+        let limits = doc.dereference(node.get(b"Limits").ok()?).ok()?.1.as_array().ok()?;
+        match limits.as_slice() {
+            [low, high] => Some((K::from_object(low).ok()?, K::from_object(high).ok()?)),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&Object> {
+        self.entries.get(key)
+    }
+
+    /// Insert an entry, returning the previous value for the key.
+    pub fn insert(&mut self, key: K, value: Object) -> Option<Object> {
+        self.entries.insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<Object> {
+        self.entries.remove(key)
+    }
+
+    /// Iterate over the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &Object)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the tree into the document as a balanced tree and return the ID of its root node.
+    ///
+    /// Small trees are written as a single root node holding all entries.
+    pub fn write(&self, doc: &mut Document) -> Result<ObjectId> {
+        let entries: Vec<(&K, &Object)> = self.entries.iter().collect();
+        if entries.len() <= NODE_SIZE {
+            return Ok(doc.add_object(Self::leaf(&entries)));
+        }
+
+        // Each level holds the node IDs together with the lowest and highest key they cover.
+        let mut level: Vec<(ObjectId, &K, &K)> = entries
+            .chunks(NODE_SIZE)
+            .map(|chunk| {
+                let (low, high) = (chunk[0].0, chunk[chunk.len() - 1].0);
+                let mut leaf = Self::leaf(chunk);
+                leaf.set("Limits", vec![low.to_object(), high.to_object()]);
+                (doc.add_object(leaf), low, high)
+            })
+            .collect();
+        while level.len() > NODE_SIZE {
+            level = level
+                .chunks(NODE_SIZE)
+                .map(|chunk| {
+                    let (low, high) = (chunk[0].1, chunk[chunk.len() - 1].2);
+                    let node = dictionary! {
+                        "Kids" => chunk.iter().map(|(id, _, _)| Object::Reference(*id)).collect::<Vec<_>>(),
+                        "Limits" => vec![low.to_object(), high.to_object()],
+                    };
+                    (doc.add_object(node), low, high)
+                })
+                .collect();
+        }
+        Ok(doc.add_object(dictionary! {
+            "Kids" => level.iter().map(|(id, _, _)| Object::Reference(*id)).collect::<Vec<_>>(),
+        }))
+    }
+
+    fn leaf(entries: &[(&K, &Object)]) -> Dictionary {
+        let mut array = Vec::with_capacity(entries.len() * 2);
+        for (key, value) in entries {
+            array.push(key.to_object());
+            array.push((*value).clone());
+        }
+        dictionary! { K::ENTRIES_KEY => array }
+    }
+}
+
+impl<K: TreeKey> FromIterator<(K, Object)> for Tree<K> {
+    fn from_iter<I: IntoIterator<Item = (K, Object)>>(iter: I) -> Self {
+        Tree {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NameTree, NumberTree};
+    use crate::{Document, Object};
+
+    #[test]
+    fn write_and_read_balanced_tree() {
+        let mut doc = Document::with_version("1.7");
+        let tree: NumberTree = (0..2000).map(|i| (i * 2, Object::Integer(i))).collect();
+        let root_id = tree.write(&mut doc).unwrap();
+
+        let root = doc.get_dictionary(root_id).unwrap();
+        assert!(root.get(b"Limits").is_err());
+        assert_eq!(NumberTree::load(&doc, root).unwrap(), tree);
+        assert_eq!(
+            NumberTree::lookup(&doc, root, &1998).unwrap(),
+            Some(&Object::Integer(999))
+        );
+        assert_eq!(NumberTree::lookup(&doc, root, &1999).unwrap(), None);
+        assert_eq!(NumberTree::lookup(&doc, root, &5000).unwrap(), None);
+    }
+
+    #[test]
+    fn skip_invalid_nodes() {
+        let mut doc = Document::with_version("1.7");
+        let first_id = doc.add_object(dictionary! { "Nums" => vec![1.into(), "one".into()] });
+        let second_id = doc.add_object(dictionary! {
+            "Nums" => vec!["bad".into(), Object::Null, 2.into(), "two".into()],
+            "Limits" => vec![2.into(), 2.into()],
+        });
+        let broken_id = doc.add_object(Object::Integer(0));
+        let root = dictionary! {
+            "Kids" => vec![first_id.into(), broken_id.into(), (99, 0).into(), Object::Null, second_id.into()],
+        };
+
+        let tree = NumberTree::load(&doc, &root).unwrap();
+        let keys: Vec<_> = tree.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, [1, 2]);
+        // The first kid has no limits but doesn't hold the key, the search goes on.
+        assert_eq!(
+            NumberTree::lookup(&doc, &root, &2).unwrap(),
+            Some(&Object::Name(b"two".to_vec()))
+        );
+        assert_eq!(NumberTree::lookup(&doc, &root, &3).unwrap(), None);
+    }
+
+    #[test]
+    fn name_tree_order() {
+        let mut tree = NameTree::new();
+        tree.insert(b"b".to_vec(), Object::Integer(2));
+        tree.insert(b"a".to_vec(), Object::Integer(1));
+        assert_eq!(tree.insert(b"b".to_vec(), Object::Integer(3)), Some(Object::Integer(2)));
+        let keys: Vec<_> = tree.iter().map(|(key, _)| key.as_slice()).collect();
+        assert_eq!(keys, [b"a", b"b"]);
+
+        let mut doc = Document::with_version("1.7");
+        let root_id = tree.write(&mut doc).unwrap();
+        let root = doc.get_dictionary(root_id).unwrap();
+        assert_eq!(
+            NameTree::lookup(&doc, root, &b"b".to_vec()).unwrap(),
+            Some(&Object::Integer(3))
+        );
+    }
+}
@@ -42,8 +42,15 @@ pub fn remove_object(&mut self, object_id: &ObjectId) -> Result<()> {
     /// annotation object itself is removed.
     pub fn remove_annot(&mut self, object_id: &ObjectId) -> Result<()> {
         for (_, page_id) in self.get_pages() {
-            let page = self.get_object_mut(page_id)?.as_dict_mut()?;
-            let annots = page.get_mut(b"Annots")?.as_array_mut()?;
+            let annots_id = match self.get_dictionary(page_id)?.get(b"Annots") {
+                Ok(Object::Reference(id)) => Some(*id),
+                Ok(_) => None,
+                Err(_) => continue,
+            };
+            let annots = match annots_id {
+                Some(id) => self.get_object_mut(id)?.as_array_mut()?,
+                None => self.get_dictionary_mut(page_id)?.get_mut(b"Annots")?.as_array_mut()?,
+            };
 
             annots.retain(|object| {
                 if let Ok(id) = object.as_reference() {
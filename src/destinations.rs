@@ -1,5 +1,6 @@
-use super::{Dictionary, Document, Object, Result};
+use super::{Dictionary, Document, NameTree, Object, Result, StringFormat};
 use indexmap::IndexMap;
+use log::warn;
 #[derive(Debug, Clone)]
 pub struct Destination(Dictionary);
 
@@ -30,45 +31,51 @@ pub fn page(&self) -> Result<&Object> {
 }
 
 impl Document {
+    /// Collect the destinations of a name tree in document order. Nodes and entries that can't
+    /// be read are skipped with a warning.
     pub fn get_named_destinations(
         &self, tree: &Dictionary, named_destinations: &mut IndexMap<Vec<u8>, Destination>,
     ) -> Result<()> {
-        if let Ok(kids) = tree.get(b"Kids") {
-            for kid in kids.as_array()? {
-                if let Ok(kid) = kid.as_reference().and_then(move |id| self.get_dictionary(id)) {
-                    self.get_named_destinations(kid, named_destinations)?;
+        NameTree::walk(self, tree, |key, value| {
+            let dest = match self.dereference(value) {
+                Ok((_, Object::Dictionary(dict))) => dict
+                    .get(b"D")
+                    .and_then(|dest| self.dereference(dest))
+                    .and_then(|(_, dest)| dest.as_array()),
+                Ok((_, dest)) => dest.as_array(),
+                Err(err) => Err(err),
+            };
+            match dest {
+                Ok(dest) if dest.len() >= 2 => {
+                    let title = Object::String(key.clone(), StringFormat::Literal);
+                    named_destinations.insert(key, Destination::new(title, dest[0].clone(), dest[1].clone()));
                 }
+                _ => warn!("Skipping invalid named destination {:?}", String::from_utf8_lossy(&key)),
             }
-        }
-        if let Ok(names) = tree.get(b"Names") {
-            let mut names = names.as_array()?.iter();
-            loop {
-                let key = names.next();
-                if key.is_none() {
-                    break;
-                }
-                let val = names.next();
-                if val.is_none() {
-                    break;
-                }
-                if let Ok(obj_ref) = val.unwrap().as_reference() {
-                    if let Ok(dict) = self.get_dictionary(obj_ref) {
-                        let val = dict.get(b"D").as_ref().unwrap().as_array()?;
-                        let dest = Destination::new(key.unwrap().clone(), val[0].clone(), val[1].clone());
-                        named_destinations.insert(key.unwrap().as_str()?.to_vec(), dest);
-                    } else if let Ok(Object::Array(val)) = self.get_object(obj_ref) {
-                        let dest = Destination::new(key.unwrap().clone(), val[0].clone(), val[1].clone());
-                        named_destinations.insert(key.unwrap().as_str()?.to_vec(), dest);
-                    }
-                } else if let Ok(dict) = val.unwrap().as_dict() {
-                    let val = dict.get(b"D").as_ref().unwrap().as_array()?;
-                    let dest = Destination::new(key.unwrap().clone(), val[0].clone(), val[1].clone());
-                    named_destinations.insert(key.unwrap().as_str()?.to_vec(), dest);
-                } else {
-                    // TODO: Log error: Unpexpected node type
-                }
-            }
-        }
+        });
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Document, Object};
+    use indexmap::IndexMap;
+
+    #[test]
+    fn named_destinations_in_document_order() {
+        let mut doc = Document::with_version("1.7");
+        let page = Object::Reference((100, 0));
+        let leaf = |key: &str| vec![Object::string_literal(key), vec![page.clone(), "Fit".into()].into()];
+        let second_id = doc.add_object(dictionary! { "Names" => leaf("a") });
+        let first_id = doc.add_object(dictionary! { "Names" => leaf("b") });
+        let broken_id = doc.add_object(Object::Integer(1));
+        let root = dictionary! {
+            "Kids" => vec![first_id.into(), broken_id.into(), (99, 0).into(), second_id.into()],
+        };
+
+        let mut destinations = IndexMap::new();
+        doc.get_named_destinations(&root, &mut destinations).unwrap();
+        assert_eq!(destinations.keys().collect::<Vec<_>>(), [b"b", b"a"]);
+    }
+}
@@ -1,10 +1,10 @@
 use super::encodings::Encoding;
 use super::{Bookmark, Dictionary, Object, ObjectId};
 use crate::encryption::crypt_filters::*;
-use crate::encryption::{self, EncryptionState, PasswordAlgorithm};
+use crate::encryption::{self, EncryptionState, PasswordAlgorithm, Permissions, RecipientDecryptor};
 use crate::xobject::PdfImage;
 use crate::xref::{Xref, XrefType};
-use crate::{Error, ObjectStream, Result, Stream};
+use crate::{Error, ObjectStream, Result, Revision, Stream, StringFormat};
 use log::debug;
 use std::cmp::max;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -53,9 +53,18 @@ pub struct Document {
     /// Default value is `0`.
     pub xref_start: usize,
 
+    /// The revisions of the file, i.e. the original file and its incremental updates, oldest first.
+    /// This value is only set during reading.
+    pub(crate) revisions: Vec<Revision>,
+
     /// The encryption state stores the parameters that were used to decrypt this document if the
     /// document has been decrypted.
     pub encryption_state: Option<EncryptionState>,
+
+    /// Whether operations that the permissions of a decrypted document don't allow are refused
+    /// with [`Error::PermissionDenied`], unless it was decrypted with the owner password.
+    /// Default value is `false`.
+    pub enforce_permissions: bool,
 }
 
 impl Document {
@@ -72,7 +81,9 @@ pub fn new() -> Self {
             bookmarks: Vec::new(),
             bookmark_table: HashMap::new(),
             xref_start: 0,
+            revisions: Vec::new(),
             encryption_state: None,
+            enforce_permissions: false,
         }
     }
 
@@ -91,7 +102,9 @@ pub fn new_from_prev(prev: &Document) -> Self {
             bookmarks: Vec::new(),
             bookmark_table: HashMap::new(),
             xref_start: 0,
+            revisions: Vec::new(),
             encryption_state: None,
+            enforce_permissions: false,
         }
     }
 
@@ -235,10 +248,8 @@ fn traverse_object<A: Fn(&mut Object)>(object: &mut Object, action: &A, refs: &m
                 Object::Array(array) => traverse_array(array, action, refs),
                 Object::Dictionary(dict) => traverse_dictionary(dict, action, refs),
                 Object::Stream(stream) => traverse_dictionary(&mut stream.dict, action, refs),
-                Object::Reference(id) => {
-                    if !refs.contains(id) {
-                        refs.push(*id);
-                    }
+                Object::Reference(id) if !refs.contains(id) => {
+                    refs.push(*id);
                 }
                 _ => {}
             }
@@ -424,6 +435,12 @@ pub fn encrypt(&mut self, state: &EncryptionState) -> Result<()> {
 
         let object_id = self.add_object(encrypted);
         self.trailer.set(b"Encrypt", Object::Reference(object_id));
+        if let Some(file_id) = state.file_id() {
+            if crate::writer::file_id(&self.trailer).is_none() {
+                let file_id = Object::String(file_id.to_vec(), StringFormat::Hexadecimal);
+                self.trailer.set("ID", vec![file_id.clone(), file_id]);
+            }
+        }
         self.encryption_state = None;
 
         Ok(())
@@ -435,9 +452,17 @@ pub fn decrypt(&mut self, password: &str) -> Result<()> {
             return Err(Error::NotEncrypted);
         }
 
-        let algorithm = PasswordAlgorithm::try_from(&*self)?;
+        let state = self.password_encryption_state(password)?;
+        self.decrypt_with_state(state)
+    }
+
+    /// Authenticate `password` and decode the encryption state of the document with it.
+    pub(crate) fn password_encryption_state(&self, password: &str) -> Result<EncryptionState> {
+        let algorithm = PasswordAlgorithm::try_from(self)?;
         let password = algorithm.sanitize_password(password)?;
-        self.decrypt_raw(&password)
+        self.authenticate_raw_password(&password)?;
+
+        EncryptionState::decode(self, password)
     }
 
     /// Replaces all encrypted Strings and Streams with their decrypted contents with the password
@@ -452,11 +477,26 @@ pub fn decrypt_raw<P>(&mut self, password: P) -> Result<()>
 
         self.authenticate_raw_password(&password)?;
 
+        let state = EncryptionState::decode(&*self, password)?;
+        self.decrypt_with_state(state)
+    }
+
+    /// Replaces all encrypted Strings and Streams with their decrypted contents, for a document
+    /// encrypted with the public-key security handler. The `decryptor` holds the private key of
+    /// one of the recipients.
+    pub fn decrypt_for_recipient(&mut self, decryptor: &dyn RecipientDecryptor) -> Result<()> {
+        if !self.is_encrypted() {
+            return Err(Error::NotEncrypted);
+        }
+
+        let state = EncryptionState::decode_for_recipient(&*self, decryptor)?;
+        self.decrypt_with_state(state)
+    }
+
+    fn decrypt_with_state(&mut self, state: EncryptionState) -> Result<()> {
         // Find the ID of the encryption dict; we'll want to skip it when decrypting
         let encryption_obj_id = self.trailer.get(b"Encrypt").and_then(Object::as_reference)?;
 
-        let state = EncryptionState::decode(&*self, password)?;
-
         for (&id, obj) in self.objects.iter_mut() {
             // The encryption dictionary is not encrypted, leave it alone
             if id == encryption_obj_id {
@@ -500,6 +540,23 @@ pub fn decrypt_raw<P>(&mut self, password: P) -> Result<()>
         Ok(())
     }
 
+    /// Check that the permissions of the document allow an operation that requires `required`.
+    ///
+    /// Always succeeds unless [`Document::enforce_permissions`] is set and the document was
+    /// decrypted with the user password.
+    pub fn check_permissions(&self, required: Permissions) -> Result<()> {
+        match &self.encryption_state {
+            Some(state) if self.enforce_permissions && !state.owner_authenticated() => {
+                if state.permissions().contains(required) {
+                    Ok(())
+                } else {
+                    Err(Error::PermissionDenied(required))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Return the PDF document catalog, which is the root of the document's object graph.
     pub fn catalog(&self) -> Result<&Dictionary> {
         self.trailer
@@ -824,14 +881,12 @@ fn next(&mut self) -> Option<Self::Item> {
                             b"Page" => {
                                 return Some(kid_id);
                             }
-                            b"Pages" => {
-                                if self.stack.len() < Self::PAGE_TREE_DEPTH_LIMIT {
-                                    let kids = self.kids.unwrap();
-                                    if !kids.is_empty() {
-                                        self.stack.push(kids);
-                                    }
-                                    self.kids = Self::kids(self.doc, kid_id);
+                            b"Pages" if self.stack.len() < Self::PAGE_TREE_DEPTH_LIMIT => {
+                                let kids = self.kids.unwrap();
+                                if !kids.is_empty() {
+                                    self.stack.push(kids);
                                 }
+                                self.kids = Self::kids(self.doc, kid_id);
                             }
                             _ => {}
                         }
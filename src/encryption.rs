@@ -1,16 +1,19 @@
 mod algorithms;
 pub mod crypt_filters;
 mod pkcs5;
+mod public_key;
 mod rc4;
 
 use bitflags::bitflags;
-use crate::{Dictionary, Document, Error, Object, ObjectId};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Stream};
 use crypt_filters::*;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 use thiserror::Error;
 
 pub use algorithms::PasswordAlgorithm;
+pub use public_key::{Recipient, RecipientDecryptor, RecipientEncryptor};
+use public_key::PublicKeyState;
 
 #[derive(Error, Debug)]
 pub enum DecryptionError {
@@ -49,6 +52,8 @@ pub enum DecryptionError {
     NotDecryptable,
     #[error("the supplied password is incorrect")]
     IncorrectPassword,
+    #[error("none of the recipients could be decrypted")]
+    NoMatchingRecipient,
 
     #[error("the document uses an encryption scheme that is not implemented in lopdf")]
     UnsupportedEncryption,
@@ -208,12 +213,26 @@ pub struct EncryptionState {
     pub(crate) file_encryption_key: Vec<u8>,
     pub(crate) stream_filter: Vec<u8>,
     pub(crate) string_filter: Vec<u8>,
+    /// Crypt filter for embedded file streams; the stream filter is used if this is empty.
+    pub(crate) embedded_file_filter: Vec<u8>,
     pub(crate) owner_value: Vec<u8>,
     pub(crate) owner_encrypted: Vec<u8>,
     pub(crate) user_value: Vec<u8>,
     pub(crate) user_encrypted: Vec<u8>,
     pub(crate) permissions: Permissions,
     pub(crate) permission_encrypted: Vec<u8>,
+    /// Whether the document was decrypted with the owner password, which grants all permissions.
+    pub(crate) owner_authenticated: bool,
+    pub(crate) public_key: Option<PublicKeyState>,
+    /// File identifier generated for a document without a trailer `/ID`; it is added to the
+    /// trailer when the document is encrypted.
+    pub(crate) file_id: Option<Vec<u8>>,
+}
+
+/// A file identifier for documents whose trailer has no `/ID` yet, which the R2-R4 algorithms
+/// depend on.
+fn generated_file_id(document: &Document) -> Option<Vec<u8>> {
+    crate::writer::file_id(&document.trailer).is_none().then(|| document.generate_file_id())
 }
 
 impl TryFrom<EncryptionVersion<'_>> for EncryptionState {
@@ -235,6 +254,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     version: 1,
                     revision: 2,
                     permissions,
+                    file_id: generated_file_id(document),
                     ..Default::default()
                 };
 
@@ -265,6 +285,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     owner_value: algorithm.owner_value,
                     user_value: algorithm.user_value,
                     permissions: algorithm.permissions,
+                    file_id: algorithm.file_id,
                     ..Default::default()
                 })
             }
@@ -283,6 +304,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     version: 2,
                     revision: 3,
                     permissions,
+                    file_id: generated_file_id(document),
                     ..Default::default()
                 };
 
@@ -313,6 +335,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     owner_value: algorithm.owner_value,
                     user_value: algorithm.user_value,
                     permissions,
+                    file_id: algorithm.file_id,
                     ..Default::default()
                 })
             }
@@ -334,6 +357,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     version: 4,
                     revision: 4,
                     permissions,
+                    file_id: generated_file_id(document),
                     ..Default::default()
                 };
 
@@ -367,6 +391,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     owner_value: algorithm.owner_value,
                     user_value: algorithm.user_value,
                     permissions: algorithm.permissions,
+                    file_id: algorithm.file_id,
                     ..Default::default()
                 })
             }
@@ -433,6 +458,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     user_encrypted: algorithm.user_encrypted,
                     permissions: algorithm.permissions,
                     permission_encrypted: algorithm.permission_encrypted,
+                    ..Default::default()
                 })
             }
             EncryptionVersion::V5 {
@@ -497,6 +523,7 @@ fn try_from(version: EncryptionVersion) -> Result<EncryptionState, Self::Error>
                     user_encrypted: algorithm.user_encrypted,
                     permissions: algorithm.permissions,
                     permission_encrypted: algorithm.permission_encrypted,
+                    ..Default::default()
                 })
             }
         }
@@ -536,6 +563,22 @@ pub fn default_string_filter(&self) -> &[u8] {
         self.string_filter.as_ref()
     }
 
+    pub fn default_embedded_file_filter(&self) -> &[u8] {
+        if self.embedded_file_filter.is_empty() {
+            self.default_stream_filter()
+        } else {
+            self.embedded_file_filter.as_ref()
+        }
+    }
+
+    /// Use the crypt filter `name` for embedded file streams (`/EFF`) instead of the default
+    /// stream filter, e.g. to only encrypt the attachments of a document by setting the stream and
+    /// string filters to `Identity`. Only meaningful with V 4 and 5.
+    pub fn with_embedded_file_filter(mut self, name: impl Into<Vec<u8>>) -> Self {
+        self.embedded_file_filter = name.into();
+        self
+    }
+
     pub fn owner_value(&self) -> &[u8] {
         self.owner_value.as_ref()
     }
@@ -560,6 +603,16 @@ pub fn permission_encrypted(&self) -> &[u8] {
         self.permission_encrypted.as_ref()
     }
 
+    /// Whether the document was decrypted with the owner password rather than the user password.
+    pub fn owner_authenticated(&self) -> bool {
+        self.owner_authenticated
+    }
+
+    /// The file identifier generated for a document that didn't have one.
+    pub fn file_id(&self) -> Option<&[u8]> {
+        self.file_id.as_deref()
+    }
+
     pub fn decode<P>(
         document: &Document,
         password: P,
@@ -585,7 +638,17 @@ pub fn decode<P>(
         }
 
         let algorithm = PasswordAlgorithm::try_from(document)?;
-        let file_encryption_key = algorithm.compute_file_encryption_key(document, password)?;
+        let password = password.as_ref();
+        let owner_authenticated = algorithm.authenticate_owner_password(document, password).is_ok();
+
+        // Before revision 5, the file encryption key is computed from the user password, which
+        // has to be retrieved first when the owner password is given.
+        let file_encryption_key = if owner_authenticated && algorithm.revision <= 4 {
+            let user_password = algorithm.compute_user_password_r4(password)?;
+            algorithm.compute_file_encryption_key(document, user_password)?
+        } else {
+            algorithm.compute_file_encryption_key(document, password)?
+        };
 
         let mut crypt_filters = document.get_crypt_filters();
 
@@ -607,10 +670,11 @@ pub fn decode<P>(
             user_encrypted: algorithm.user_encrypted,
             permissions: algorithm.permissions,
             permission_encrypted: algorithm.permission_encrypted,
+            owner_authenticated,
             ..Default::default()
         };
 
-        // StmF and StrF are meaningful only when the value of V is 4 (PDF 1.5) or 5 (PDF 2.0).
+        // StmF, StrF and EFF are meaningful only when the value of V is 4 (PDF 1.5) or 5 (PDF 2.0).
         if algorithm.version == 4 || algorithm.version == 5 {
             if let Ok(stream_filter) = document.get_encrypted()
                 .and_then(|dict| dict.get(b"StmF"))
@@ -623,12 +687,22 @@ pub fn decode<P>(
                 .and_then(|object| object.as_name()) {
                 state.string_filter = string_filter.to_vec();
             }
+
+            if let Ok(embedded_file_filter) = document.get_encrypted()
+                .and_then(|dict| dict.get(b"EFF"))
+                .and_then(|object| object.as_name()) {
+                state.embedded_file_filter = embedded_file_filter.to_vec();
+            }
         }
 
         Ok(state)
     }
 
     pub fn encode(&self) -> Result<Dictionary, DecryptionError> {
+        if let Some(public_key) = &self.public_key {
+            return Ok(self.encode_public_key(public_key));
+        }
+
         let mut encrypted = Dictionary::new();
 
         encrypted.set(b"Filter", Object::Name(b"Standard".to_vec()));
@@ -665,6 +739,10 @@ pub fn encode(&self) -> Result<Dictionary, DecryptionError> {
             encrypted.set(b"CF", Object::Dictionary(filters));
             encrypted.set(b"StmF", Object::Name(self.stream_filter.clone()));
             encrypted.set(b"StrF", Object::Name(self.string_filter.clone()));
+
+            if !self.embedded_file_filter.is_empty() {
+                encrypted.set(b"EFF", Object::Name(self.embedded_file_filter.clone()));
+            }
         }
 
         if self.revision >= 5 {
@@ -677,11 +755,93 @@ pub fn encode(&self) -> Result<Dictionary, DecryptionError> {
     }
 
     pub fn get_stream_filter(&self) -> Arc<dyn CryptFilter> {
-        self.crypt_filters.get(&self.stream_filter).cloned().unwrap_or(Arc::new(Rc4CryptFilter))
+        self.get_crypt_filter(&self.stream_filter)
     }
 
     pub fn get_string_filter(&self) -> Arc<dyn CryptFilter> {
-        self.crypt_filters.get(&self.string_filter).cloned().unwrap_or(Arc::new(Rc4CryptFilter))
+        self.get_crypt_filter(&self.string_filter)
+    }
+
+    pub fn get_embedded_file_filter(&self) -> Arc<dyn CryptFilter> {
+        self.get_crypt_filter(self.default_embedded_file_filter())
+    }
+
+    fn get_crypt_filter(&self, name: &[u8]) -> Arc<dyn CryptFilter> {
+        if let Some(crypt_filter) = self.crypt_filters.get(name) {
+            return crypt_filter.clone();
+        }
+
+        // Before V 4 there are no crypt filters and RC4 is used for everything. From V 4 onwards,
+        // Identity is the default and the only name that doesn't have to be defined in CF.
+        if self.version >= 4 {
+            Arc::new(IdentityCryptFilter)
+        } else {
+            Arc::new(Rc4CryptFilter)
+        }
+    }
+
+    /// The crypt filter selected by the `/Crypt` filter of `stream`, if it has one, along with the
+    /// position of the `/Crypt` filter in its filter chain.
+    fn get_override_crypt_filter(&self, stream: &Stream) -> Option<(usize, Arc<dyn CryptFilter>)> {
+        // A stream filter type, the Crypt filter can be specified for any stream in the document
+        // to override the default filter for streams. The stream's DecodeParms entry shall
+        // contain a Crypt filter decode parameters dictionary whose Name entry specifies the
+        // particular crypt filter that shell be used (if missing, Identity is used). With several
+        // filters, DecodeParms is an array with the parameters of each filter.
+        let index = stream.filters().ok()?.iter().position(|filter| *filter == b"Crypt")?;
+
+        let params = match stream.dict.get(b"DecodeParms") {
+            Ok(Object::Array(params)) => params.get(index),
+            Ok(params) => Some(params),
+            Err(_) => None,
+        };
+
+        let crypt_filter = params
+            .and_then(|object| object.as_dict().ok())
+            .and_then(|dict| dict.get(b"Name").and_then(|object| object.as_name()).ok())
+            .and_then(|name| self.crypt_filters.get(name).cloned())
+            .unwrap_or(Arc::new(IdentityCryptFilter));
+
+        Some((index, crypt_filter))
+    }
+
+    /// The crypt filter to use for `stream`, and the position of its `/Crypt` filter if it
+    /// specifies its own crypt filter.
+    fn get_crypt_filter_for_stream(&self, stream: &Stream) -> (Option<usize>, Arc<dyn CryptFilter>) {
+        // If the stream object specifies its own crypt filter, it overrides the default one.
+        if let Some((index, crypt_filter)) = self.get_override_crypt_filter(stream) {
+            return (Some(index), crypt_filter);
+        }
+
+        if stream.dict.has_type(b"EmbeddedFile") {
+            (None, self.get_embedded_file_filter())
+        } else {
+            (None, self.get_stream_filter())
+        }
+    }
+}
+
+/// Remove the filter at `index` and its decode parameters from the filter chain of `stream`.
+fn remove_filter(stream: &mut Stream, index: usize) {
+    let Ok(Object::Array(filters)) = stream.dict.get_mut(b"Filter") else {
+        stream.dict.remove(b"Filter");
+        stream.dict.remove(b"DecodeParms");
+        return;
+    };
+
+    filters.remove(index);
+    if filters.is_empty() {
+        stream.dict.remove(b"Filter");
+    }
+
+    if let Ok(Object::Array(params)) = stream.dict.get_mut(b"DecodeParms") {
+        if index < params.len() {
+            params.remove(index);
+        }
+
+        if params.iter().all(|params| matches!(params, Object::Null)) {
+            stream.dict.remove(b"DecodeParms");
+        }
     }
 }
 
@@ -702,24 +862,9 @@ pub fn encrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Objec
         return Ok(());
     }
 
-    // A stream filter type, the Crypt filter can be specified for any stream in the document to
-    // override the default filter for streams. The stream's DecodeParms entry shall contain a
-    // Crypt filter decode parameters dictionary whose Name entry specifies the particular crypt
-    // filter that shell be used (if missing, Identity is used).
-    let override_crypt_filter = obj.as_stream().ok()
-        .filter(|stream| stream.filters().map(|filters| filters.contains(&&b"Crypt"[..])).unwrap_or(false))
-        .and_then(|stream| stream.dict.get(b"DecodeParms").ok())
-        .and_then(|object| object.as_dict().ok())
-        .map(|dict| dict.get(b"Name")
-            .and_then(|object| object.as_name())
-            .ok()
-            .and_then(|name| state.crypt_filters.get(name).cloned())
-            .unwrap_or(Arc::new(IdentityCryptFilter))
-        );
-
     // Retrieve the plaintext and the crypt filter to use to decrypt the ciphertext from the given
     // object.
-    let (mut crypt_filter, plaintext) = match obj {
+    let (crypt_filter, plaintext) = match obj {
         // Encryption applies to all strings and streams in the document's PDF file, i.e., we have to
         // recursively process array and dictionary objects to decrypt any string and stream objects
         // stored inside of those.
@@ -740,19 +885,13 @@ pub fn encrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Objec
         // Encryption applies to all strings and streams in the document's PDF file. We return the
         // crypt filter and the content here.
         Object::String(content, _) => (state.get_string_filter(), &*content),
-        Object::Stream(stream) => (state.get_stream_filter(), &stream.content),
+        Object::Stream(stream) => (state.get_crypt_filter_for_stream(stream).1, &stream.content),
         // Encryption is not applied to other object types such as integers and boolean values.
         _ => {
             return Ok(());
         }
     };
 
-    // If the stream object specifies its own crypt filter, override the default one with the one
-    // from this stream object.
-    if let Some(filter) = override_crypt_filter {
-        crypt_filter = filter;
-    }
-
     // Compute the key from the original file encryption key and the object identifier to use for
     // the corresponding object.
     let key = crypt_filter.compute_key(&state.file_encryption_key, obj_id)?;
@@ -770,6 +909,28 @@ pub fn encrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Objec
     Ok(())
 }
 
+/// A crypt filter along with the key to use it with for a particular object.
+pub(crate) type ObjectCryptFilter = (Arc<dyn CryptFilter>, Vec<u8>);
+
+/// The crypt filter and key to encrypt the content of `stream` with, or `None` if the stream is not
+/// encrypted at all, which lets the writer encrypt the content while it is written.
+pub(crate) fn stream_encryption(
+    state: &EncryptionState,
+    obj_id: ObjectId,
+    stream: &Stream,
+) -> Result<Option<ObjectCryptFilter>, DecryptionError> {
+    // The cross-reference stream, and the Metadata stream if EncryptMetadata is false, are not
+    // encrypted.
+    if stream.dict.has_type(b"XRef") || (stream.dict.has_type(b"Metadata") && !state.encrypt_metadata) {
+        return Ok(None);
+    }
+
+    let (_, crypt_filter) = state.get_crypt_filter_for_stream(stream);
+    let key = crypt_filter.compute_key(&state.file_encryption_key, obj_id)?;
+
+    Ok(Some((crypt_filter, key)))
+}
+
 /// Decrypts `obj`.
 pub fn decrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Object) -> Result<(), DecryptionError> {
     // The cross-reference stream shall not be encrypted and strings appearing in the
@@ -787,24 +948,9 @@ pub fn decrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Objec
         return Ok(());
     }
 
-    // A stream filter type, the Crypt filter can be specified for any stream in the document to
-    // override the default filter for streams. The stream's DecodeParms entry shall contain a
-    // Crypt filter decode parameters dictionary whose Name entry specifies the particular crypt
-    // filter that shell be used (if missing, Identity is used).
-    let override_crypt_filter = obj.as_stream().ok()
-        .filter(|stream| stream.filters().map(|filters| filters.contains(&&b"Crypt"[..])).unwrap_or(false))
-        .and_then(|stream| stream.dict.get(b"DecodeParms").ok())
-        .and_then(|object| object.as_dict().ok())
-        .map(|dict| dict.get(b"Name")
-            .and_then(|object| object.as_name())
-            .ok()
-            .and_then(|name| state.crypt_filters.get(name).cloned())
-            .unwrap_or(Arc::new(IdentityCryptFilter))
-        );
-
     // Retrieve the ciphertext and the crypt filter to use to decrypt the ciphertext from the given
     // object.
-    let (mut crypt_filter, ciphertext) = match obj {
+    let (crypt_filter, ciphertext, crypt_filter_index) = match obj {
         // Encryption applies to all strings and streams in the document's PDF file, i.e., we have to
         // recursively process array and dictionary objects to decrypt any string and stream objects
         // stored inside of those.
@@ -824,20 +970,17 @@ pub fn decrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Objec
         }
         // Encryption applies to all strings and streams in the document's PDF file. We return the
         // crypt filter and the content here.
-        Object::String(content, _) => (state.get_string_filter(), &*content),
-        Object::Stream(stream) => (state.get_stream_filter(), &stream.content),
+        Object::String(content, _) => (state.get_string_filter(), &*content, None),
+        Object::Stream(stream) => {
+            let (index, crypt_filter) = state.get_crypt_filter_for_stream(stream);
+            (crypt_filter, &stream.content, index)
+        }
         // Encryption is not applied to other object types such as integers and boolean values.
         _ => {
             return Ok(());
         }
     };
 
-    // If the stream object specifies its own crypt filter, override the default one with the one
-    // from this stream object.
-    if let Some(filter) = override_crypt_filter {
-        crypt_filter = filter;
-    }
-
     // Compute the key from the original file encryption key and the object identifier to use for
     // the corresponding object.
     let key = crypt_filter.compute_key(&state.file_encryption_key, obj_id)?;
@@ -847,7 +990,16 @@ pub fn decrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Objec
 
     // Store the plaintext in the object.
     match obj {
-        Object::Stream(stream) => stream.set_content(plaintext),
+        Object::Stream(stream) => {
+            stream.set_content(plaintext);
+
+            // A named crypt filter only applies to the encrypted content, so it is removed from
+            // the filter chain once decrypted. Identity is kept: it tells that the stream stays
+            // in the clear when the document is encrypted again.
+            if let Some(index) = crypt_filter_index.filter(|_| crypt_filter.method() != b"Identity") {
+                remove_filter(stream, index);
+            }
+        }
         Object::String(content, _) => *content = plaintext,
         _ => (),
     }
@@ -857,7 +1009,7 @@ pub fn decrypt_object(state: &EncryptionState, obj_id: ObjectId, obj: &mut Objec
 
 #[cfg(test)]
 mod tests {
-    use crate::{EncryptionState, EncryptionVersion, Permissions};
+    use crate::{Document, EncryptionState, EncryptionVersion, Object, Permissions};
     use crate::creator::tests::create_document;
     use crate::encryption::{CryptFilter, Aes128CryptFilter, Aes256CryptFilter};
     use rand::Rng as _;
@@ -1005,4 +1157,238 @@ fn encrypt_v5() {
         assert!(document.encrypt(&state).is_ok());
         assert!(document.decrypt("user").is_ok());
     }
+
+    #[test]
+    fn encrypt_embedded_files_only() {
+        let mut document = create_document();
+        let embedded_file = crate::Stream::new(
+            crate::dictionary! { "Type" => "EmbeddedFile" },
+            b"attachment".to_vec(),
+        );
+        let embedded_file_id = document.add_object(embedded_file);
+        let content = crate::Stream::new(crate::Dictionary::new(), b"content".to_vec());
+        let content_id = document.add_object(content);
+
+        let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+        let version = EncryptionVersion::V4 {
+            document: &document,
+            encrypt_metadata: true,
+            crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+            stream_filter: b"Identity".to_vec(),
+            string_filter: b"Identity".to_vec(),
+            owner_password: "owner",
+            user_password: "user",
+            permissions: Permissions::all(),
+        };
+        let state = EncryptionState::try_from(version).unwrap().with_embedded_file_filter("StdCF");
+        assert_eq!(state.encode().unwrap().get(b"EFF").unwrap(), &Object::Name(b"StdCF".to_vec()));
+
+        document.encrypt(&state).unwrap();
+        let content = |document: &Document, id| document.get_object(id).unwrap().as_stream().unwrap().content.clone();
+        assert_ne!(content(&document, embedded_file_id), b"attachment");
+        assert_eq!(content(&document, content_id), b"content");
+
+        let mut bytes = vec![];
+        document.save_to(&mut bytes).unwrap();
+        let mut document = Document::load_mem(&bytes).unwrap();
+        document.decrypt("user").unwrap();
+        assert_eq!(content(&document, embedded_file_id), b"attachment");
+        assert_eq!(content(&document, content_id), b"content");
+    }
+
+    #[test]
+    fn encrypt_with_stream_crypt_filter() {
+        use std::io::Write as _;
+
+        let mut document = create_document();
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(b"<x:xmpmeta/>").unwrap();
+        let compressed = encoder.finish().unwrap();
+        // The metadata stays in the clear through its own Identity crypt filter, with the
+        // parameters of each filter given in an array.
+        let metadata = crate::Stream::new(
+            crate::dictionary! {
+                "Type" => "Metadata",
+                "Filter" => vec!["Crypt".into(), "FlateDecode".into()],
+                "DecodeParms" => vec![
+                    crate::dictionary! { "Type" => "CryptFilterDecodeParms", "Name" => "Identity" }.into(),
+                    Object::Null,
+                ]
+            },
+            compressed.clone(),
+        );
+        let metadata_id = document.add_object(metadata);
+
+        let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+        let version = EncryptionVersion::V4 {
+            document: &document,
+            encrypt_metadata: true,
+            crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+            stream_filter: b"StdCF".to_vec(),
+            string_filter: b"StdCF".to_vec(),
+            owner_password: "owner",
+            user_password: "user",
+            permissions: Permissions::all(),
+        };
+        let state = EncryptionState::try_from(version).unwrap();
+        document.encrypt(&state).unwrap();
+        let mut bytes = vec![];
+        document.save_to(&mut bytes).unwrap();
+
+        let mut document = Document::load_mem(&bytes).unwrap();
+        assert_eq!(document.get_object(metadata_id).unwrap().as_stream().unwrap().content, compressed);
+        document.decrypt("user").unwrap();
+        let metadata = document.get_object(metadata_id).unwrap().as_stream().unwrap();
+        assert_eq!(metadata.content, compressed);
+        assert_eq!(metadata.decompressed_content().unwrap(), b"<x:xmpmeta/>");
+    }
+
+    #[test]
+    fn enforce_permissions() {
+        let mut document = create_document();
+        let version = EncryptionVersion::V2 {
+            document: &document,
+            owner_password: "owner",
+            user_password: "user",
+            key_length: 128,
+            permissions: Permissions::PRINTABLE,
+        };
+        let state = EncryptionState::try_from(version).unwrap();
+        let text = document.extract_text(&[1]).unwrap();
+        document.encrypt(&state).unwrap();
+        let mut bytes = vec![];
+        document.save_to(&mut bytes).unwrap();
+
+        let mut document = Document::load_mem(&bytes).unwrap();
+        document.decrypt("user").unwrap();
+        assert!(!document.encryption_state.as_ref().unwrap().owner_authenticated());
+        assert_eq!(document.extract_text(&[1]).unwrap(), text);
+
+        document.enforce_permissions = true;
+        let page_id = document.page_iter().next().unwrap();
+        assert!(matches!(
+            document.extract_text(&[1]),
+            Err(crate::Error::PermissionDenied(Permissions::COPYABLE))
+        ));
+        assert!(matches!(document.set_page_rotation(page_id, 90), Err(crate::Error::PermissionDenied(_))));
+        assert!(matches!(document.delete_pages(&[1]), Err(crate::Error::PermissionDenied(_))));
+        assert!(matches!(
+            document.n_up(2, 1, (842.0, 595.0), &Default::default()),
+            Err(crate::Error::PermissionDenied(Permissions::ASSEMBLABLE))
+        ));
+        assert!(matches!(
+            document.flatten_annotations(&Default::default()),
+            Err(crate::Error::PermissionDenied(Permissions::ANNOTABLE))
+        ));
+        let chunks = document.extract_text_chunks(&[1, 1]);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|chunk| chunk.is_err()));
+        assert_eq!(document.get_pages().len(), 1);
+
+        // The owner password grants all permissions.
+        let mut document = Document::load_mem(&bytes).unwrap();
+        document.decrypt("owner").unwrap();
+        document.enforce_permissions = true;
+        assert!(document.encryption_state.as_ref().unwrap().owner_authenticated());
+        assert_eq!(document.extract_text(&[1]).unwrap(), text);
+        assert!(document.delete_pages(&[1]).is_ok());
+    }
+
+    #[test]
+    fn encrypt_without_file_id() {
+        let mut document = create_document();
+        document.trailer.remove(b"ID");
+
+        let version = EncryptionVersion::V2 {
+            document: &document,
+            owner_password: "owner",
+            user_password: "user",
+            key_length: 128,
+            permissions: Permissions::all(),
+        };
+        let state = EncryptionState::try_from(version).unwrap();
+        assert!(state.file_id().is_some());
+        document.encrypt(&state).unwrap();
+        let mut bytes = vec![];
+        document.save_to(&mut bytes).unwrap();
+
+        let mut document = Document::load_mem(&bytes).unwrap();
+        let id = document.trailer.get(b"ID").unwrap().as_array().unwrap();
+        assert_eq!(id[0].as_str().unwrap(), state.file_id().unwrap());
+        assert!(document.decrypt("user").is_ok());
+    }
+
+    #[test]
+    fn save_encrypted_keeps_security_settings() {
+        let mut document = create_document();
+        let version = EncryptionVersion::V2 {
+            document: &document,
+            owner_password: "owner",
+            user_password: "user",
+            key_length: 128,
+            permissions: Permissions::PRINTABLE,
+        };
+        let state = EncryptionState::try_from(version).unwrap();
+        document.encrypt(&state).unwrap();
+        let mut original = vec![];
+        document.save_to(&mut original).unwrap();
+
+        let mut document = Document::load_mem(&original).unwrap();
+        let encrypt = document.get_encrypted().unwrap().clone();
+        let id = document.trailer.get(b"ID").unwrap().clone();
+        document.decrypt("user").unwrap();
+        document.trailer.set("Info", crate::dictionary! { "Title" => Object::string_literal("edited") });
+        let max_id = document.max_id;
+        let mut saved = vec![];
+        document.save_encrypted_to(&mut saved).unwrap();
+        // The document itself is still decrypted.
+        assert!(!document.is_encrypted());
+        assert!(document.save_encrypted_to(&mut vec![]).is_ok());
+        assert_eq!(document.max_id, max_id);
+
+        let mut reloaded = Document::load_mem(&saved).unwrap();
+        let reencrypted = reloaded.get_encrypted().unwrap();
+        for key in [b"O".as_slice(), b"U", b"P", b"V", b"R", b"Length"] {
+            assert_eq!(reencrypted.get(key).unwrap(), encrypt.get(key).unwrap());
+        }
+        // The first identifier, which the file encryption key depends on, is kept.
+        let first_id = |id: &Object| id.as_array().unwrap()[0].clone();
+        assert_eq!(first_id(reloaded.trailer.get(b"ID").unwrap()), first_id(&id));
+        assert!(reloaded.authenticate_password("owner").is_ok());
+        reloaded.decrypt("user").unwrap();
+        assert_eq!(
+            reloaded.encryption_state.as_ref().unwrap().file_encryption_key(),
+            document.encryption_state.as_ref().unwrap().file_encryption_key()
+        );
+        assert_eq!(reloaded.extract_text(&[1]).unwrap(), document.extract_text(&[1]).unwrap());
+        assert!(create_document().save_encrypted_to(&mut vec![]).is_err());
+    }
+
+    #[test]
+    fn save_encrypted_large_stream() {
+        let mut document = create_document();
+        let content: Vec<u8> = (0..200_000).map(|i| (i % 253) as u8).collect();
+        let stream_id = document.add_object(crate::Stream::new(crate::Dictionary::new(), content.clone()));
+
+        let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+        let version = EncryptionVersion::V4 {
+            document: &document,
+            encrypt_metadata: true,
+            crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+            stream_filter: b"StdCF".to_vec(),
+            string_filter: b"StdCF".to_vec(),
+            owner_password: "owner",
+            user_password: "user",
+            permissions: Permissions::all(),
+        };
+        document.encryption_state = Some(EncryptionState::try_from(version).unwrap());
+        let mut saved = vec![];
+        document.save_encrypted_to(&mut saved).unwrap();
+
+        let mut reloaded = Document::load_mem(&saved).unwrap();
+        let encrypted = reloaded.get_object(stream_id).unwrap().as_stream().unwrap();
+        assert_eq!(encrypted.content.len(), 16 + (content.len() + 16) / 16 * 16);
+        reloaded.decrypt("user").unwrap();
+        assert_eq!(reloaded.get_object(stream_id).unwrap().as_stream().unwrap().content, content);
+    }
 }
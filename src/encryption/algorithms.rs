@@ -34,6 +34,9 @@ pub struct PasswordAlgorithm {
     pub(crate) user_encrypted: Vec<u8>,
     pub(crate) permissions: Permissions,
     pub(crate) permission_encrypted: Vec<u8>,
+    /// First file identifier to use instead of the one in the trailer, for documents that don't
+    /// have a trailer `/ID` yet.
+    pub(crate) file_id: Option<Vec<u8>>,
 }
 
 impl TryFrom<&Document> for PasswordAlgorithm {
@@ -226,11 +229,29 @@ fn try_from(value: &Document) -> Result<Self, Self::Error> {
             user_encrypted,
             permissions,
             permission_encrypted,
+            file_id: None,
         })
     }
 }
 
 impl PasswordAlgorithm {
+    /// The first element of the file identifier.
+    fn file_id_0<'a>(&'a self, doc: &'a Document) -> Result<&'a [u8], DecryptionError> {
+        if let Some(file_id) = &self.file_id {
+            return Ok(file_id);
+        }
+
+        doc.trailer
+            .get(b"ID")
+            .map_err(|_| DecryptionError::MissingFileID)?
+            .as_array()
+            .map_err(|_| DecryptionError::InvalidType)?
+            .first()
+            .ok_or(DecryptionError::InvalidType)?
+            .as_str()
+            .map_err(|_| DecryptionError::InvalidType)
+    }
+
     /// Sanitize the password (revision 4 and earlier).
     ///
     /// This implements the first step of Algorithm 2 as described in ISO 32000-2:2020 (PDF 2.0).
@@ -296,17 +317,7 @@ pub(crate) fn compute_file_encryption_key_r4<P>(
 
         // Pass the first element of the file's file identifier array (the value of the ID entry in the
         // document's trailer dictionary to the MD5 hash function.
-        let file_id_0 = doc
-            .trailer
-            .get(b"ID")
-            .map_err(|_| DecryptionError::MissingFileID)?
-            .as_array()
-            .map_err(|_| DecryptionError::InvalidType)?
-            .first()
-            .ok_or(DecryptionError::InvalidType)?
-            .as_str()
-            .map_err(|_| DecryptionError::InvalidType)?;
-        hasher.update(file_id_0);
+        hasher.update(self.file_id_0(doc)?);
 
         // (Security handlers of revision 4 or greater) If document metadata is not being encrypted,
         // pass 4 bytes with the value 0xFFFFFFFF to the MD5 hash function.
@@ -717,17 +728,7 @@ pub(crate) fn compute_hashed_user_password_r3_r4<U>(
 
         // Pass the first element of the file's file identifier array (the value of the ID entry in the
         // document's trailer dictionary) to the hash function and finish the hash.
-        let file_id_0 = doc
-            .trailer
-            .get(b"ID")
-            .map_err(|_| DecryptionError::MissingFileID)?
-            .as_array()
-            .map_err(|_| DecryptionError::InvalidType)?
-            .first()
-            .ok_or(DecryptionError::InvalidType)?
-            .as_str()
-            .map_err(|_| DecryptionError::InvalidType)?;
-        hasher.update(file_id_0);
+        hasher.update(self.file_id_0(doc)?);
 
         let hash = hasher.finalize();
 
@@ -812,6 +813,25 @@ fn authenticate_owner_password_r4<O>(
         doc: &Document,
         owner_password: O,
     ) -> Result<(), DecryptionError>
+    where
+        O: AsRef<[u8]>,
+    {
+        let user_password = self.compute_user_password_r4(owner_password)?;
+
+        // The result of the previous step purports to be the user password. Authenticate this user
+        // password using Algorithm 5. If it is correct, the password supplied is the correct owner
+        // password.
+        self.authenticate_user_password_r4(doc, user_password)
+    }
+
+    /// Retrieve the user password from the encryption dictionary's O entry with the owner password
+    /// (revision 2-4).
+    ///
+    /// This implements the first steps of Algorithm 7 as described in ISO 32000-2:2020 (PDF 2.0).
+    pub(crate) fn compute_user_password_r4<O>(
+        &self,
+        owner_password: O,
+    ) -> Result<Vec<u8>, DecryptionError>
     where
         O: AsRef<[u8]>,
     {
@@ -889,10 +909,7 @@ fn authenticate_owner_password_r4<O>(
         // encryption key.
         result = Rc4::new(&hash[..n]).decrypt(&result);
 
-        // The result of the previous step purports to be the user password. Authenticate this user
-        // password using Algorithm 5. If it is correct, the password supplied is the correct owner
-        // password.
-        self.authenticate_user_password_r4(doc, &result)
+        Ok(result)
     }
 
     /// Compute the encryption dictionary's U-entry value (revision 6).
@@ -1416,7 +1433,7 @@ fn authenticate_password_r5() {
         assert!(algorithm.authenticate_user_password_r6(&owner_password).is_err());
 
         // Assert that the permissions validate correctly.
-        assert!(algorithm.validate_permissions(&file_encryption_key).is_ok());
+        assert!(algorithm.validate_permissions(file_encryption_key).is_ok());
 
         // Assert that the file encryption key is equal for the owner password.
         let key = algorithm.compute_file_encryption_key_r6(&owner_password).unwrap();
@@ -1479,7 +1496,7 @@ fn authenticate_password_r6() {
         assert!(algorithm.authenticate_user_password_r6(&owner_password).is_err());
 
         // Assert that the permissions validate correctly.
-        assert!(algorithm.validate_permissions(&file_encryption_key).is_ok());
+        assert!(algorithm.validate_permissions(file_encryption_key).is_ok());
 
         // Assert that the file encryption key is equal for the owner password.
         let key = algorithm.compute_file_encryption_key_r6(&owner_password).unwrap();
@@ -2,10 +2,16 @@
 use crate::ObjectId;
 use md5::{Digest as _, Md5};
 use rand::Rng as _;
+use std::io::{ErrorKind, Read, Write};
 use super::DecryptionError;
 use super::pkcs5::Pkcs5;
 use super::rc4::Rc4;
 
+/// Size of the parts in which content is encrypted or decrypted by
+/// [`CryptFilter::encrypt_reader`] and [`CryptFilter::decrypt_writer`]. A multiple of the AES
+/// block size.
+const CHUNK_SIZE: usize = 64 * 1024;
+
 type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
 type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
 
@@ -17,6 +23,43 @@ pub trait CryptFilter: std::fmt::Debug + Send + Sync {
     fn compute_key(&self, key: &[u8], obj_id: ObjectId) -> Result<Vec<u8>, DecryptionError>;
     fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, DecryptionError>;
     fn decrypt(&self, key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, DecryptionError>;
+
+    /// Encrypt the plaintext read from `plaintext` and write the ciphertext to `ciphertext`, part
+    /// by part for the crypt filters of lopdf, so that large streams don't have to be held in
+    /// memory twice. Returns the length of the ciphertext.
+    fn encrypt_reader(
+        &self,
+        key: &[u8],
+        plaintext: &mut dyn Read,
+        ciphertext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        let mut buffer = vec![];
+        plaintext.read_to_end(&mut buffer)?;
+        let buffer = self.encrypt(key, &buffer)?;
+        ciphertext.write_all(&buffer)?;
+        Ok(buffer.len() as u64)
+    }
+
+    /// Decrypt the ciphertext read from `ciphertext` and write the plaintext to `plaintext`, part
+    /// by part for the crypt filters of lopdf. Returns the length of the plaintext.
+    fn decrypt_writer(
+        &self,
+        key: &[u8],
+        ciphertext: &mut dyn Read,
+        plaintext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        let mut buffer = vec![];
+        ciphertext.read_to_end(&mut buffer)?;
+        let buffer = self.decrypt(key, &buffer)?;
+        plaintext.write_all(&buffer)?;
+        Ok(buffer.len() as u64)
+    }
+
+    /// The length of the ciphertext for a plaintext of `plaintext_len` bytes, if it is known
+    /// before encrypting. The writer needs it to encrypt streams while writing them.
+    fn ciphertext_len(&self, _plaintext_len: usize) -> Option<usize> {
+        None
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -38,6 +81,28 @@ fn encrypt(&self, _key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, DecryptionEr
     fn decrypt(&self, _key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, DecryptionError> {
         Ok(ciphertext.to_vec())
     }
+
+    fn encrypt_reader(
+        &self,
+        _key: &[u8],
+        plaintext: &mut dyn Read,
+        ciphertext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        Ok(std::io::copy(plaintext, ciphertext)?)
+    }
+
+    fn decrypt_writer(
+        &self,
+        _key: &[u8],
+        ciphertext: &mut dyn Read,
+        plaintext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        Ok(std::io::copy(ciphertext, plaintext)?)
+    }
+
+    fn ciphertext_len(&self, plaintext_len: usize) -> Option<usize> {
+        Some(plaintext_len)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -78,6 +143,42 @@ fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, DecryptionErr
     fn decrypt(&self, key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, DecryptionError> {
         Ok(Rc4::new(key).decrypt(ciphertext))
     }
+
+    fn encrypt_reader(
+        &self,
+        key: &[u8],
+        plaintext: &mut dyn Read,
+        ciphertext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        // RC4 is symmetric.
+        self.decrypt_writer(key, plaintext, ciphertext)
+    }
+
+    fn decrypt_writer(
+        &self,
+        key: &[u8],
+        ciphertext: &mut dyn Read,
+        plaintext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        let mut keystream = Rc4::new(key).keystream();
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let mut written = 0;
+
+        loop {
+            let len = read_chunk(ciphertext, &mut buffer)?;
+            if len == 0 {
+                return Ok(written);
+            }
+
+            keystream.apply(&mut buffer[..len]);
+            plaintext.write_all(&buffer[..len])?;
+            written += len as u64;
+        }
+    }
+
+    fn ciphertext_len(&self, plaintext_len: usize) -> Option<usize> {
+        Some(plaintext_len)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -184,6 +285,39 @@ fn decrypt(&self, key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, DecryptionEr
             .map_err(|_| DecryptionError::Padding)?
             .to_vec())
     }
+
+    fn encrypt_reader(
+        &self,
+        key: &[u8],
+        plaintext: &mut dyn Read,
+        ciphertext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        // Ensure that the key is 128 bits (i.e., 16 bytes).
+        if key.len() != 16 {
+            return Err(DecryptionError::InvalidKeyLength)?;
+        }
+
+        let iv = random_iv();
+        encrypt_cbc(Aes128CbcEnc::new(key.into(), &iv.into()), &iv, plaintext, ciphertext)
+    }
+
+    fn decrypt_writer(
+        &self,
+        key: &[u8],
+        ciphertext: &mut dyn Read,
+        plaintext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        // Ensure that the key is 128 bits (i.e., 16 bytes).
+        if key.len() != 16 {
+            return Err(DecryptionError::InvalidKeyLength)?;
+        }
+
+        decrypt_cbc(|iv| Aes128CbcDec::new(key.into(), iv.into()), ciphertext, plaintext)
+    }
+
+    fn ciphertext_len(&self, plaintext_len: usize) -> Option<usize> {
+        Some(aes_ciphertext_len(plaintext_len))
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -268,4 +402,205 @@ fn decrypt(&self, key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, DecryptionEr
             .map_err(|_| DecryptionError::Padding)?
             .to_vec())
     }
+
+    fn encrypt_reader(
+        &self,
+        key: &[u8],
+        plaintext: &mut dyn Read,
+        ciphertext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        // Ensure that the key is 256 bits (i.e., 32 bytes).
+        if key.len() != 32 {
+            return Err(DecryptionError::InvalidKeyLength)?;
+        }
+
+        let iv = random_iv();
+        encrypt_cbc(Aes256CbcEnc::new(key.into(), &iv.into()), &iv, plaintext, ciphertext)
+    }
+
+    fn decrypt_writer(
+        &self,
+        key: &[u8],
+        ciphertext: &mut dyn Read,
+        plaintext: &mut dyn Write,
+    ) -> crate::Result<u64> {
+        // Ensure that the key is 256 bits (i.e., 32 bytes).
+        if key.len() != 32 {
+            return Err(DecryptionError::InvalidKeyLength)?;
+        }
+
+        decrypt_cbc(|iv| Aes256CbcDec::new(key.into(), iv.into()), ciphertext, plaintext)
+    }
+
+    fn ciphertext_len(&self, plaintext_len: usize) -> Option<usize> {
+        Some(aes_ciphertext_len(plaintext_len))
+    }
+}
+
+/// Read from `reader` until `buffer` is full or the end of the input is reached. Returns the
+/// number of bytes read.
+fn read_chunk(reader: &mut dyn Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(len)
+}
+
+/// Generate a random initialization vector for the AES algorithms.
+fn random_iv() -> [u8; 16] {
+    let mut rng = rand::rng();
+    let mut iv = [0u8; 16];
+    rng.fill(&mut iv);
+    iv
+}
+
+/// The length of the initialization vector followed by the padded plaintext.
+fn aes_ciphertext_len(plaintext_len: usize) -> usize {
+    16 + (plaintext_len + 16) / 16 * 16
+}
+
+/// Encrypt with AES-CBC part by part, writing the initialization vector first and padding the last
+/// block with PKCS#5.
+fn encrypt_cbc<C: BlockEncryptMut>(
+    mut cipher: C,
+    iv: &[u8; 16],
+    plaintext: &mut dyn Read,
+    ciphertext: &mut dyn Write,
+) -> crate::Result<u64> {
+    ciphertext.write_all(iv)?;
+
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut written = iv.len() as u64;
+
+    loop {
+        let len = read_chunk(plaintext, &mut buffer)?;
+
+        // The end of the plaintext is reached if the buffer isn't full, so pad the last block.
+        let is_last = len < buffer.len();
+        let end = if is_last {
+            let padded_len = (len + 16) / 16 * 16;
+            buffer[len..padded_len].fill((padded_len - len) as u8);
+            padded_len
+        } else {
+            len
+        };
+
+        for block in buffer[..end].chunks_exact_mut(16) {
+            cipher.encrypt_block_mut(block.into());
+        }
+
+        ciphertext.write_all(&buffer[..end])?;
+        written += end as u64;
+
+        if is_last {
+            return Ok(written);
+        }
+    }
+}
+
+/// Decrypt with AES-CBC part by part, reading the initialization vector first and removing the
+/// PKCS#5 padding of the last block.
+fn decrypt_cbc<C: BlockDecryptMut>(
+    new_cipher: impl FnOnce(&[u8; 16]) -> C,
+    ciphertext: &mut dyn Read,
+    plaintext: &mut dyn Write,
+) -> crate::Result<u64> {
+    let mut iv = [0u8; 16];
+
+    match read_chunk(ciphertext, &mut iv)? {
+        // There is nothing to decrypt if the ciphertext is empty.
+        0 => return Ok(0),
+        16 => (),
+        _ => return Err(DecryptionError::InvalidCipherTextLength.into()),
+    }
+
+    let mut cipher = new_cipher(&iv);
+
+    // The last block holds the padding, so it is kept in the buffer until the end of the
+    // ciphertext is reached.
+    let mut buffer = vec![0u8; CHUNK_SIZE + 16];
+    let mut kept = 0;
+    let mut written = 0;
+
+    loop {
+        let len = kept + read_chunk(ciphertext, &mut buffer[kept..])?;
+
+        if len < buffer.len() {
+            // Ensure that the ciphertext length is a multiple of 16 bytes.
+            if len % 16 != 0 {
+                return Err(DecryptionError::InvalidCipherTextLength.into());
+            }
+
+            // There is nothing left to decrypt if the ciphertext only contains the IV.
+            if len == 0 {
+                return Ok(written);
+            }
+
+            for block in buffer[..len].chunks_exact_mut(16) {
+                cipher.decrypt_block_mut(block.into());
+            }
+
+            let padding = buffer[len - 1] as usize;
+            if padding == 0 || padding > 16 || buffer[len - padding..len].iter().any(|&byte| byte as usize != padding) {
+                return Err(DecryptionError::Padding.into());
+            }
+
+            plaintext.write_all(&buffer[..len - padding])?;
+            return Ok(written + (len - padding) as u64);
+        }
+
+        let end = len - 16;
+        for block in buffer[..end].chunks_exact_mut(16) {
+            cipher.decrypt_block_mut(block.into());
+        }
+
+        plaintext.write_all(&buffer[..end])?;
+        written += end as u64;
+
+        buffer.copy_within(end..len, 0);
+        kept = 16;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streaming_matches_whole_content() {
+        let filters: [(&dyn CryptFilter, usize); 4] = [
+            (&IdentityCryptFilter, 16),
+            (&Rc4CryptFilter, 16),
+            (&Aes128CryptFilter, 16),
+            (&Aes256CryptFilter, 32),
+        ];
+
+        for (filter, key_len) in filters {
+            let key = vec![7u8; key_len];
+
+            for len in [0, 1, 15, 16, 17, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 16, 3 * CHUNK_SIZE + 5] {
+                let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+
+                let mut ciphertext = vec![];
+                let written = filter.encrypt_reader(&key, &mut plaintext.as_slice(), &mut ciphertext).unwrap();
+                assert_eq!(written as usize, ciphertext.len());
+                assert_eq!(filter.ciphertext_len(len), Some(ciphertext.len()));
+                assert_eq!(filter.decrypt(&key, &ciphertext).unwrap(), plaintext);
+
+                let ciphertext = filter.encrypt(&key, &plaintext).unwrap();
+                let mut decrypted = vec![];
+                let written = filter.decrypt_writer(&key, &mut ciphertext.as_slice(), &mut decrypted).unwrap();
+                assert_eq!(written as usize, len);
+                assert_eq!(decrypted, plaintext);
+            }
+        }
+    }
 }
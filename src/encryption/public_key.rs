@@ -0,0 +1,314 @@
+use super::{DecryptionError, EncryptionState, Permissions};
+use super::crypt_filters::CryptFilter;
+use crate::{Dictionary, Document, Error, Object};
+use rand::Rng as _;
+use sha1::{Digest as _, Sha1};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Name of the crypt filter written for documents encrypted for recipients.
+const DEFAULT_CRYPT_FILTER: &[u8] = b"DefaultCryptFilter";
+
+/// Decrypts the seed of a public-key security handler with a recipient's private key.
+///
+/// The private key never has to be handed to lopdf: the document passes each entry of
+/// `/Recipients` to the implementation, which decrypts the ones addressed to it.
+pub trait RecipientDecryptor {
+    /// Decrypt a DER encoded PKCS#7 enveloped data object and return its content, or `None` if it
+    /// is not addressed to this recipient.
+    fn decrypt(&self, enveloped_data: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Encrypts the seed of a public-key security handler for a recipient's certificate.
+pub trait RecipientEncryptor {
+    /// Return a DER encoded PKCS#7 enveloped data object holding `content`, encrypted for the
+    /// recipient.
+    fn encrypt(&self, content: &[u8]) -> crate::Result<Vec<u8>>;
+}
+
+/// A recipient of a document encrypted with the public-key security handler.
+pub struct Recipient<'a> {
+    pub encryptor: &'a dyn RecipientEncryptor,
+    /// Permissions granted to the recipient.
+    pub permissions: Permissions,
+}
+
+/// State of the public-key security handler, written back to the encryption dictionary.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PublicKeyState {
+    pub(crate) sub_filter: Vec<u8>,
+    pub(crate) recipients: Vec<Vec<u8>>,
+}
+
+/// Compute the file encryption key from the seed and all recipients (ISO 32000-2 §7.6.5.3).
+fn file_encryption_key(seed: &[u8], recipients: &[Vec<u8>], encrypt_metadata: bool, key_length: usize) -> Vec<u8> {
+    let metadata: &[u8] = if encrypt_metadata { &[] } else { &[0xff; 4] };
+    let mut digest = if key_length > 20 {
+        let mut hasher = Sha256::new();
+        hasher.update(seed);
+        recipients.iter().for_each(|recipient| hasher.update(recipient));
+        hasher.update(metadata);
+        hasher.finalize().to_vec()
+    } else {
+        let mut hasher = Sha1::new();
+        hasher.update(seed);
+        recipients.iter().for_each(|recipient| hasher.update(recipient));
+        hasher.update(metadata);
+        hasher.finalize().to_vec()
+    };
+    digest.truncate(key_length);
+    digest
+}
+
+/// Key length in bytes of a crypt filter method; `length` is the `/Length` entry, in bits.
+fn key_length(method: &[u8], length: Option<i64>) -> usize {
+    match method {
+        b"AESV3" => 32,
+        b"AESV2" => 16,
+        _ => length.map_or(16, |length| (length as usize / 8).clamp(5, 16)),
+    }
+}
+
+/// The `/Recipients` entry, which may be a single string or an array of strings.
+fn recipients(dict: &Dictionary) -> Result<Vec<Vec<u8>>, Error> {
+    match dict.get(b"Recipients")? {
+        Object::String(recipient, _) => Ok(vec![recipient.clone()]),
+        Object::Array(recipients) => Ok(recipients
+            .iter()
+            .filter_map(|recipient| recipient.as_str().ok().map(<[u8]>::to_vec))
+            .collect()),
+        _ => Err(DecryptionError::InvalidType)?,
+    }
+}
+
+impl EncryptionState {
+    /// Create the state of the public-key security handler (`adbe.pkcs7.s5`) for a list of
+    /// recipients, encrypting strings and streams with `crypt_filter`.
+    ///
+    /// A random seed is encrypted for each recipient together with its permissions.
+    pub fn for_recipients(
+        recipients: &[Recipient], crypt_filter: Arc<dyn CryptFilter>, encrypt_metadata: bool,
+    ) -> Result<Self, Error> {
+        if recipients.is_empty() {
+            return Err(Error::DictKey("Recipients".to_string()));
+        }
+
+        let method = crypt_filter.method().to_vec();
+        let key_length = key_length(&method, None);
+
+        let mut seed = [0u8; 20];
+        rand::rng().fill(&mut seed);
+
+        let mut enveloped = Vec::with_capacity(recipients.len());
+        for recipient in recipients {
+            // The seed followed by the permissions as a big-endian 32-bit integer.
+            let permissions = recipient.permissions.correct_bits().bits() as u32;
+            let mut content = seed.to_vec();
+            content.extend_from_slice(&permissions.to_be_bytes());
+            enveloped.push(recipient.encryptor.encrypt(&content)?);
+        }
+
+        Ok(Self {
+            version: if method == b"AESV3" { 5 } else { 4 },
+            revision: if method == b"AESV3" { 5 } else { 4 },
+            key_length: Some(key_length * 8),
+            encrypt_metadata,
+            crypt_filters: BTreeMap::from([(DEFAULT_CRYPT_FILTER.to_vec(), crypt_filter)]),
+            file_encryption_key: file_encryption_key(&seed, &enveloped, encrypt_metadata, key_length),
+            stream_filter: DEFAULT_CRYPT_FILTER.to_vec(),
+            string_filter: DEFAULT_CRYPT_FILTER.to_vec(),
+            permissions: Permissions::all(),
+            public_key: Some(PublicKeyState {
+                sub_filter: b"adbe.pkcs7.s5".to_vec(),
+                recipients: enveloped,
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// Decode the encryption dictionary of a document encrypted with the public-key security
+    /// handler (`/Filter /Adobe.PubSec`), using `decryptor` to recover the seed.
+    ///
+    /// The permissions are those granted to the recipient the seed was encrypted for.
+    pub fn decode_for_recipient(document: &Document, decryptor: &dyn RecipientDecryptor) -> Result<Self, Error> {
+        let encrypted = document.get_encrypted()
+            .map_err(|_| DecryptionError::MissingEncryptDictionary)?;
+        let filter = encrypted.get(b"Filter")
+            .and_then(Object::as_name)
+            .map_err(|_| Error::DictKey("Filter".to_string()))?;
+        if filter != b"Adobe.PubSec" {
+            return Err(Error::UnsupportedSecurityHandler(filter.to_vec()));
+        }
+        let sub_filter = encrypted.get(b"SubFilter").and_then(Object::as_name).unwrap_or(b"adbe.pkcs7.s4");
+        let version = encrypted.get(b"V").and_then(Object::as_i64)
+            .map_err(|_| DecryptionError::MissingVersion)?;
+
+        let mut state = Self {
+            version,
+            revision: encrypted.get(b"R").and_then(Object::as_i64).unwrap_or(version),
+            encrypt_metadata: encrypted.get(b"EncryptMetadata").and_then(Object::as_bool).unwrap_or(true),
+            ..Default::default()
+        };
+
+        // With V 4 and 5 the recipients are listed in the crypt filter; before that in the
+        // encryption dictionary itself.
+        let (recipients, key_length) = if version >= 4 {
+            state.crypt_filters = document.get_crypt_filters();
+            state.stream_filter = encrypted.get(b"StmF").and_then(Object::as_name)
+                .unwrap_or(b"Identity").to_vec();
+            state.string_filter = encrypted.get(b"StrF").and_then(Object::as_name)
+                .unwrap_or(b"Identity").to_vec();
+            state.embedded_file_filter = encrypted.get(b"EFF").and_then(Object::as_name)
+                .map(<[u8]>::to_vec).unwrap_or_default();
+
+            // The recipients are in whichever crypt filter is used, e.g. only in the one of the
+            // embedded files when the stream and string filters are Identity.
+            let filters = encrypted.get(b"CF").and_then(Object::as_dict)
+                .map_err(|_| DecryptionError::UnsupportedEncryption)?;
+            let filter = [&state.stream_filter, &state.string_filter, &state.embedded_file_filter].into_iter()
+                .find_map(|name| filters.get(name).and_then(Object::as_dict).ok())
+                .ok_or(DecryptionError::UnsupportedEncryption)?;
+            if let Ok(encrypt_metadata) = filter.get(b"EncryptMetadata").and_then(Object::as_bool) {
+                state.encrypt_metadata = encrypt_metadata;
+            }
+            let method = filter.get(b"CFM").and_then(Object::as_name).unwrap_or(b"None");
+            let length = filter.get(b"Length").and_then(Object::as_i64).ok()
+                // Some writers give the length of the crypt filter in bytes.
+                .map(|length| if length <= 32 { length * 8 } else { length });
+            (recipients(filter)?, key_length(method, length))
+        } else {
+            let length = encrypted.get(b"Length").and_then(Object::as_i64).unwrap_or(40);
+            (recipients(encrypted)?, key_length(b"V2", Some(length)))
+        };
+
+        let content = recipients.iter()
+            .find_map(|recipient| decryptor.decrypt(recipient))
+            .ok_or(DecryptionError::NoMatchingRecipient)?;
+        if content.len() < 20 {
+            return Err(DecryptionError::InvalidKeyLength)?;
+        }
+        if let Some(bytes) = content.get(20..24) {
+            let bits = u32::from_be_bytes(bytes.try_into().unwrap_or_default());
+            state.permissions = Permissions::from_bits_truncate(u64::from(bits));
+        }
+
+        state.key_length = Some(key_length * 8);
+        state.file_encryption_key = file_encryption_key(&content[..20], &recipients, state.encrypt_metadata, key_length);
+        state.public_key = Some(PublicKeyState {
+            sub_filter: sub_filter.to_vec(),
+            recipients,
+        });
+        Ok(state)
+    }
+
+    /// Encode the encryption dictionary of the public-key security handler.
+    pub(crate) fn encode_public_key(&self, public_key: &PublicKeyState) -> Dictionary {
+        let recipients = Object::Array(public_key.recipients.iter()
+            .map(|recipient| Object::String(recipient.clone(), crate::StringFormat::Hexadecimal))
+            .collect());
+
+        let mut encrypted = Dictionary::new();
+        encrypted.set(b"Filter", Object::Name(b"Adobe.PubSec".to_vec()));
+        encrypted.set(b"SubFilter", Object::Name(public_key.sub_filter.clone()));
+        encrypted.set(b"V", Object::Integer(self.version));
+        encrypted.set(b"R", Object::Integer(self.revision));
+        if let Some(key_length) = self.key_length {
+            encrypted.set(b"Length", Object::Integer(key_length as i64));
+        }
+
+        if self.version < 4 {
+            encrypted.set(b"Recipients", recipients);
+            return encrypted;
+        }
+
+        let mut filters = Dictionary::new();
+        for (name, crypt_filter) in &self.crypt_filters {
+            let mut filter = Dictionary::new();
+            filter.set(b"Type", Object::Name(b"CryptFilter".to_vec()));
+            filter.set(b"CFM", Object::Name(crypt_filter.method().to_vec()));
+            if let Some(key_length) = self.key_length {
+                filter.set(b"Length", Object::Integer(key_length as i64));
+            }
+            if *name == self.stream_filter || *name == self.string_filter || *name == self.embedded_file_filter {
+                filter.set(b"Recipients", recipients.clone());
+                filter.set(b"EncryptMetadata", Object::Boolean(self.encrypt_metadata));
+            }
+            filters.set(name.to_vec(), Object::Dictionary(filter));
+        }
+        encrypted.set(b"CF", Object::Dictionary(filters));
+        encrypted.set(b"StmF", Object::Name(self.stream_filter.clone()));
+        encrypted.set(b"StrF", Object::Name(self.string_filter.clone()));
+        if !self.embedded_file_filter.is_empty() {
+            encrypted.set(b"EFF", Object::Name(self.embedded_file_filter.clone()));
+        }
+        encrypted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Recipient, RecipientDecryptor, RecipientEncryptor};
+    use crate::creator::tests::create_document;
+    use crate::encryption::{Aes128CryptFilter, Aes256CryptFilter, CryptFilter, DecryptionError};
+    use crate::{Document, EncryptionState, Error, Permissions};
+    use std::sync::Arc;
+
+    /// Stand-in for PKCS#7 enveloping: the content is XORed with the recipient's key and tagged
+    /// with its name.
+    struct TestRecipient(&'static [u8]);
+
+    impl TestRecipient {
+        fn xor(&self, data: &[u8]) -> Vec<u8> {
+            data.iter().zip(self.0.iter().cycle()).map(|(byte, key)| byte ^ key).collect()
+        }
+    }
+
+    impl RecipientEncryptor for TestRecipient {
+        fn encrypt(&self, content: &[u8]) -> crate::Result<Vec<u8>> {
+            Ok([self.0, b":", &self.xor(content)].concat())
+        }
+    }
+
+    impl RecipientDecryptor for TestRecipient {
+        fn decrypt(&self, enveloped_data: &[u8]) -> Option<Vec<u8>> {
+            let content = enveloped_data.strip_prefix(self.0)?.strip_prefix(b":")?;
+            Some(self.xor(content))
+        }
+    }
+
+    #[test]
+    fn encrypt_for_recipients() {
+        let (alice, bob, eve) = (TestRecipient(b"alice"), TestRecipient(b"bob"), TestRecipient(b"eve"));
+        let filters: [Arc<dyn CryptFilter>; 2] = [Arc::new(Aes128CryptFilter), Arc::new(Aes256CryptFilter)];
+        for crypt_filter in filters {
+            let mut document = create_document();
+            let text = document.extract_text(&[1]).unwrap();
+            let recipients = [
+                Recipient { encryptor: &alice, permissions: Permissions::all() },
+                Recipient { encryptor: &bob, permissions: Permissions::PRINTABLE },
+            ];
+            let state = EncryptionState::for_recipients(&recipients, crypt_filter, true).unwrap();
+            document.encrypt(&state).unwrap();
+            let mut bytes = vec![];
+            document.save_to(&mut bytes).unwrap();
+
+            let mut encrypted = Document::load_mem(&bytes).unwrap();
+            assert!(encrypted.is_encrypted());
+            assert!(matches!(
+                encrypted.decrypt_for_recipient(&eve),
+                Err(Error::Decryption(DecryptionError::NoMatchingRecipient))
+            ));
+
+            encrypted.decrypt_for_recipient(&bob).unwrap();
+            assert_eq!(encrypted.extract_text(&[1]).unwrap(), text);
+            let state = encrypted.encryption_state.as_ref().unwrap();
+            assert!(state.permissions().contains(Permissions::PRINTABLE));
+            assert!(!state.permissions().contains(Permissions::MODIFIABLE));
+
+            let mut encrypted = Document::load_mem(&bytes).unwrap();
+            encrypted.decrypt_for_recipient(&alice).unwrap();
+            assert_eq!(encrypted.extract_text(&[1]).unwrap(), text);
+        }
+    }
+}
@@ -30,15 +30,18 @@ pub fn apply_keystream<'i, 'o, Input, Output>(&self, input: Input, output: Outpu
         Input: Iterator<Item = &'i u8>,
         Output: Iterator<Item = &'o mut u8>,
     {
-        let mut state = self.initial_state;
-        let mut i = 0_u8;
-        let mut j = 0_u8;
+        let mut keystream = self.keystream();
         for (i_byte, o_byte) in input.zip(output) {
-            i = i.wrapping_add(1);
-            j = j.wrapping_add(state[i as usize]);
-            state.swap(i as usize, j as usize);
-            let key_byte = state[(state[i as usize].wrapping_add(state[j as usize])) as usize];
-            *o_byte = i_byte ^ key_byte;
+            *o_byte = i_byte ^ keystream.next_byte();
+        }
+    }
+
+    /// Returns the keystream, to encrypt/decrypt content that comes in several parts.
+    pub fn keystream(&self) -> Rc4Keystream {
+        Rc4Keystream {
+            state: self.initial_state,
+            i: 0,
+            j: 0,
         }
     }
 
@@ -64,3 +67,26 @@ pub fn encrypt<Input>(&self, input: Input) -> Vec<u8>
         self.decrypt(input)
     }
 }
+
+/// The state of the RC4 keystream, which continues where the previous part of the content ended.
+pub struct Rc4Keystream {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4Keystream {
+    fn next_byte(&mut self) -> u8 {
+        self.i = self.i.wrapping_add(1);
+        self.j = self.j.wrapping_add(self.state[self.i as usize]);
+        self.state.swap(self.i as usize, self.j as usize);
+        self.state[(self.state[self.i as usize].wrapping_add(self.state[self.j as usize])) as usize]
+    }
+
+    /// Encrypts/decrypts the next part of the content in place.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            *byte ^= self.next_byte();
+        }
+    }
+}
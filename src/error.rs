@@ -40,15 +40,45 @@ pub enum Error {
     /// Invalid inline image.
     #[error("invalid inline image: {0}")]
     InvalidInlineImage(String),
+    /// Invalid or unsupported image file.
+    #[error("invalid image: {0}")]
+    InvalidImage(String),
     /// Invalid document outline.
     #[error("invalid document outline: {0}")]
     InvalidOutline(String),
+    /// Form field with the given fully qualified name was not found.
+    #[error("form field \"{0}\" not found")]
+    FormFieldNotFound(String),
+    /// Value can't be assigned to the form field.
+    #[error("invalid form field value: {0}")]
+    InvalidFormFieldValue(String),
+    /// Invalid FDF or XFDF data.
+    #[error("invalid FDF data: {0}")]
+    InvalidFdf(String),
+    /// The file has no revision with the given index.
+    #[error("revision {0} not found")]
+    RevisionNotFound(usize),
+    /// Signing the document failed.
+    #[error("signature error: {0}")]
+    Signature(String),
+    /// Invalid imposition layout.
+    #[error("invalid imposition layout: {0}")]
+    InvalidImposition(String),
+    /// Page rotation is not a multiple of 90 degrees.
+    #[error("invalid page rotation {0}; must be a multiple of 90")]
+    InvalidRotation(i64),
+    /// The permissions of the encrypted document don't allow the operation.
+    #[error("operation not permitted by the document permissions; requires {0:?}")]
+    PermissionDenied(encryption::Permissions),
     /// Invalid stream.
     #[error("invalid stream: {0}")]
     InvalidStream(String),
     /// Invalid object stream.
     #[error("invalid object stream: {0}")]
     InvalidObjectStream(String),
+    /// Rectangle array does not have four elements.
+    #[error("invalid rectangle: expected 4 numbers but found {0}")]
+    InvalidRectangle(usize),
     /// Byte offset in stream or file is invalid.
     #[error("invalid byte offset")]
     InvalidOffset(usize),
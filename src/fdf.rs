@@ -0,0 +1,702 @@
+mod xml;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use crate::annotation::PRINT_FLAG;
+use crate::form::decode_value;
+use crate::parser::{self, ParserInput};
+use crate::writer::Writer;
+use crate::{
+    Annotation, AnnotationCommon, Dictionary, Document, Error, FieldType, FieldValue, FormField, Object, Reader, Rect,
+    Result, decode_text_string, text_string,
+};
+use xml::Element;
+
+/// Maximum depth of the field hierarchy, guarding against reference loops.
+const FIELD_TREE_DEPTH_LIMIT: usize = 32;
+
+const XFDF_NAMESPACE: &str = "http://ns.adobe.com/xfdf/";
+
+/// The value of a form field in exchanged form data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FdfField {
+    /// Fully qualified field name.
+    pub name: String,
+    pub value: FieldValue,
+}
+
+/// An annotation in exchanged form data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FdfAnnotation {
+    /// Zero-based index of the page the annotation belongs to.
+    pub page: u32,
+    pub annotation: Annotation,
+}
+
+/// Form field values and annotations in Forms Data Format (ISO 32000-1 §12.7.8) or its XML
+/// counterpart XFDF.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fdf {
+    /// The PDF file the data belongs to (`/F`).
+    pub file: Option<String>,
+    pub fields: Vec<FdfField>,
+    pub annotations: Vec<FdfAnnotation>,
+}
+
+/// A node of the field hierarchy, built from fully qualified names when writing.
+struct FieldNode<'a> {
+    name: &'a str,
+    value: Option<&'a FieldValue>,
+    kids: Vec<FieldNode<'a>>,
+}
+
+impl<'a> FieldNode<'a> {
+    fn tree(fields: &'a [FdfField]) -> Vec<FieldNode<'a>> {
+        let mut roots = vec![];
+        for field in fields {
+            let mut nodes = &mut roots;
+            let mut parts = field.name.split('.').peekable();
+            while let Some(part) = parts.next() {
+                let index = match nodes.iter().position(|node: &FieldNode| node.name == part) {
+                    Some(index) => index,
+                    None => {
+                        nodes.push(FieldNode {
+                            name: part,
+                            value: None,
+                            kids: vec![],
+                        });
+                        nodes.len() - 1
+                    }
+                };
+                if parts.peek().is_none() {
+                    nodes[index].value = Some(&field.value);
+                }
+                nodes = &mut nodes[index].kids;
+            }
+        }
+        roots
+    }
+
+    fn to_fdf(&self) -> Object {
+        let mut dict = dictionary! { "T" => text_string(self.name) };
+        match self.value {
+            Some(FieldValue::Text(text)) => dict.set("V", text_string(text)),
+            Some(FieldValue::Name(name)) => dict.set("V", Object::Name(name.as_bytes().to_vec())),
+            Some(FieldValue::Choices(choices)) => dict.set(
+                "V",
+                choices.iter().map(|choice| text_string(choice)).collect::<Vec<_>>(),
+            ),
+            Some(FieldValue::None) | None => {}
+        }
+        if !self.kids.is_empty() {
+            dict.set("Kids", self.kids.iter().map(FieldNode::to_fdf).collect::<Vec<_>>());
+        }
+        Object::Dictionary(dict)
+    }
+
+    fn to_xfdf(&self) -> Element {
+        let mut element = Element::new("field").with_attribute("name", self.name);
+        match self.value {
+            Some(FieldValue::Text(value) | FieldValue::Name(value)) => {
+                element = element.with_child(Element::new("value").with_text(value));
+            }
+            Some(FieldValue::Choices(choices)) => {
+                for choice in choices {
+                    element = element.with_child(Element::new("value").with_text(choice));
+                }
+            }
+            Some(FieldValue::None) | None => {}
+        }
+        self.kids
+            .iter()
+            .fold(element, |element, kid| element.with_child(kid.to_xfdf()))
+    }
+}
+
+impl Fdf {
+    /// Parse an FDF file.
+    pub fn parse(data: &[u8]) -> Result<Fdf> {
+        let reader = Reader {
+            buffer: data,
+            document: Document::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        let (version, objects, trailer) = parser::fdf(ParserInput::new_extra(data, "fdf"), &reader)?;
+        let mut doc = Document::new();
+        doc.version = version;
+        doc.objects.extend(objects);
+        doc.trailer = trailer;
+
+        let catalog = doc.catalog()?;
+        let fdf_dict = doc.dereference(catalog.get(b"FDF")?)?.1.as_dict()?;
+        let mut fdf = Fdf {
+            file: fdf_dict.get(b"F").ok().and_then(|file| file_name(&doc, file)),
+            ..Fdf::default()
+        };
+        if let Ok(fields) = fdf_dict.get(b"Fields") {
+            for field in doc.dereference(fields)?.1.as_array()? {
+                collect_fdf_fields(&doc, field, "", &mut fdf.fields, 0)?;
+            }
+        }
+        if let Ok(annots) = fdf_dict.get(b"Annots") {
+            for annot in doc.dereference(annots)?.1.as_array()? {
+                let dict = doc.dereference(annot)?.1.as_dict()?;
+                if dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Popup") {
+                    continue;
+                }
+                let annotation = match Annotation::from_dict(dict) {
+                    Ok(annotation) => annotation,
+                    Err(Error::Unimplemented(_)) => continue,
+                    Err(err) => return Err(err),
+                };
+                fdf.annotations.push(FdfAnnotation {
+                    page: dict.get(b"Page").and_then(Object::as_i64).unwrap_or(0).max(0) as u32,
+                    annotation,
+                });
+            }
+        }
+        Ok(fdf)
+    }
+
+    /// Serialize as an FDF file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        // The catalog is object 1, the annotations follow.
+        let mut objects = vec![];
+        let mut fdf_dict = Dictionary::new();
+        if let Some(file) = &self.file {
+            fdf_dict.set("F", text_string(file));
+        }
+        if !self.fields.is_empty() {
+            let fields: Vec<Object> = FieldNode::tree(&self.fields).iter().map(FieldNode::to_fdf).collect();
+            fdf_dict.set("Fields", fields);
+        }
+        if !self.annotations.is_empty() {
+            let mut annots = vec![];
+            for (index, annotation) in self.annotations.iter().enumerate() {
+                let id = (index as u32 + 2, 0);
+                let mut dict = annotation.annotation.to_dict();
+                dict.set("Page", annotation.page as i64);
+                objects.push((id, Object::Dictionary(dict)));
+                annots.push(Object::Reference(id));
+            }
+            fdf_dict.set("Annots", annots);
+        }
+        objects.insert(0, ((1, 0), Object::Dictionary(dictionary! { "FDF" => fdf_dict })));
+
+        let mut out = b"%FDF-1.2\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        for (id, object) in &objects {
+            out.extend(format!("{} {} obj\n", id.0, id.1).as_bytes());
+            Writer::write_object(&mut out, object)?;
+            out.extend(b"\nendobj\n");
+        }
+        out.extend(b"trailer\n");
+        Writer::write_object(&mut out, &Object::Dictionary(dictionary! { "Root" => (1, 0) }))?;
+        out.extend(b"\n%%EOF\n");
+        Ok(out)
+    }
+
+    /// Parse an XFDF document.
+    ///
+    /// Field values are read as text; [`Document::import_fdf`] converts them to button states where needed.
+    pub fn parse_xfdf(xfdf: &str) -> Result<Fdf> {
+        let root = xml::parse(xfdf)?;
+        if root.name != "xfdf" {
+            return Err(Error::InvalidFdf(format!(
+                "XFDF: unexpected root element <{}>",
+                root.name
+            )));
+        }
+        let mut fdf = Fdf {
+            file: root.child("f").and_then(|f| f.attribute("href")).map(String::from),
+            ..Fdf::default()
+        };
+        if let Some(fields) = root.child("fields") {
+            for field in fields.elements().filter(|e| e.name == "field") {
+                collect_xfdf_fields(field, "", &mut fdf.fields);
+            }
+        }
+        if let Some(annots) = root.child("annots") {
+            for element in annots.elements() {
+                if let Some(annotation) = annotation_from_xfdf(element)? {
+                    fdf.annotations.push(annotation);
+                }
+            }
+        }
+        Ok(fdf)
+    }
+
+    /// Serialize as an XFDF document.
+    ///
+    /// Only annotations that can be expressed in XFDF are written; links, file attachments and
+    /// popups are skipped.
+    pub fn to_xfdf(&self) -> String {
+        let mut root = Element::new("xfdf")
+            .with_attribute("xmlns", XFDF_NAMESPACE)
+            .with_attribute("xml:space", "preserve");
+        if let Some(file) = &self.file {
+            root = root.with_child(Element::new("f").with_attribute("href", file));
+        }
+        if !self.fields.is_empty() {
+            let fields = FieldNode::tree(&self.fields)
+                .iter()
+                .fold(Element::new("fields"), |fields, node| fields.with_child(node.to_xfdf()));
+            root = root.with_child(fields);
+        }
+        if !self.annotations.is_empty() {
+            let annots = self
+                .annotations
+                .iter()
+                .filter_map(annotation_to_xfdf)
+                .fold(Element::new("annots"), Element::with_child);
+            root = root.with_child(annots);
+        }
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        root.write(&mut out);
+        out.push('\n');
+        out
+    }
+}
+
+fn file_name(doc: &Document, file: &Object) -> Option<String> {
+    match doc.dereference(file).ok()?.1 {
+        Object::Dictionary(spec) => spec
+            .get(b"UF")
+            .or_else(|_| spec.get(b"F"))
+            .and_then(decode_text_string)
+            .ok(),
+        file => decode_text_string(file).ok(),
+    }
+}
+
+fn collect_fdf_fields(
+    doc: &Document, field: &Object, parent: &str, fields: &mut Vec<FdfField>, depth: usize,
+) -> Result<()> {
+    if depth > FIELD_TREE_DEPTH_LIMIT {
+        return Err(Error::InvalidFdf("field hierarchy too deep".to_string()));
+    }
+    let field = doc.dereference(field)?.1.as_dict()?;
+    let partial_name = field.get(b"T").and_then(decode_text_string)?;
+    let name = if parent.is_empty() {
+        partial_name
+    } else {
+        format!("{parent}.{partial_name}")
+    };
+    if let Ok(value) = field.get(b"V") {
+        let value = decode_value(doc.dereference(value)?.1);
+        if value != FieldValue::None {
+            fields.push(FdfField {
+                name: name.clone(),
+                value,
+            });
+        }
+    }
+    if let Ok(kids) = field.get(b"Kids") {
+        for kid in doc.dereference(kids)?.1.as_array()? {
+            collect_fdf_fields(doc, kid, &name, fields, depth + 1)?;
+        }
+    }
+    Ok(())
+}
+
+fn collect_xfdf_fields(field: &Element, parent: &str, fields: &mut Vec<FdfField>) {
+    let partial_name = field.attribute("name").unwrap_or_default();
+    let name = if parent.is_empty() {
+        partial_name.to_string()
+    } else {
+        format!("{parent}.{partial_name}")
+    };
+    let mut values: Vec<String> = field
+        .elements()
+        .filter(|e| e.name == "value")
+        .map(Element::text)
+        .collect();
+    let value = match values.len() {
+        0 => FieldValue::None,
+        1 => FieldValue::Text(values.remove(0)),
+        _ => FieldValue::Choices(values),
+    };
+    if value != FieldValue::None {
+        fields.push(FdfField {
+            name: name.clone(),
+            value,
+        });
+    }
+    for kid in field.elements().filter(|e| e.name == "field") {
+        collect_xfdf_fields(kid, &name, fields);
+    }
+}
+
+fn number_list(values: &[f32]) -> String {
+    values.iter().map(f32::to_string).collect::<Vec<_>>().join(",")
+}
+
+fn parse_numbers(text: &str) -> Result<Vec<f32>> {
+    text.split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| {
+            number
+                .parse()
+                .map_err(|_| Error::InvalidFdf(format!("XFDF: invalid number {number:?}")))
+        })
+        .collect()
+}
+
+/// Format a color as `#RRGGBB`, converting gray and CMYK colors to RGB.
+fn hex_color(color: &[f32]) -> Option<String> {
+    let rgb = match *color {
+        [gray] => [gray; 3],
+        [r, g, b] => [r, g, b],
+        [c, m, y, k] => [(1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)],
+        _ => return None,
+    };
+    let [r, g, b] = rgb.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Some(format!("#{r:02X}{g:02X}{b:02X}"))
+}
+
+fn parse_color(text: &str) -> Result<Vec<f32>> {
+    let invalid = || Error::InvalidFdf(format!("XFDF: invalid color {text:?}"));
+    let hex = text
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .ok_or_else(invalid)?;
+    (0..3)
+        .map(|i| {
+            hex.get(i * 2..i * 2 + 2)
+                .and_then(|component| u8::from_str_radix(component, 16).ok())
+                .map(|component| component as f32 / 255.0)
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
+
+/// XFDF names of the annotation flags.
+const ANNOTATION_FLAG_NAMES: [(&str, u32); 10] = [
+    ("invisible", 1),
+    ("hidden", 1 << 1),
+    ("print", 1 << 2),
+    ("nozoom", 1 << 3),
+    ("norotate", 1 << 4),
+    ("noview", 1 << 5),
+    ("readonly", 1 << 6),
+    ("locked", 1 << 7),
+    ("togglenoview", 1 << 8),
+    ("lockedcontents", 1 << 9),
+];
+
+/// Parse a comma-separated list of annotation flag names, ignoring unknown ones.
+fn parse_annotation_flags(text: &str) -> u32 {
+    text.split(',')
+        .filter_map(|name| ANNOTATION_FLAG_NAMES.iter().find(|(known, _)| *known == name.trim()))
+        .fold(0, |flags, (_, flag)| flags | flag)
+}
+
+fn annotation_to_xfdf(annotation: &FdfAnnotation) -> Option<Element> {
+    let FdfAnnotation { page, annotation } = annotation;
+    let common = annotation.common();
+    let rect = common.rect;
+    let mut element = Element::new(&annotation.subtype().to_lowercase())
+        .with_attribute("page", page)
+        .with_attribute("rect", number_list(&[rect.llx, rect.lly, rect.urx, rect.ury]));
+    if let Some(color) = common.color.as_deref().and_then(hex_color) {
+        element = element.with_attribute("color", color);
+    }
+    if let Some(author) = &common.author {
+        element = element.with_attribute("title", author);
+    }
+    if common.flags != 0 {
+        let names: Vec<&str> = ANNOTATION_FLAG_NAMES
+            .iter()
+            .filter(|(_, flag)| common.flags & flag != 0)
+            .map(|(name, _)| *name)
+            .collect();
+        element = element.with_attribute("flags", names.join(","));
+    }
+    let mut children = vec![];
+    match annotation {
+        Annotation::Text { icon, .. } | Annotation::Stamp { name: icon, .. } => {
+            element = element.with_attribute("icon", icon);
+        }
+        Annotation::FreeText { default_appearance, .. } => {
+            children.push(Element::new("defaultappearance").with_text(default_appearance));
+        }
+        Annotation::Highlight { quad_points, .. }
+        | Annotation::Underline { quad_points, .. }
+        | Annotation::StrikeOut { quad_points, .. }
+        | Annotation::Squiggly { quad_points, .. } => {
+            element = element.with_attribute("coords", number_list(quad_points));
+        }
+        Annotation::Square {
+            interior_color,
+            border_width,
+            ..
+        }
+        | Annotation::Circle {
+            interior_color,
+            border_width,
+            ..
+        } => {
+            element = element.with_attribute("width", border_width);
+            if let Some(color) = interior_color.as_deref().and_then(hex_color) {
+                element = element.with_attribute("interior-color", color);
+            }
+        }
+        Annotation::Line { line, border_width, .. } => {
+            element = element
+                .with_attribute("start", number_list(&line[..2]))
+                .with_attribute("end", number_list(&line[2..]))
+                .with_attribute("width", border_width);
+        }
+        Annotation::Ink {
+            ink_list, border_width, ..
+        } => {
+            element = element.with_attribute("width", border_width);
+            let gestures = ink_list.iter().map(|path| {
+                let points: Vec<String> = path.chunks_exact(2).map(number_list).collect();
+                Element::new("gesture").with_text(&points.join(";"))
+            });
+            children.push(gestures.fold(Element::new("inklist"), Element::with_child));
+        }
+        Annotation::Link { .. } | Annotation::FileAttachment { .. } | Annotation::Popup { .. } => return None,
+    }
+    if let Some(contents) = &common.contents {
+        element = element.with_child(Element::new("contents").with_text(contents));
+    }
+    Some(children.into_iter().fold(element, Element::with_child))
+}
+
+/// Read an XFDF annotation element; unsupported annotation types give `None`.
+fn annotation_from_xfdf(element: &Element) -> Result<Option<FdfAnnotation>> {
+    let missing = |name: &str| Error::InvalidFdf(format!("XFDF: <{}> without {name}", element.name));
+    let numbers = |name: &str| parse_numbers(element.attribute(name).ok_or_else(|| missing(name))?);
+    let page = element
+        .attribute("page")
+        .and_then(|page| page.parse().ok())
+        .ok_or_else(|| missing("page"))?;
+    let rect = match numbers("rect")?.as_slice() {
+        [llx, lly, urx, ury] => Rect::new(*llx, *lly, *urx, *ury),
+        rect => return Err(Error::InvalidRectangle(rect.len())),
+    };
+    let common = AnnotationCommon {
+        rect,
+        contents: element.child("contents").map(Element::text),
+        author: element.attribute("title").map(String::from),
+        color: element.attribute("color").map(parse_color).transpose()?,
+        flags: element.attribute("flags").map_or(PRINT_FLAG, parse_annotation_flags),
+    };
+    let icon = |default: &str| element.attribute("icon").unwrap_or(default).to_string();
+    let border_width = element.attribute("width").and_then(|w| w.parse().ok()).unwrap_or(1.0);
+    let interior_color = element.attribute("interior-color").map(parse_color).transpose()?;
+
+    let annotation = match element.name.as_str() {
+        "text" => Annotation::Text {
+            common,
+            icon: icon("Note"),
+            open: false,
+        },
+        "freetext" => Annotation::FreeText {
+            common,
+            default_appearance: element
+                .child("defaultappearance")
+                .map(Element::text)
+                .unwrap_or_default(),
+        },
+        "highlight" => Annotation::Highlight {
+            common,
+            quad_points: numbers("coords")?,
+        },
+        "underline" => Annotation::Underline {
+            common,
+            quad_points: numbers("coords")?,
+        },
+        "strikeout" => Annotation::StrikeOut {
+            common,
+            quad_points: numbers("coords")?,
+        },
+        "squiggly" => Annotation::Squiggly {
+            common,
+            quad_points: numbers("coords")?,
+        },
+        "square" => Annotation::Square {
+            common,
+            interior_color,
+            border_width,
+        },
+        "circle" => Annotation::Circle {
+            common,
+            interior_color,
+            border_width,
+        },
+        "line" => {
+            let mut line = numbers("start")?;
+            line.extend(numbers("end")?);
+            Annotation::Line {
+                common,
+                line: line.try_into().map_err(|_| missing("start and end points"))?,
+                border_width,
+            }
+        }
+        "ink" => Annotation::Ink {
+            common,
+            ink_list: element
+                .child("inklist")
+                .map(|ink_list| {
+                    ink_list
+                        .elements()
+                        .filter(|e| e.name == "gesture")
+                        .map(|gesture| parse_numbers(&gesture.text()))
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            border_width,
+        },
+        "stamp" => Annotation::Stamp {
+            common,
+            name: icon("Draft"),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(FdfAnnotation { page, annotation }))
+}
+
+impl Document {
+    /// Export the form field values and the annotations of the document.
+    ///
+    /// Fields without a value, push buttons and signature fields are left out, as are links, file
+    /// attachments and popups, which refer to other objects of the document.
+    pub fn export_fdf(&self) -> Result<Fdf> {
+        let fields = self
+            .get_form_fields()?
+            .into_iter()
+            .filter(|field| !matches!(field.field_type, FieldType::PushButton | FieldType::Signature))
+            .filter(|field| field.value != FieldValue::None)
+            .map(|field| FdfField {
+                name: field.name,
+                value: field.value,
+            })
+            .collect();
+        let mut annotations = vec![];
+        for (page_number, page_id) in self.get_pages() {
+            for (_, annotation) in self.get_annotations(page_id)? {
+                if !matches!(
+                    annotation,
+                    Annotation::Link { .. } | Annotation::FileAttachment { .. } | Annotation::Popup { .. }
+                ) {
+                    annotations.push(FdfAnnotation {
+                        page: page_number - 1,
+                        annotation,
+                    });
+                }
+            }
+        }
+        Ok(Fdf {
+            file: None,
+            fields,
+            annotations,
+        })
+    }
+
+    /// Set the field values and add the annotations of exchanged form data.
+    ///
+    /// Text values of checkboxes and radio buttons, as read from XFDF, are taken as state names.
+    /// Fails if a field or page does not exist in the document.
+    pub fn import_fdf(&mut self, fdf: &Fdf) -> Result<()> {
+        let fields: HashMap<String, FormField> = self
+            .get_form_fields()?
+            .into_iter()
+            .map(|field| (field.name.clone(), field))
+            .collect();
+        for FdfField { name, value } in &fdf.fields {
+            let field = fields
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::FormFieldNotFound(name.clone()))?;
+            let value = match (field.field_type, value) {
+                (FieldType::Checkbox | FieldType::Radio, FieldValue::Text(state)) => FieldValue::Name(state.clone()),
+                (FieldType::Text | FieldType::ComboBox | FieldType::ListBox, FieldValue::Name(text)) => {
+                    FieldValue::Text(text.clone())
+                }
+                _ => value.clone(),
+            };
+            self.set_field_value(field, value)?;
+        }
+        let pages = self.get_pages();
+        for FdfAnnotation { page, annotation } in &fdf.annotations {
+            let page_id = *pages
+                .get(&(page + 1))
+                .ok_or_else(|| Error::InvalidFdf(format!("page {page} not in document")))?;
+            self.add_annotation(page_id, annotation)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fdf;
+    use crate::form::tests::create_form_document;
+    use crate::{Annotation, AnnotationCommon, FieldValue, Rect};
+
+    fn filled_document() -> crate::Document {
+        let (mut doc, page_id) = create_form_document();
+        doc.set_form_field_value("person.name", FieldValue::Text("Zoë".to_string()))
+            .unwrap();
+        doc.set_form_field_value("agree", FieldValue::Name("Yes".to_string()))
+            .unwrap();
+        doc.add_annotation(
+            page_id,
+            &Annotation::text(Rect::new(10.0, 10.0, 30.0, 30.0), "a < b & c"),
+        )
+        .unwrap();
+        let ink = Annotation::Ink {
+            common: AnnotationCommon::new(Rect::new(0.0, 0.0, 100.0, 100.0)),
+            ink_list: vec![vec![1.0, 2.0, 3.5, 4.0], vec![5.0, 6.0]],
+            border_width: 2.0,
+        };
+        doc.add_annotation(page_id, &ink).unwrap();
+        doc
+    }
+
+    fn check_import(fdf: &Fdf) {
+        let (mut doc, page_id) = create_form_document();
+        doc.import_fdf(fdf).unwrap();
+        assert_eq!(
+            doc.get_form_field("person.name").unwrap().value,
+            FieldValue::Text("Zoë".to_string())
+        );
+        assert_eq!(
+            doc.get_form_field("agree").unwrap().value,
+            FieldValue::Name("Yes".to_string())
+        );
+        let annotations = doc.get_annotations(page_id).unwrap();
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0].1.contents(), Some("a < b & c"));
+        assert_eq!(annotations[1].1, fdf.annotations[1].annotation);
+    }
+
+    #[test]
+    fn fdf_round_trip() {
+        let fdf = filled_document().export_fdf().unwrap();
+        assert_eq!(fdf.fields.len(), 2);
+        assert_eq!(fdf.annotations.len(), 2);
+
+        let bytes = fdf.to_bytes().unwrap();
+        assert!(bytes.starts_with(b"%FDF-1.2"));
+        let parsed = Fdf::parse(&bytes).unwrap();
+        assert_eq!(parsed, fdf);
+        check_import(&parsed);
+    }
+
+    #[test]
+    fn xfdf_round_trip() {
+        let fdf = filled_document().export_fdf().unwrap();
+        let xfdf = fdf.to_xfdf();
+        assert!(xfdf.contains(r#"flags="print""#));
+        assert!(xfdf.contains(r#"<field name="person"><field name="name"><value>Zoë</value></field></field>"#));
+        let parsed = Fdf::parse_xfdf(&xfdf).unwrap();
+        assert_eq!(parsed.fields[1].value, FieldValue::Text("Yes".to_string()));
+        check_import(&parsed);
+    }
+}
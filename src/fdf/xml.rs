@@ -0,0 +1,300 @@
+//! Minimal XML reader and writer helpers, sufficient for XFDF.
+
+use std::fmt::Write;
+
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn new(name: &str) -> Self {
+        Element {
+            name: name.to_string(),
+            ..Element::default()
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn with_attribute(mut self, name: &str, value: impl ToString) -> Self {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_child(mut self, child: Element) -> Self {
+        self.children.push(Node::Element(child));
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.children.push(Node::Text(text.to_string()));
+        self
+    }
+
+    /// Child elements, ignoring text.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Concatenated text content of the element and its descendants.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for node in &self.children {
+            match node {
+                Node::Element(element) => text.push_str(&element.text()),
+                Node::Text(t) => text.push_str(t),
+            }
+        }
+        text
+    }
+
+    pub fn write(&self, out: &mut String) {
+        let _ = write!(out, "<{}", self.name);
+        for (key, value) in &self.attributes {
+            let _ = write!(out, " {}=\"{}\"", key, escape(value));
+        }
+        if self.children.is_empty() {
+            out.push_str("/>");
+            return;
+        }
+        out.push('>');
+        for node in &self.children {
+            match node {
+                Node::Element(element) => element.write(out),
+                Node::Text(text) => out.push_str(&escape(text)),
+            }
+        }
+        let _ = write!(out, "</{}>", self.name);
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\r' => escaped.push_str("&#13;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Result<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or_else(|| invalid("unterminated entity"))?;
+        let entity = &rest[start + 1..start + end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| invalid(&format!("unknown entity &{entity};")))?,
+        };
+        unescaped.push(c);
+        rest = &rest[start + end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+/// Maximum allowed nesting of elements.
+const MAX_DEPTH: usize = 100;
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidFdf(format!("XFDF: {reason}"))
+}
+
+/// Parse an XML document and return its root element.
+pub(crate) fn parse(input: &str) -> Result<Element> {
+    let mut parser = Parser { input, pos: 0 };
+    parser.skip_misc()?;
+    let root = parser.element(MAX_DEPTH)?;
+    parser.skip_misc()?;
+    if parser.pos < input.len() {
+        return Err(invalid("data after root element"));
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> Result<&'a str> {
+        let end = self
+            .rest()
+            .find(terminator)
+            .ok_or_else(|| invalid(&format!("missing {terminator}")))?;
+        let skipped = &self.input[self.pos..self.pos + end];
+        self.pos += end + terminator.len();
+        Ok(skipped)
+    }
+
+    /// Skip whitespace, the XML declaration, processing instructions, comments and doctype.
+    fn skip_misc(&mut self) -> Result<()> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || "=/>".contains(c))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(invalid("expected a name"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn element(&mut self, depth: usize) -> Result<Element> {
+        if depth == 0 {
+            return Err(invalid("elements nested too deeply"));
+        }
+        if !self.rest().starts_with('<') {
+            return Err(invalid("expected an element"));
+        }
+        self.pos += 1;
+        let mut element = Element::new(&self.name()?);
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(invalid("expected ="));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(invalid("expected a quoted attribute value")),
+            };
+            self.pos += 1;
+            let value = unescape(self.skip_past(&quote.to_string())?)?;
+            element.attributes.push((key, value));
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.pos += 2;
+                if self.name()? != element.name {
+                    return Err(invalid(&format!("mismatched end tag of <{}>", element.name)));
+                }
+                self.skip_past(">")?;
+                return Ok(element);
+            } else if rest.starts_with("<!--") || rest.starts_with("<?") {
+                self.skip_past(if rest.starts_with("<?") { "?>" } else { "-->" })?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?.to_string();
+                element.children.push(Node::Text(text));
+            } else if rest.starts_with('<') {
+                let child = self.element(depth - 1)?;
+                element.children.push(Node::Element(child));
+            } else if rest.is_empty() {
+                return Err(invalid(&format!("unterminated element <{}>", element.name)));
+            } else {
+                let text = unescape(&rest[..rest.find('<').unwrap_or(rest.len())])?;
+                self.pos += rest.find('<').unwrap_or(rest.len());
+                element.children.push(Node::Text(text));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Element, MAX_DEPTH, parse};
+
+    #[test]
+    fn parse_and_write() {
+        let xml = r#"<?xml version="1.0"?><!-- c --><a x='1' y="&lt;&#65;&#x42;">t&amp;<b/><![CDATA[<c>]]></a>"#;
+        let root = parse(xml).unwrap();
+        assert_eq!(root.attribute("y"), Some("<AB"));
+        assert_eq!(root.text(), "t&<c>");
+        assert!(root.child("b").is_some());
+
+        let mut out = String::new();
+        Element::new("a")
+            .with_attribute("v", "\"&")
+            .with_text("<")
+            .write(&mut out);
+        assert_eq!(out, r#"<a v="&quot;&amp;">&lt;</a>"#);
+        assert!(parse("<a><b></a>").is_err());
+    }
+
+    #[test]
+    fn parse_depth_limit() {
+        let nested = |depth| "<a>".repeat(depth) + &"</a>".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+    }
+}
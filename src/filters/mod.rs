@@ -1 +1,84 @@
 pub mod png;
+
+use crate::{Dictionary, dictionary};
+
+/// A filter that [`Stream::encode_with`](crate::Stream::encode_with) can encode stream content with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterSpec {
+    /// `/FlateDecode`, optionally applying a PNG predictor before compressing.
+    Flate(Option<Predictor>),
+    /// `/LZWDecode` with the default early code length change, optionally applying a PNG predictor before compressing.
+    Lzw(Option<Predictor>),
+    /// `/ASCII85Decode`, which only produces printable 7-bit characters.
+    Ascii85,
+}
+
+impl FilterSpec {
+    /// The filter name used in the `/Filter` entry.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterSpec::Flate(_) => "FlateDecode",
+            FilterSpec::Lzw(_) => "LZWDecode",
+            FilterSpec::Ascii85 => "ASCII85Decode",
+        }
+    }
+
+    /// The `/DecodeParms` dictionary of the filter, if it needs one.
+    pub fn decode_params(&self) -> Option<Dictionary> {
+        match self {
+            FilterSpec::Flate(predictor) | FilterSpec::Lzw(predictor) => predictor.map(|p| p.decode_params()),
+            FilterSpec::Ascii85 => None,
+        }
+    }
+}
+
+/// PNG predictor parameters for Flate and LZW encoded image data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Predictor {
+    /// The PNG filter type applied to every row, or `None` to choose one for each row (`/Predictor 15`).
+    pub filter: Option<png::FilterType>,
+    /// Number of color components per sample (`/Colors`).
+    pub colors: u8,
+    /// Number of bits per color component (`/BitsPerComponent`), one of 1, 2, 4, 8 or 16.
+    pub bits_per_component: u8,
+    /// Number of samples per row (`/Columns`).
+    pub columns: u32,
+}
+
+impl Predictor {
+    /// Predictor choosing the PNG filter of each row by heuristic.
+    pub fn new(colors: u8, bits_per_component: u8, columns: u32) -> Self {
+        Predictor {
+            filter: None,
+            colors,
+            bits_per_component,
+            columns,
+        }
+    }
+
+    /// Use the same PNG filter type for every row.
+    pub fn with_filter(mut self, filter: png::FilterType) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Number of bytes a filter looks back to find the corresponding byte of the previous sample.
+    pub fn bytes_per_pixel(&self) -> usize {
+        (usize::from(self.colors) * usize::from(self.bits_per_component) / 8).max(1)
+    }
+
+    /// Number of bytes in a row, not counting the filter type.
+    pub fn bytes_per_row(&self) -> usize {
+        (usize::from(self.colors) * usize::from(self.bits_per_component) * self.columns as usize).div_ceil(8)
+    }
+
+    pub fn decode_params(&self) -> Dictionary {
+        let predictor = self.filter.map_or(15, |filter| 10 + filter as i64);
+        dictionary! {
+            "Predictor" => predictor,
+            "Colors" => i64::from(self.colors),
+            "BitsPerComponent" => i64::from(self.bits_per_component),
+            "Columns" => i64::from(self.columns)
+        }
+    }
+}
@@ -69,7 +69,8 @@ pub fn decode_row(filter: FilterType, bpp: usize, previous: &[u8], current: &mut
             }
 
             for i in bpp..len {
-                current[i] = current[i].wrapping_add((i16::from(current[i - bpp]) + i16::from(previous[i]) / 2) as u8);
+                current[i] =
+                    current[i].wrapping_add(((u16::from(current[i - bpp]) + u16::from(previous[i])) / 2) as u8);
             }
         }
         Paeth => {
@@ -85,7 +86,14 @@ pub fn decode_row(filter: FilterType, bpp: usize, previous: &[u8], current: &mut
 }
 
 pub fn decode_frame(content: &[u8], bytes_per_pixel: usize, pixels_per_row: usize) -> Result<Vec<u8>> {
-    let bytes_per_row = bytes_per_pixel * pixels_per_row;
+    decode_rows(content, bytes_per_pixel, bytes_per_pixel * pixels_per_row)
+}
+
+/// Decode rows of `bytes_per_row` bytes, each preceded by its filter type.
+///
+/// Unlike [`decode_frame`], the row length doesn't have to be a multiple of the pixel size,
+/// as is the case for images with less than 8 bits per component.
+pub fn decode_rows(content: &[u8], bytes_per_pixel: usize, bytes_per_row: usize) -> Result<Vec<u8>> {
     let mut previous = Vec::new();
     previous.try_reserve(bytes_per_row)?;
     previous.resize(bytes_per_row, 0_u8);
@@ -132,7 +140,8 @@ pub fn encode_row(method: FilterType, bpp: usize, previous: &[u8], current: &mut
         }
         Avg => {
             for i in (bpp..len).rev() {
-                current[i] = current[i].wrapping_sub(current[i - bpp].wrapping_add(previous[i]) / 2);
+                current[i] =
+                    current[i].wrapping_sub(((u16::from(current[i - bpp]) + u16::from(previous[i])) / 2) as u8);
             }
 
             for i in 0..bpp {
@@ -150,3 +159,63 @@ pub fn encode_row(method: FilterType, bpp: usize, previous: &[u8], current: &mut
         }
     }
 }
+
+/// Encode rows of `bytes_per_row` bytes, prefixing each of them with its filter type.
+///
+/// With `filter` set to `None` the filter is chosen for every row separately, picking the one
+/// whose output has the smallest sum of absolute values when read as signed bytes.
+pub fn encode_rows(
+    content: &[u8], bytes_per_pixel: usize, bytes_per_row: usize, filter: Option<FilterType>,
+) -> Vec<u8> {
+    use self::FilterType::*;
+    let bytes_per_row = bytes_per_row.max(1);
+    let mut encoded = Vec::with_capacity(content.len() + content.len().div_ceil(bytes_per_row));
+    let mut previous = vec![0_u8; bytes_per_row];
+    let mut current = Vec::with_capacity(bytes_per_row);
+
+    for row in content.chunks(bytes_per_row) {
+        let above = &previous[..row.len()];
+        let method = filter.unwrap_or_else(|| {
+            [None, Sub, Up, Avg, Paeth]
+                .into_iter()
+                .min_by_key(|&method| {
+                    current.clear();
+                    current.extend_from_slice(row);
+                    encode_row(method, bytes_per_pixel, above, &mut current);
+                    current
+                        .iter()
+                        .map(|&b| u64::from((b as i8).unsigned_abs()))
+                        .sum::<u64>()
+                })
+                .unwrap_or(None)
+        });
+        current.clear();
+        current.extend_from_slice(row);
+        encode_row(method, bytes_per_pixel, above, &mut current);
+        encoded.push(method as u8);
+        encoded.extend_from_slice(&current);
+        previous[..row.len()].copy_from_slice(row);
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_rows_round_trip() {
+        let content: Vec<u8> = (0..240_u32).map(|i| (i * i / 7 + i % 13) as u8).collect();
+        for filter in [
+            None,
+            Some(FilterType::Sub),
+            Some(FilterType::Up),
+            Some(FilterType::Avg),
+            Some(FilterType::Paeth),
+        ] {
+            let encoded = encode_rows(&content, 3, 30, filter);
+            assert_eq!(encoded.len(), content.len() + 8);
+            assert_eq!(decode_rows(&encoded, 3, 30).unwrap(), content);
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use crate::content::{Content, Operation};
+use crate::{Annotation, Dictionary, Document, Object, ObjectId, Permissions, Rect, Result, Stream};
+
+/// Annotation flag: don't display or print the annotation.
+const HIDDEN: i64 = 1 << 1;
+/// Annotation flag: print the annotation when the page is printed.
+const PRINT: i64 = 1 << 2;
+
+/// Options for [`Document::flatten_forms`] and [`Document::flatten_annotations`].
+#[derive(Debug, Clone, Default)]
+pub struct FlattenOptions {
+    /// Generate appearance streams before flattening: for all form fields, and for annotations
+    /// that have none. This replaces the appearances of form fields, including ones made by the
+    /// author of the document, so it is off by default.
+    pub update_appearances: bool,
+    /// Only flatten annotations with the print flag set; others are removed.
+    pub only_printable: bool,
+}
+
+/// Bounding box of `rect` transformed by `matrix`.
+fn transform_rect(rect: Rect, matrix: &[f32; 6]) -> Rect {
+    let corners = [
+        (rect.llx, rect.lly),
+        (rect.urx, rect.lly),
+        (rect.llx, rect.ury),
+        (rect.urx, rect.ury),
+    ]
+    .map(|(x, y)| {
+        (
+            matrix[0] * x + matrix[2] * y + matrix[4],
+            matrix[1] * x + matrix[3] * y + matrix[5],
+        )
+    });
+    let xs = corners.map(|(x, _)| x);
+    let ys = corners.map(|(_, y)| y);
+    Rect::new(
+        xs.into_iter().fold(f32::MAX, f32::min),
+        ys.into_iter().fold(f32::MAX, f32::min),
+        xs.into_iter().fold(f32::MIN, f32::max),
+        ys.into_iter().fold(f32::MIN, f32::max),
+    )
+}
+
+impl Document {
+    /// Flatten all form fields into the page content and remove the interactive form.
+    ///
+    /// The normal appearance of each widget annotation is drawn onto its page and the widget is
+    /// removed, together with the `/AcroForm` entry of the catalog. Widgets without an appearance
+    /// are removed without being drawn, as no field refers to them anymore.
+    pub fn flatten_forms(&mut self, options: &FlattenOptions) -> Result<()> {
+        self.check_permissions(Permissions::ANNOTABLE)?;
+        if options.update_appearances {
+            self.update_form_appearances()?;
+        }
+        for page_id in self.get_pages().into_values() {
+            self.flatten_page_annotations(page_id, options, true, |annotation| {
+                annotation.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget")
+            })?;
+        }
+        self.catalog_mut()?.remove(b"AcroForm");
+        Ok(())
+    }
+
+    /// Flatten all annotations except form field widgets into the page content.
+    ///
+    /// Annotations without an appearance stream, like links, are kept. Popups are removed along
+    /// with the annotation they belong to.
+    pub fn flatten_annotations(&mut self, options: &FlattenOptions) -> Result<()> {
+        self.check_permissions(Permissions::ANNOTABLE)?;
+        for page_id in self.get_pages().into_values() {
+            self.flatten_page_annotations(page_id, options, false, |annotation| {
+                annotation.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Widget")
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Flatten the selected annotations of a page. Those that can't be drawn are kept, unless
+    /// `remove_undrawn` is set.
+    fn flatten_page_annotations(
+        &mut self, page_id: ObjectId, options: &FlattenOptions, remove_undrawn: bool,
+        select: impl Fn(&Dictionary) -> bool,
+    ) -> Result<()> {
+        let annotation_ids: Vec<ObjectId> = match self.get_dictionary(page_id)?.get(b"Annots") {
+            Ok(annots) => self
+                .dereference(annots)?
+                .1
+                .as_array()?
+                .iter()
+                .filter_map(|annot| annot.as_reference().ok())
+                .collect(),
+            Err(_) => return Ok(()),
+        };
+
+        let mut operations = vec![];
+        let mut removed = vec![];
+        for annotation_id in annotation_ids {
+            let Ok(annotation) = self.get_dictionary(annotation_id) else {
+                continue;
+            };
+            if !select(annotation) {
+                continue;
+            }
+            let flags = annotation.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+            if flags & HIDDEN != 0 || (options.only_printable && flags & PRINT == 0) {
+                removed.push(annotation_id);
+                continue;
+            }
+            if annotation.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Popup") {
+                continue;
+            }
+
+            let mut appearance_id = self.normal_appearance(annotation);
+            if appearance_id.is_none() && options.update_appearances {
+                if let Some(appearance) = Annotation::from_dict(annotation).ok().and_then(|a| a.appearance()) {
+                    appearance_id = Some(self.add_object(appearance));
+                }
+            }
+            let Some(appearance_id) = appearance_id else {
+                if remove_undrawn {
+                    removed.push(annotation_id);
+                }
+                continue;
+            };
+
+            let annotation = self.get_dictionary(annotation_id)?;
+            let rect = Rect::from_object(annotation.get(b"Rect")?)?;
+            let popup_id = annotation.get(b"Popup").and_then(Object::as_reference).ok();
+
+            // Map the transformed bounding box of the appearance onto the annotation rectangle
+            // (ISO 32000-1 §12.5.5, Algorithm 8.1).
+            let appearance = self.get_object(appearance_id).and_then(Object::as_stream)?;
+            let bbox = appearance.dict.get(b"BBox").and_then(Rect::from_object).unwrap_or(rect);
+            let matrix: [f32; 6] = appearance
+                .dict
+                .get(b"Matrix")
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|m| m.iter().map(|v| v.as_float().ok()).collect::<Option<Vec<f32>>>())
+                .and_then(|m| m.try_into().ok())
+                .unwrap_or([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+            let bbox = transform_rect(bbox, &matrix);
+            if bbox.width() <= 0.0 || bbox.height() <= 0.0 {
+                if remove_undrawn {
+                    removed.push(annotation_id);
+                }
+                continue;
+            }
+            removed.push(annotation_id);
+            removed.extend(popup_id);
+            let (sx, sy) = (rect.width() / bbox.width(), rect.height() / bbox.height());
+            let placement = [sx, 0.0, 0.0, sy, rect.llx - bbox.llx * sx, rect.lly - bbox.lly * sy];
+
+            let name = self.add_flattened_xobject(page_id, appearance_id)?;
+            operations.push(Operation::new("q", vec![]));
+            operations.push(Operation::new(
+                "cm",
+                placement.iter().map(|v| Object::Real(*v)).collect(),
+            ));
+            operations.push(Operation::new("Do", vec![Object::Name(name)]));
+            operations.push(Operation::new("Q", vec![]));
+        }
+
+        if !operations.is_empty() {
+            // Isolate the existing content so that its graphics state doesn't leak into ours.
+            let prefix_id = self.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+            operations.insert(0, Operation::new("Q", vec![]));
+            let mut suffix = b"\n".to_vec();
+            suffix.extend(Content { operations }.encode()?);
+            let suffix_id = self.add_object(Stream::new(Dictionary::new(), suffix));
+            let mut contents = vec![Object::Reference(prefix_id)];
+            contents.extend(self.get_page_contents(page_id).into_iter().map(Object::Reference));
+            contents.push(Object::Reference(suffix_id));
+            self.get_dictionary_mut(page_id)?.set("Contents", contents);
+        }
+
+        if !removed.is_empty() {
+            let page = self.get_dictionary(page_id)?;
+            let annots_id = page.get(b"Annots").and_then(Object::as_reference).ok();
+            let annots = match annots_id {
+                Some(id) => self.get_object_mut(id)?.as_array_mut()?,
+                None => self.get_dictionary_mut(page_id)?.get_mut(b"Annots")?.as_array_mut()?,
+            };
+            annots.retain(|annot| annot.as_reference().map_or(true, |id| !removed.contains(&id)));
+            if annots.is_empty() {
+                self.get_dictionary_mut(page_id)?.remove(b"Annots");
+            }
+            for id in removed {
+                self.objects.remove(&id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Object ID of the normal appearance stream of an annotation, selected by `/AS` if the
+    /// annotation has several appearance states.
+    fn normal_appearance(&self, annotation: &Dictionary) -> Option<ObjectId> {
+        let ap = self.dereference(annotation.get(b"AP").ok()?).ok()?.1.as_dict().ok()?;
+        let normal = ap.get(b"N").ok()?;
+        match self.dereference(normal).ok()? {
+            (Some(id), Object::Stream(_)) => Some(id),
+            (_, Object::Dictionary(states)) => {
+                let state = annotation.get(b"AS").and_then(Object::as_name).ok()?;
+                let id = states.get(state).and_then(Object::as_reference).ok()?;
+                self.get_object(id).and_then(Object::as_stream).ok().map(|_| id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Register an XObject in the page resources under a name the page doesn't use yet, first
+    /// copying inherited resources to the page so that they are not shadowed. Returns the name.
+    fn add_flattened_xobject(&mut self, page_id: ObjectId, xobject_id: ObjectId) -> Result<Vec<u8>> {
+        if !self.get_dictionary(page_id)?.has(b"Resources") {
+            let inherited = self
+                .get_inherited_page_attribute(page_id, b"Resources")
+                .and_then(|resources| self.dereference(resources))
+                .and_then(|(_, resources)| resources.as_dict())
+                .cloned()
+                .unwrap_or_default();
+            self.get_dictionary_mut(page_id)?.set("Resources", inherited);
+        }
+        let xobjects = self
+            .get_dictionary(page_id)?
+            .get_deref(b"Resources", self)
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get_deref(b"XObject", self))
+            .and_then(Object::as_dict)
+            .ok();
+        let mut number = 1;
+        while xobjects.is_some_and(|xobjects| xobjects.has(format!("Flat{number}").as_bytes())) {
+            number += 1;
+        }
+        let name = format!("Flat{number}").into_bytes();
+        self.add_xobject(page_id, name.clone(), xobject_id)?;
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlattenOptions;
+    use crate::content::Content;
+    use crate::form::tests::create_form_document;
+    use crate::{Annotation, FieldValue, Object, Rect};
+
+    #[test]
+    fn flatten_form() {
+        let (mut doc, page_id) = create_form_document();
+        doc.set_form_field_value("person.name", FieldValue::Text("Jane".to_string()))
+            .unwrap();
+        doc.set_form_field_value("agree", FieldValue::Name("Yes".to_string()))
+            .unwrap();
+        doc.add_annotation(page_id, &Annotation::text(Rect::new(0.0, 0.0, 20.0, 20.0), "note"))
+            .unwrap();
+
+        doc.flatten_forms(&FlattenOptions::default()).unwrap();
+        assert!(doc.catalog().unwrap().get(b"AcroForm").is_err());
+        assert!(doc.get_form_fields().unwrap().is_empty());
+        // Only the sticky note is left.
+        assert_eq!(doc.get_page_annotations(page_id).unwrap().len(), 1);
+
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let operators: Vec<_> = content.operations.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(operators.first(), Some(&"q"));
+        // Text field, checkbox and the three radio buttons.
+        assert_eq!(operators.iter().filter(|op| **op == "Do").count(), 5);
+        // Fonts inherited from the page tree are still available.
+        assert_eq!(doc.get_page_fonts(page_id).unwrap().len(), 1);
+
+        doc.flatten_annotations(&FlattenOptions::default()).unwrap();
+        assert!(doc.get_page_annotations(page_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn flatten_keeps_existing_xobject_names() {
+        let (mut doc, page_id) = create_form_document();
+        let existing_id = doc.add_object(crate::xobject::form(
+            vec![0.0, 0.0, 10.0, 10.0],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            vec![],
+        ));
+        doc.add_xobject(page_id, "Flat1", existing_id).unwrap();
+
+        doc.flatten_forms(&FlattenOptions::default()).unwrap();
+        let xobjects = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get_deref(b"Resources", &doc)
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get_deref(b"XObject", &doc))
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert_eq!(xobjects.get(b"Flat1").unwrap().as_reference().unwrap(), existing_id);
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let names: Vec<_> = content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Do")
+            .map(|op| op.operands[0].as_name().unwrap())
+            .collect();
+        assert_eq!(names.len(), 4);
+        assert!(!names.contains(&b"Flat1".as_slice()));
+        assert!(names.iter().all(|name| xobjects.has(name)));
+    }
+
+    #[test]
+    fn flatten_form_without_appearances() {
+        let (mut doc, page_id) = create_form_document();
+        // The text field has no appearance, and none is generated by default.
+        doc.flatten_forms(&FlattenOptions::default()).unwrap();
+        assert!(doc.get_page_annotations(page_id).unwrap().is_empty());
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        // The checkbox and the three radio buttons.
+        assert_eq!(content.operations.iter().filter(|op| op.operator == "Do").count(), 4);
+    }
+
+    #[test]
+    fn keep_annotation_with_empty_bbox() {
+        let (mut doc, page_id) = create_form_document();
+        let appearance_id = doc.add_object(crate::xobject::form(
+            vec![0.0, 0.0, 0.0, 0.0],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            b"0 0 10 10 re f".to_vec(),
+        ));
+        let mut note = Annotation::text(Rect::new(0.0, 0.0, 20.0, 20.0), "note").to_dict();
+        note.set("AP", dictionary! { "N" => appearance_id });
+        let note_id = doc.add_object(note);
+        doc.get_dictionary_mut(page_id)
+            .unwrap()
+            .get_mut(b"Annots")
+            .unwrap()
+            .as_array_mut()
+            .unwrap()
+            .push(Object::Reference(note_id));
+
+        doc.flatten_annotations(&FlattenOptions::default()).unwrap();
+        assert!(doc.objects.contains_key(&note_id));
+        assert_eq!(doc.get_page_annotations(page_id).unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,566 @@
+mod appearance;
+
+use std::collections::HashSet;
+
+use bitflags::bitflags;
+use log::warn;
+
+use crate::{Dictionary, Document, Error, Object, ObjectId, Permissions, Result, decode_text_string, text_string};
+
+/// Maximum depth of the field hierarchy, guarding against reference loops.
+const FIELD_TREE_DEPTH_LIMIT: usize = 32;
+
+/// Type of an interactive form field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Text,
+    Checkbox,
+    Radio,
+    PushButton,
+    ComboBox,
+    ListBox,
+    Signature,
+}
+
+bitflags! {
+    /// Field flags (`/Ff`), ISO 32000-1 Tables 221, 226, 228 and 230.
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    pub struct FieldFlags: u32 {
+        const READ_ONLY = 1;
+        const REQUIRED = 1 << 1;
+        const NO_EXPORT = 1 << 2;
+
+        const MULTILINE = 1 << 12;
+        const PASSWORD = 1 << 13;
+        const FILE_SELECT = 1 << 20;
+        const DO_NOT_SPELL_CHECK = 1 << 22;
+        const DO_NOT_SCROLL = 1 << 23;
+        /// Text fields divided into `/MaxLen` equally spaced positions.
+        const COMB = 1 << 24;
+        const RICH_TEXT = 1 << 25;
+
+        const NO_TOGGLE_TO_OFF = 1 << 14;
+        const RADIO = 1 << 15;
+        const PUSHBUTTON = 1 << 16;
+        const RADIOS_IN_UNISON = 1 << 25;
+
+        const COMBO = 1 << 17;
+        const EDIT = 1 << 18;
+        const SORT = 1 << 19;
+        const MULTI_SELECT = 1 << 21;
+        const COMMIT_ON_SEL_CHANGE = 1 << 26;
+    }
+}
+
+/// Value of a form field (`/V`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    None,
+    /// Value of text fields and single-selection choice fields.
+    Text(String),
+    /// State of checkboxes and radio buttons, e.g. `Yes` or `Off`.
+    Name(String),
+    /// Selected options of multiple-selection list boxes.
+    Choices(Vec<String>),
+}
+
+/// A terminal form field, i.e. a field holding a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormField {
+    pub id: ObjectId,
+    /// Fully qualified name, the partial names of the field and its ancestors joined by periods.
+    pub name: String,
+    pub field_type: FieldType,
+    pub flags: FieldFlags,
+    pub value: FieldValue,
+    /// Default appearance string (`/DA`), inherited from ancestors or the AcroForm dictionary.
+    pub default_appearance: Option<String>,
+    /// For choice fields, the `(export value, display text)` pairs of `/Opt`.
+    /// For checkboxes and radio buttons, the names of the "on" states of the widgets.
+    pub options: Vec<(String, String)>,
+    /// Maximum length of text fields (`/MaxLen`).
+    pub max_len: Option<u32>,
+    /// The widget annotations of the field; the field dictionary itself if it has no kids.
+    pub widgets: Vec<ObjectId>,
+}
+
+pub(crate) fn decode_value(object: &Object) -> FieldValue {
+    match object {
+        Object::Name(name) => FieldValue::Name(String::from_utf8_lossy(name).into_owned()),
+        Object::String(..) => decode_text_string(object).map_or(FieldValue::None, FieldValue::Text),
+        Object::Array(values) => {
+            FieldValue::Choices(values.iter().filter_map(|v| decode_text_string(v).ok()).collect())
+        }
+        _ => FieldValue::None,
+    }
+}
+
+impl Document {
+    /// Get the interactive form dictionary of the catalog.
+    fn acro_form(&self) -> Result<&Dictionary> {
+        let acro_form = self.catalog()?.get(b"AcroForm")?;
+        self.dereference(acro_form)?.1.as_dict()
+    }
+
+    /// Look up an inheritable field attribute in the field or its ancestors.
+    fn inherited_field_attribute(&self, field_id: ObjectId, key: &[u8]) -> Option<&Object> {
+        let mut node = self.get_dictionary(field_id).ok()?;
+        for _ in 0..FIELD_TREE_DEPTH_LIMIT {
+            if let Ok(value) = node.get(key) {
+                return self.dereference(value).ok().map(|(_, value)| value);
+            }
+            node = node
+                .get(b"Parent")
+                .and_then(Object::as_reference)
+                .and_then(|id| self.get_dictionary(id))
+                .ok()?;
+        }
+        None
+    }
+
+    fn collect_form_fields(
+        &self, field_id: ObjectId, parent_name: &str, fields: &mut Vec<FormField>, seen: &mut HashSet<ObjectId>,
+    ) -> Result<()> {
+        if !seen.insert(field_id) || seen.len() > 100_000 {
+            return Ok(());
+        }
+        let Ok(field) = self.get_dictionary(field_id) else {
+            warn!("Skipping invalid form field {field_id:?}");
+            return Ok(());
+        };
+        let name = match field.get(b"T").and_then(decode_text_string) {
+            Ok(partial) if parent_name.is_empty() => partial,
+            Ok(partial) => format!("{parent_name}.{partial}"),
+            Err(_) => parent_name.to_string(),
+        };
+
+        let kids: Vec<ObjectId> = match field.get(b"Kids") {
+            Ok(kids) => match self.dereference(kids).and_then(|(_, kids)| kids.as_array()) {
+                Ok(kids) => kids.iter().filter_map(|kid| kid.as_reference().ok()).collect(),
+                Err(_) => {
+                    warn!("Skipping invalid kids of form field {field_id:?}");
+                    vec![]
+                }
+            },
+            Err(_) => vec![],
+        };
+        // Kids without a partial name are widget annotations of this field.
+        let child_fields: Vec<ObjectId> = kids
+            .iter()
+            .copied()
+            .filter(|kid| self.get_dictionary(*kid).is_ok_and(|kid| kid.has(b"T")))
+            .collect();
+        if !child_fields.is_empty() {
+            for kid in child_fields {
+                self.collect_form_fields(kid, &name, fields, seen)?;
+            }
+            return Ok(());
+        }
+
+        let widgets = if kids.is_empty() { vec![field_id] } else { kids };
+        if let Some(form_field) = self.read_form_field(field_id, name, widgets) {
+            fields.push(form_field);
+        }
+        Ok(())
+    }
+
+    fn read_form_field(&self, field_id: ObjectId, name: String, widgets: Vec<ObjectId>) -> Option<FormField> {
+        let flags = self
+            .inherited_field_attribute(field_id, b"Ff")
+            .and_then(|ff| ff.as_i64().ok())
+            .map_or(FieldFlags::empty(), |ff| FieldFlags::from_bits_retain(ff as u32));
+        let field_type = match self.inherited_field_attribute(field_id, b"FT")?.as_name().ok()? {
+            b"Tx" => FieldType::Text,
+            b"Btn" if flags.contains(FieldFlags::PUSHBUTTON) => FieldType::PushButton,
+            b"Btn" if flags.contains(FieldFlags::RADIO) => FieldType::Radio,
+            b"Btn" => FieldType::Checkbox,
+            b"Ch" if flags.contains(FieldFlags::COMBO) => FieldType::ComboBox,
+            b"Ch" => FieldType::ListBox,
+            b"Sig" => FieldType::Signature,
+            _ => return None,
+        };
+        let value = self
+            .inherited_field_attribute(field_id, b"V")
+            .map_or(FieldValue::None, decode_value);
+        let default_appearance = self
+            .inherited_field_attribute(field_id, b"DA")
+            .or_else(|| self.acro_form().ok()?.get(b"DA").ok())
+            .and_then(|da| da.as_str().ok())
+            .map(|da| String::from_utf8_lossy(da).into_owned());
+
+        let options = match field_type {
+            FieldType::ComboBox | FieldType::ListBox => self
+                .get_dictionary(field_id)
+                .ok()?
+                .get(b"Opt")
+                .and_then(|opt| Ok(self.dereference(opt)?.1.as_array()?.as_slice()))
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|option| match option {
+                    Object::Array(pair) if pair.len() == 2 => {
+                        Some((decode_text_string(&pair[0]).ok()?, decode_text_string(&pair[1]).ok()?))
+                    }
+                    _ => {
+                        let text = decode_text_string(option).ok()?;
+                        Some((text.clone(), text))
+                    }
+                })
+                .collect(),
+            FieldType::Checkbox | FieldType::Radio => {
+                let mut states = vec![];
+                for state in widgets.iter().flat_map(|id| self.widget_on_states(*id)) {
+                    if !states.contains(&state) {
+                        states.push(state);
+                    }
+                }
+                states.into_iter().map(|state| (state.clone(), state)).collect()
+            }
+            _ => vec![],
+        };
+        let max_len = self
+            .inherited_field_attribute(field_id, b"MaxLen")
+            .and_then(|max_len| max_len.as_i64().ok())
+            .and_then(|max_len| u32::try_from(max_len).ok());
+
+        Some(FormField {
+            id: field_id,
+            name,
+            field_type,
+            flags,
+            value,
+            default_appearance,
+            options,
+            max_len,
+            widgets,
+        })
+    }
+
+    /// Names of the appearance states of a widget other than `Off`.
+    fn widget_on_states(&self, widget_id: ObjectId) -> Vec<String> {
+        let normal = self
+            .get_dictionary(widget_id)
+            .and_then(|widget| widget.get(b"AP"))
+            .and_then(|ap| self.dereference(ap))
+            .and_then(|(_, ap)| ap.as_dict())
+            .and_then(|ap| ap.get(b"N"))
+            .and_then(|n| self.dereference(n))
+            .and_then(|(_, n)| n.as_dict());
+        match normal {
+            Ok(normal) => normal
+                .iter()
+                .map(|(state, _)| String::from_utf8_lossy(state).into_owned())
+                .filter(|state| state != "Off")
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// List the terminal fields of the interactive form, in document order.
+    pub fn get_form_fields(&self) -> Result<Vec<FormField>> {
+        let Ok(acro_form) = self.acro_form() else {
+            return Ok(vec![]);
+        };
+        let roots: Vec<ObjectId> = match acro_form.get(b"Fields") {
+            Ok(fields) => self
+                .dereference(fields)?
+                .1
+                .as_array()?
+                .iter()
+                .filter_map(|field| field.as_reference().ok())
+                .collect(),
+            Err(_) => vec![],
+        };
+        let mut fields = vec![];
+        let mut seen = HashSet::new();
+        for root in roots {
+            self.collect_form_fields(root, "", &mut fields, &mut seen)?;
+        }
+        Ok(fields)
+    }
+
+    /// Get a terminal field by its fully qualified name.
+    pub fn get_form_field(&self, name: &str) -> Result<FormField> {
+        self.get_form_fields()?
+            .into_iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| Error::FormFieldNotFound(name.to_string()))
+    }
+
+    /// Set the value of a field, given by its fully qualified name.
+    ///
+    /// For checkboxes and radio buttons, the value is the name of an "on" state or `Off`; the
+    /// appearance state (`/AS`) of every widget is updated to match. For other fields the value is
+    /// stored in `/V` and the appearance streams of the widgets are regenerated.
+    pub fn set_form_field_value(&mut self, name: &str, value: FieldValue) -> Result<()> {
+        let field = self.get_form_field(name)?;
+        self.set_field_value(field, value)
+    }
+
+    /// Set the value of a field that was already looked up.
+    pub(crate) fn set_field_value(&mut self, mut field: FormField, value: FieldValue) -> Result<()> {
+        // Filling in forms is also allowed by the permission to annotate.
+        self.check_permissions(Permissions::FILLABLE)
+            .or_else(|_| self.check_permissions(Permissions::ANNOTABLE))?;
+        let name = field.name.as_str();
+        let invalid = |reason: &str| Err(Error::InvalidFormFieldValue(format!("{name}: {reason}")));
+        if field.flags.contains(FieldFlags::READ_ONLY) {
+            return invalid("field is read-only");
+        }
+
+        let value_object = match (field.field_type, &value) {
+            (FieldType::PushButton | FieldType::Signature, _) => return invalid("field has no settable value"),
+            (_, FieldValue::None) => None,
+            (FieldType::Checkbox | FieldType::Radio, FieldValue::Name(state)) => {
+                if state != "Off" && !field.options.iter().any(|(on, _)| on == state) {
+                    return invalid("unknown button state");
+                }
+                Some(Object::Name(state.as_bytes().to_vec()))
+            }
+            (FieldType::Checkbox | FieldType::Radio, _) => return invalid("buttons take a state name"),
+            (FieldType::Text | FieldType::ComboBox | FieldType::ListBox, FieldValue::Text(text)) => {
+                if field
+                    .max_len
+                    .is_some_and(|max_len| text.chars().count() > max_len as usize)
+                {
+                    return invalid("text exceeds maximum length");
+                }
+                Some(text_string(text))
+            }
+            (FieldType::ListBox, FieldValue::Choices(choices)) => Some(Object::Array(
+                choices.iter().map(|choice| text_string(choice)).collect(),
+            )),
+            _ => return invalid("value doesn't match field type"),
+        };
+
+        let dict = self.get_dictionary_mut(field.id)?;
+        match value_object {
+            Some(value_object) => dict.set("V", value_object),
+            None => {
+                dict.remove(b"V");
+            }
+        }
+
+        match (field.field_type, &value) {
+            (FieldType::Checkbox | FieldType::Radio, _) => {
+                let state = match &value {
+                    FieldValue::Name(state) => state.as_str(),
+                    _ => "Off",
+                };
+                for widget_id in &field.widgets {
+                    let has_state = self.widget_on_states(*widget_id).iter().any(|on| on == state);
+                    let appearance_state = if has_state { state } else { "Off" };
+                    self.get_dictionary_mut(*widget_id)?
+                        .set("AS", Object::Name(appearance_state.as_bytes().to_vec()));
+                }
+            }
+            (FieldType::ListBox, _) => {
+                let selected: Vec<&String> = match &value {
+                    FieldValue::Text(text) => vec![text],
+                    FieldValue::Choices(choices) => choices.iter().collect(),
+                    _ => vec![],
+                };
+                let indices: Vec<Object> = field
+                    .options
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (export, _))| selected.contains(&export))
+                    .map(|(index, _)| Object::Integer(index as i64))
+                    .collect();
+                self.get_dictionary_mut(field.id)?.set("I", indices);
+                field.value = value;
+                self.refresh_form_field_appearance(&field)?;
+            }
+            _ => {
+                field.value = value;
+                self.refresh_form_field_appearance(&field)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Regenerate the appearance of a field after its value changed, falling back to asking
+    /// viewers to do so if it can't be generated.
+    fn refresh_form_field_appearance(&mut self, field: &FormField) -> Result<()> {
+        if self.update_form_field_appearance(field).is_err() {
+            self.set_need_appearances()?;
+        }
+        Ok(())
+    }
+
+    fn set_need_appearances(&mut self) -> Result<()> {
+        let acro_form = self.catalog()?.get(b"AcroForm")?;
+        let acro_form = match acro_form {
+            Object::Reference(id) => self.get_dictionary_mut(*id)?,
+            _ => self.catalog_mut()?.get_mut(b"AcroForm")?.as_dict_mut()?,
+        };
+        acro_form.set("NeedAppearances", true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{FieldFlags, FieldType, FieldValue};
+    use crate::creator::tests::create_document;
+    use crate::{Document, Object, ObjectId, Rect};
+
+    /// Add a form with a text field `person.name`, a checkbox `agree` and a radio group `size`
+    /// to the first page of a test document.
+    pub fn create_form_document() -> (Document, ObjectId) {
+        let mut doc = create_document();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let widget = |rect: Rect| {
+            dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "Rect" => rect,
+                "P" => page_id,
+            }
+        };
+        let states = |doc: &mut Document, on: &str| {
+            let on_id = doc.add_object(crate::xobject::form(
+                vec![0.0, 0.0, 10.0, 10.0],
+                vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                b"0 0 10 10 re f".to_vec(),
+            ));
+            let off_id = doc.add_object(crate::xobject::form(
+                vec![0.0, 0.0, 10.0, 10.0],
+                vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                vec![],
+            ));
+            dictionary! { "N" => dictionary! { on => on_id, "Off" => off_id } }
+        };
+
+        let person_id = doc.new_object_id();
+        let mut name = widget(Rect::new(100.0, 700.0, 300.0, 720.0));
+        name.set("Parent", person_id);
+        name.set("T", Object::string_literal("name"));
+        name.set("FT", "Tx");
+        name.set("MaxLen", 20);
+        name.set("V", Object::string_literal("John"));
+        let name_id = doc.add_object(name);
+        doc.objects.insert(
+            person_id,
+            Object::Dictionary(dictionary! { "T" => Object::string_literal("person"), "Kids" => vec![name_id.into()] }),
+        );
+
+        let mut agree = widget(Rect::new(100.0, 650.0, 110.0, 660.0));
+        agree.set("T", Object::string_literal("agree"));
+        agree.set("FT", "Btn");
+        agree.set("V", "Off");
+        agree.set("AS", "Off");
+        agree.set("AP", states(&mut doc, "Yes"));
+        let agree_id = doc.add_object(agree);
+
+        let size_id = doc.new_object_id();
+        let mut radio_ids = vec![];
+        for (index, state) in ["S", "M", "L"].iter().enumerate() {
+            let x = 100.0 + 20.0 * index as f32;
+            let mut radio = widget(Rect::new(x, 600.0, x + 10.0, 610.0));
+            radio.set("Parent", size_id);
+            radio.set("AS", "Off");
+            radio.set("AP", states(&mut doc, state));
+            radio_ids.push(Object::Reference(doc.add_object(radio)));
+        }
+        doc.objects.insert(
+            size_id,
+            Object::Dictionary(dictionary! {
+                "T" => Object::string_literal("size"),
+                "FT" => "Btn",
+                "Ff" => FieldFlags::RADIO.bits() as i64,
+                "Kids" => radio_ids.clone(),
+            }),
+        );
+
+        let mut annots = vec![name_id.into(), agree_id.into()];
+        annots.extend(radio_ids);
+        doc.get_dictionary_mut(page_id).unwrap().set("Annots", annots);
+        let acro_form_id = doc.add_object(dictionary! {
+            "Fields" => vec![person_id.into(), agree_id.into(), size_id.into()],
+            "DA" => Object::string_literal("/Helv 0 Tf 0 g"),
+        });
+        doc.catalog_mut().unwrap().set("AcroForm", acro_form_id);
+        (doc, page_id)
+    }
+
+    #[test]
+    fn list_fields() {
+        let (doc, _) = create_form_document();
+        let fields = doc.get_form_fields().unwrap();
+        let names: Vec<_> = fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, ["person.name", "agree", "size"]);
+
+        assert_eq!(fields[0].field_type, FieldType::Text);
+        assert_eq!(fields[0].value, FieldValue::Text("John".to_string()));
+        assert_eq!(fields[0].default_appearance.as_deref(), Some("/Helv 0 Tf 0 g"));
+        assert_eq!(fields[0].widgets, vec![fields[0].id]);
+        assert_eq!(fields[1].field_type, FieldType::Checkbox);
+        assert_eq!(fields[2].field_type, FieldType::Radio);
+        assert_eq!(fields[2].widgets.len(), 3);
+        let states: Vec<_> = fields[2].options.iter().map(|(state, _)| state.as_str()).collect();
+        assert_eq!(states, ["S", "M", "L"]);
+    }
+
+    #[test]
+    fn skip_dangling_fields() {
+        let (mut doc, _) = create_form_document();
+        let acro_form_id = doc.catalog().unwrap().get(b"AcroForm").unwrap().as_reference().unwrap();
+        doc.get_dictionary_mut(acro_form_id)
+            .unwrap()
+            .get_mut(b"Fields")
+            .unwrap()
+            .as_array_mut()
+            .unwrap()
+            .insert(0, Object::Reference((999, 0)));
+        assert_eq!(doc.get_form_fields().unwrap().len(), 3);
+        doc.set_form_field_value("agree", FieldValue::Name("Yes".to_string()))
+            .unwrap();
+    }
+
+    #[test]
+    fn fill_fields() {
+        let (mut doc, _) = create_form_document();
+        doc.set_form_field_value("person.name", FieldValue::Text("Jane".to_string()))
+            .unwrap();
+        doc.set_form_field_value("agree", FieldValue::Name("Yes".to_string()))
+            .unwrap();
+        doc.set_form_field_value("size", FieldValue::Name("M".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            doc.get_form_field("person.name").unwrap().value,
+            FieldValue::Text("Jane".to_string())
+        );
+        let size = doc.get_form_field("size").unwrap();
+        assert_eq!(size.value, FieldValue::Name("M".to_string()));
+        let appearance_states: Vec<_> = size
+            .widgets
+            .iter()
+            .map(|id| doc.get_dictionary(*id).unwrap().get(b"AS").unwrap().as_name().unwrap())
+            .collect();
+        assert_eq!(appearance_states, [b"Off".as_slice(), b"M", b"Off"]);
+
+        assert!(
+            doc.set_form_field_value("agree", FieldValue::Name("Maybe".to_string()))
+                .is_err()
+        );
+        assert!(
+            doc.set_form_field_value("person.name", FieldValue::Text("x".repeat(21)))
+                .is_err()
+        );
+        assert!(doc.set_form_field_value("missing", FieldValue::None).is_err());
+
+        let name_id = doc.get_form_field("person.name").unwrap().id;
+        doc.get_dictionary_mut(name_id)
+            .unwrap()
+            .set("Ff", FieldFlags::READ_ONLY.bits() as i64);
+        assert!(
+            doc.set_form_field_value("person.name", FieldValue::Text("Joe".to_string()))
+                .is_err()
+        );
+        assert_eq!(
+            doc.get_form_field("person.name").unwrap().value,
+            FieldValue::Text("Jane".to_string())
+        );
+    }
+}
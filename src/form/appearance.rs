@@ -0,0 +1,594 @@
+use crate::annotation::{DefaultAppearance, color_operation, helvetica, is_win_ansi_font, win_ansi_string};
+use crate::content::{Content, Operation};
+use crate::encodings::{self, WIN_ANSI_ENCODING};
+use crate::{Dictionary, Document, Object, ObjectId, Rect, Result, Stream, xobject};
+
+use super::{FieldFlags, FieldType, FieldValue, FormField};
+
+/// Padding between the widget border and the text.
+const PADDING: f32 = 2.0;
+/// Font size used when the default appearance asks for auto sizing and the text fits.
+const MAX_AUTO_FONT_SIZE: f32 = 12.0;
+const MIN_AUTO_FONT_SIZE: f32 = 4.0;
+/// Distance between baselines, relative to the font size.
+const LINE_HEIGHT: f32 = 1.15;
+
+/// Glyph widths of Helvetica for the codes 32 to 126, used for fonts without `/Widths`.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556,
+    556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334,
+    260, 334, 584,
+];
+
+/// Glyph widths of a simple font, in thousandths of the font size.
+struct FontMetrics {
+    first_char: usize,
+    widths: Vec<f32>,
+    default_width: f32,
+}
+
+impl FontMetrics {
+    fn from_font(doc: &Document, font: Option<&Dictionary>) -> Self {
+        let widths = font.and_then(|font| {
+            let first_char = font.get(b"FirstChar").and_then(Object::as_i64).ok()?;
+            let widths = doc.dereference(font.get(b"Widths").ok()?).ok()?.1.as_array().ok()?;
+            Some((first_char, widths.iter().map(|w| w.as_float().unwrap_or(0.0)).collect()))
+        });
+        match widths {
+            Some((first_char, widths)) => FontMetrics {
+                first_char: first_char.max(0) as usize,
+                widths,
+                default_width: 500.0,
+            },
+            None => {
+                let monospaced = font
+                    .and_then(|font| font.get(b"BaseFont").and_then(Object::as_name).ok())
+                    .is_some_and(|name| name.starts_with(b"Courier"));
+                FontMetrics {
+                    first_char: 32,
+                    widths: if monospaced {
+                        vec![]
+                    } else {
+                        HELVETICA_WIDTHS.iter().map(|w| *w as f32).collect()
+                    },
+                    default_width: if monospaced { 600.0 } else { 556.0 },
+                }
+            }
+        }
+    }
+
+    fn width(&self, text: &str, font_size: f32) -> f32 {
+        let units: f32 = encodings::string_to_bytes(&WIN_ANSI_ENCODING, text)
+            .iter()
+            .map(|code| {
+                (*code as usize)
+                    .checked_sub(self.first_char)
+                    .and_then(|index| self.widths.get(index))
+                    .copied()
+                    .unwrap_or(self.default_width)
+            })
+            .sum();
+        units * font_size / 1000.0
+    }
+}
+
+/// Split text into lines fitting into `max_width`, breaking at spaces where possible.
+fn wrap_lines(text: &str, metrics: &FontMetrics, font_size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.split(['\r', '\n']) {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{line} {word}")
+            };
+            if line.is_empty() || metrics.width(&candidate, font_size) <= max_width {
+                line = candidate;
+            } else {
+                lines.push(std::mem::take(&mut line));
+                line = word.to_string();
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Border and background colors from the widget's appearance characteristics (`/MK`).
+fn widget_decoration(widget: &Dictionary) -> (Option<Vec<f32>>, Option<Vec<f32>>) {
+    let colors = |key: &[u8]| {
+        let colors = widget
+            .get(b"MK")
+            .and_then(Object::as_dict)
+            .ok()?
+            .get(key)
+            .ok()?
+            .as_array()
+            .ok()?;
+        colors.iter().map(|c| c.as_float().ok()).collect::<Option<Vec<f32>>>()
+    };
+    (colors(b"BC"), colors(b"BG"))
+}
+
+fn draw_decoration(ops: &mut Vec<Operation>, widget: &Dictionary, width: f32, height: f32) {
+    let (border, background) = widget_decoration(widget);
+    if let Some(op) = background.as_deref().and_then(|bg| color_operation(bg, false)) {
+        ops.push(op);
+        ops.push(Operation::new(
+            "re",
+            vec![0.into(), 0.into(), width.into(), height.into()],
+        ));
+        ops.push(Operation::new("f", vec![]));
+    }
+    if let Some(op) = border.as_deref().and_then(|bc| color_operation(bc, true)) {
+        ops.push(op);
+        ops.push(Operation::new("w", vec![1.into()]));
+        ops.push(Operation::new(
+            "re",
+            vec![0.5.into(), 0.5.into(), (width - 1.0).into(), (height - 1.0).into()],
+        ));
+        ops.push(Operation::new("S", vec![]));
+    }
+}
+
+impl Document {
+    /// Generate the normal appearance (`/AP /N`) of every widget of a field from its value.
+    ///
+    /// Text and choice fields are rendered with the font and color from the default appearance
+    /// string, using the font from the AcroForm's default resources (`/DR`). A font size of 0 in
+    /// `/DA` selects the largest size, up to 12, at which the text fits. Checkboxes and radio
+    /// buttons get generated "on" and `Off` appearances only if a widget has none; the "on"
+    /// state is named after the current appearance state or value, falling back to `Yes` for
+    /// checkboxes and the widget index for radio buttons.
+    pub fn update_form_field_appearance(&mut self, field: &FormField) -> Result<()> {
+        for (index, widget_id) in field.widgets.iter().enumerate() {
+            match field.field_type {
+                FieldType::Text | FieldType::ComboBox | FieldType::ListBox => {
+                    let appearance = self.text_appearance(field, *widget_id)?;
+                    let appearance_id = self.add_object(appearance);
+                    self.set_normal_appearance(*widget_id, appearance_id)?;
+                }
+                FieldType::Checkbox | FieldType::Radio => {
+                    if self.get_dictionary(*widget_id)?.has(b"AP") {
+                        continue;
+                    }
+                    // Radio buttons need a distinct state per widget; without one, use the index.
+                    let current_state = self
+                        .get_dictionary(*widget_id)?
+                        .get(b"AS")
+                        .and_then(Object::as_name)
+                        .map(|state| String::from_utf8_lossy(state).into_owned());
+                    let on_state = match (&field.value, current_state) {
+                        (_, Ok(state)) if state != "Off" => state,
+                        (FieldValue::Name(state), _) if state != "Off" && field.field_type == FieldType::Checkbox => {
+                            state.clone()
+                        }
+                        _ if field.field_type == FieldType::Radio => index.to_string(),
+                        _ => "Yes".to_string(),
+                    };
+                    let (on, off) = self.button_appearances(field.field_type, *widget_id)?;
+                    let (on_id, off_id) = (self.add_object(on), self.add_object(off));
+                    let widget = self.get_dictionary_mut(*widget_id)?;
+                    widget.set(
+                        "AP",
+                        dictionary! { "N" => dictionary! { on_state.as_str() => on_id, "Off" => off_id } },
+                    );
+                    if !widget.has(b"AS") {
+                        let state = if field.value == FieldValue::Name(on_state.clone()) {
+                            on_state
+                        } else {
+                            "Off".into()
+                        };
+                        widget.set("AS", Object::Name(state.into_bytes()));
+                    }
+                }
+                FieldType::PushButton | FieldType::Signature => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the normal appearance of a widget, keeping its rollover and down appearances.
+    fn set_normal_appearance(&mut self, widget_id: ObjectId, appearance_id: ObjectId) -> Result<()> {
+        let widget = self.get_dictionary(widget_id)?;
+        match widget.get(b"AP") {
+            Ok(Object::Reference(ap_id)) if self.get_dictionary(*ap_id).is_ok() => {
+                let ap_id = *ap_id;
+                self.get_dictionary_mut(ap_id)?.set("N", appearance_id);
+            }
+            Ok(Object::Dictionary(_)) => {
+                let ap = self.get_dictionary_mut(widget_id)?.get_mut(b"AP")?.as_dict_mut()?;
+                ap.set("N", appearance_id);
+            }
+            _ => {
+                self.get_dictionary_mut(widget_id)?
+                    .set("AP", dictionary! { "N" => appearance_id });
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate appearances for all text and choice fields, and for buttons missing them.
+    pub fn update_form_appearances(&mut self) -> Result<()> {
+        for field in self.get_form_fields()? {
+            self.update_form_field_appearance(&field)?;
+        }
+        Ok(())
+    }
+
+    /// Look up a font in the AcroForm's default resources.
+    pub(crate) fn form_font(&self, name: &str) -> Option<(Object, &Dictionary)> {
+        let resources = self
+            .dereference(self.acro_form().ok()?.get(b"DR").ok()?)
+            .ok()?
+            .1
+            .as_dict()
+            .ok()?;
+        let fonts = self.dereference(resources.get(b"Font").ok()?).ok()?.1.as_dict().ok()?;
+        let font = fonts.get(name.as_bytes()).ok()?;
+        Some((font.clone(), self.dereference(font).ok()?.1.as_dict().ok()?))
+    }
+
+    fn text_appearance(&self, field: &FormField, widget_id: ObjectId) -> Result<Stream> {
+        let widget = self.get_dictionary(widget_id)?;
+        let rect = Rect::from_object(widget.get(b"Rect")?)?;
+        let (width, height) = (rect.width(), rect.height());
+        let appearance = DefaultAppearance::parse(field.default_appearance.as_deref().unwrap_or_default());
+        let font_name = appearance.font_name.clone().unwrap_or_else(|| "Helv".to_string());
+        // Fonts that can't show WinAnsi encoded text are replaced with Helvetica.
+        let form_font = self
+            .form_font(&font_name)
+            .filter(|(_, font)| is_win_ansi_font(self, font));
+        let (font_object, metrics) = match form_font {
+            Some((object, font)) => (object, FontMetrics::from_font(self, Some(font))),
+            None => (Object::Dictionary(helvetica()), FontMetrics::from_font(self, None)),
+        };
+        let alignment = self
+            .inherited_field_attribute(field.id, b"Q")
+            .and_then(|q| q.as_i64().ok())
+            .unwrap_or(0);
+
+        let text = match &field.value {
+            FieldValue::Text(text) | FieldValue::Name(text) => text.clone(),
+            FieldValue::Choices(choices) => choices.join(", "),
+            FieldValue::None => String::new(),
+        };
+        let text = if field.flags.contains(FieldFlags::PASSWORD) {
+            "*".repeat(text.chars().count())
+        } else {
+            text
+        };
+        // Combo boxes show the display text of the selected option.
+        let text = match field.field_type {
+            FieldType::ComboBox => field
+                .options
+                .iter()
+                .find(|(export, _)| *export == text)
+                .map_or(text, |(_, display)| display.clone()),
+            _ => text,
+        };
+
+        let inner_width = width - 2.0 * PADDING;
+        let inner_height = height - 2.0 * PADDING;
+        let multiline = field.field_type == FieldType::Text && field.flags.contains(FieldFlags::MULTILINE);
+        let font_size = if appearance.font_size > 0.0 {
+            appearance.font_size
+        } else if multiline {
+            let mut size = MAX_AUTO_FONT_SIZE;
+            while size > MIN_AUTO_FONT_SIZE
+                && wrap_lines(&text, &metrics, size, inner_width).len() as f32 * size * LINE_HEIGHT > inner_height
+            {
+                size -= 0.5;
+            }
+            size
+        } else if field.field_type == FieldType::ListBox {
+            MAX_AUTO_FONT_SIZE
+        } else {
+            let fit_height = inner_height / LINE_HEIGHT;
+            let text_width = metrics.width(&text, 1.0);
+            let fit_width = if text_width > 0.0 {
+                inner_width / text_width
+            } else {
+                f32::MAX
+            };
+            fit_height.min(fit_width).clamp(MIN_AUTO_FONT_SIZE, MAX_AUTO_FONT_SIZE)
+        };
+        let align = |line_width: f32| match alignment {
+            1 => (width - line_width) / 2.0,
+            2 => width - PADDING - line_width,
+            _ => PADDING,
+        };
+
+        let mut ops = vec![];
+        draw_decoration(&mut ops, widget, width, height);
+        ops.push(Operation::new("BMC", vec!["Tx".into()]));
+        ops.push(Operation::new("q", vec![]));
+        ops.push(Operation::new(
+            "re",
+            vec![1.into(), 1.into(), (width - 2.0).into(), (height - 2.0).into()],
+        ));
+        ops.push(Operation::new("W", vec![]));
+        ops.push(Operation::new("n", vec![]));
+
+        if field.field_type == FieldType::ListBox {
+            let selected: Vec<&str> = match &field.value {
+                FieldValue::Text(text) => vec![text],
+                FieldValue::Choices(choices) => choices.iter().map(String::as_str).collect(),
+                _ => vec![],
+            };
+            let line_height = font_size * LINE_HEIGHT;
+            ops.push(Operation::new("q", vec![]));
+            for (index, (export, _)) in field.options.iter().enumerate() {
+                if selected.contains(&export.as_str()) {
+                    let y = height - PADDING - (index + 1) as f32 * line_height;
+                    ops.push(Operation::new("rg", vec![0.6.into(), 0.75.into(), 0.85.into()]));
+                    ops.push(Operation::new(
+                        "re",
+                        vec![1.into(), y.into(), (width - 2.0).into(), line_height.into()],
+                    ));
+                    ops.push(Operation::new("f", vec![]));
+                }
+            }
+            ops.push(Operation::new("Q", vec![]));
+        }
+
+        ops.push(Operation::new("BT", vec![]));
+        if appearance.color_operations.is_empty() {
+            // Don't draw the text in the background color.
+            ops.push(Operation::new("g", vec![0.into()]));
+        }
+        ops.extend(appearance.color_operations.iter().cloned());
+        ops.push(Operation::new(
+            "Tf",
+            vec![Object::Name(font_name.as_bytes().to_vec()), font_size.into()],
+        ));
+        let show = |ops: &mut Vec<Operation>, x: f32, y: f32, line: &str| {
+            ops.push(Operation::new(
+                "Tm",
+                vec![1.into(), 0.into(), 0.into(), 1.into(), x.into(), y.into()],
+            ));
+            ops.push(Operation::new("Tj", vec![win_ansi_string(line)]));
+        };
+
+        let comb_cells = field
+            .max_len
+            .filter(|_| field.field_type == FieldType::Text && field.flags.contains(FieldFlags::COMB) && !multiline);
+        if let Some(cells) = comb_cells {
+            let cell_width = width / cells.max(1) as f32;
+            let y = (height - font_size * 0.7) / 2.0;
+            for (index, ch) in text.chars().take(cells as usize).enumerate() {
+                let ch = ch.to_string();
+                let x = cell_width * index as f32 + (cell_width - metrics.width(&ch, font_size)) / 2.0;
+                show(&mut ops, x, y, &ch);
+            }
+        } else if multiline || field.field_type == FieldType::ListBox {
+            let lines = if multiline {
+                wrap_lines(&text, &metrics, font_size, inner_width)
+            } else {
+                field.options.iter().map(|(_, display)| display.clone()).collect()
+            };
+            let line_height = font_size * LINE_HEIGHT;
+            for (index, line) in lines.iter().enumerate() {
+                let y = height - PADDING - (index + 1) as f32 * line_height
+                    + (line_height - font_size) / 2.0
+                    + font_size * 0.2;
+                show(&mut ops, align(metrics.width(line, font_size)), y, line);
+            }
+        } else {
+            let y = (height - font_size * 0.7) / 2.0;
+            show(&mut ops, align(metrics.width(&text, font_size)), y, &text);
+        }
+        ops.push(Operation::new("ET", vec![]));
+        ops.push(Operation::new("Q", vec![]));
+        ops.push(Operation::new("EMC", vec![]));
+
+        let mut form = xobject::form(
+            vec![0.0, 0.0, width, height],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            Content { operations: ops }.encode()?,
+        );
+        form.dict.set(
+            "Resources",
+            dictionary! { "Font" => dictionary! { font_name.as_str() => font_object } },
+        );
+        Ok(form)
+    }
+
+    /// "On" and `Off` appearances of a checkbox (a check mark) or radio button (a dot).
+    fn button_appearances(&self, field_type: FieldType, widget_id: ObjectId) -> Result<(Stream, Stream)> {
+        let widget = self.get_dictionary(widget_id)?;
+        let rect = Rect::from_object(widget.get(b"Rect")?)?;
+        let (width, height) = (rect.width(), rect.height());
+
+        let mut off = vec![];
+        draw_decoration(&mut off, widget, width, height);
+        let mut on = off.clone();
+        on.push(Operation::new("g", vec![0.into()]));
+        if field_type == FieldType::Radio {
+            let radius = width.min(height) / 4.0;
+            let (cx, cy) = (width / 2.0, height / 2.0);
+            let k = radius * 0.552_284_8;
+            let curve = |p: [f32; 6]| Operation::new("c", p.iter().map(|v| Object::Real(*v)).collect());
+            on.push(Operation::new("m", vec![(cx + radius).into(), cy.into()]));
+            on.push(curve([cx + radius, cy + k, cx + k, cy + radius, cx, cy + radius]));
+            on.push(curve([cx - k, cy + radius, cx - radius, cy + k, cx - radius, cy]));
+            on.push(curve([cx - radius, cy - k, cx - k, cy - radius, cx, cy - radius]));
+            on.push(curve([cx + k, cy - radius, cx + radius, cy - k, cx + radius, cy]));
+            on.push(Operation::new("f", vec![]));
+        } else {
+            on.push(Operation::new("G", vec![0.into()]));
+            on.push(Operation::new("w", vec![(width.min(height) / 10.0).into()]));
+            on.push(Operation::new("m", vec![(width * 0.2).into(), (height * 0.5).into()]));
+            on.push(Operation::new("l", vec![(width * 0.4).into(), (height * 0.25).into()]));
+            on.push(Operation::new("l", vec![(width * 0.8).into(), (height * 0.8).into()]));
+            on.push(Operation::new("S", vec![]));
+        }
+
+        let stream = |ops: Vec<Operation>| -> Result<Stream> {
+            Ok(xobject::form(
+                vec![0.0, 0.0, width, height],
+                vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                Content { operations: ops }.encode()?,
+            ))
+        };
+        Ok((stream(on)?, stream(off)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FontMetrics, wrap_lines};
+    use crate::content::Content;
+    use crate::form::tests::create_form_document;
+    use crate::{FieldFlags, FieldValue, Object};
+
+    fn appearance_operators(doc: &crate::Document, widget_id: crate::ObjectId) -> Vec<String> {
+        let widget = doc.get_dictionary(widget_id).unwrap();
+        let normal = widget
+            .get(b"AP")
+            .unwrap()
+            .as_dict()
+            .unwrap()
+            .get(b"N")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let stream = doc.get_object(normal).unwrap().as_stream().unwrap();
+        let content = Content::decode(&stream.get_plain_content().unwrap()).unwrap();
+        content.operations.into_iter().map(|op| op.operator).collect()
+    }
+
+    #[test]
+    fn wrap_text() {
+        let metrics = FontMetrics::from_font(&crate::Document::new(), None);
+        assert_eq!(metrics.width("ab", 10.0), 11.12);
+        let lines = wrap_lines("one two three\nfour", &metrics, 10.0, 40.0);
+        assert_eq!(lines, ["one two", "three", "four"]);
+    }
+
+    #[test]
+    fn text_field_appearance() {
+        let (mut doc, _) = create_form_document();
+        doc.set_form_field_value("person.name", FieldValue::Text("Jane".to_string()))
+            .unwrap();
+        let field = doc.get_form_field("person.name").unwrap();
+        assert_eq!(
+            appearance_operators(&doc, field.id)
+                .iter()
+                .filter(|op| *op == "Tj")
+                .count(),
+            1
+        );
+
+        // Comb fields show each character in its own cell.
+        let flags = FieldFlags::COMB.bits() as i64;
+        doc.get_dictionary_mut(field.id).unwrap().set("Ff", flags);
+        let field = doc.get_form_field("person.name").unwrap();
+        doc.update_form_field_appearance(&field).unwrap();
+        assert_eq!(
+            appearance_operators(&doc, field.id)
+                .iter()
+                .filter(|op| *op == "Tj")
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn text_without_color_is_black() {
+        let (mut doc, _) = create_form_document();
+        let field = doc.get_form_field("person.name").unwrap();
+        let widget = doc.get_dictionary_mut(field.id).unwrap();
+        widget.set("DA", Object::string_literal("/Helv 0 Tf"));
+        widget.set("MK", dictionary! { "BG" => vec![1.into()] });
+        let field = doc.get_form_field("person.name").unwrap();
+        doc.update_form_field_appearance(&field).unwrap();
+
+        let operators = appearance_operators(&doc, field.id);
+        let text_start = operators.iter().position(|op| op == "BT").unwrap();
+        assert_eq!(operators[text_start + 1], "g");
+    }
+
+    #[test]
+    fn composite_font_falls_back_to_helvetica() {
+        let (mut doc, _) = create_form_document();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => "NotoSans",
+            "Encoding" => "Identity-H",
+        });
+        let acro_form_id = doc.catalog().unwrap().get(b"AcroForm").unwrap().as_reference().unwrap();
+        doc.get_dictionary_mut(acro_form_id)
+            .unwrap()
+            .set("DR", dictionary! { "Font" => dictionary! { "Helv" => font_id } });
+        doc.set_form_field_value("person.name", FieldValue::Text("Jane".to_string()))
+            .unwrap();
+
+        let field = doc.get_form_field("person.name").unwrap();
+        let widget = doc.get_dictionary(field.id).unwrap();
+        let normal = widget
+            .get_deref(b"AP", &doc)
+            .unwrap()
+            .as_dict()
+            .unwrap()
+            .get(b"N")
+            .unwrap();
+        let stream = doc
+            .get_object(normal.as_reference().unwrap())
+            .unwrap()
+            .as_stream()
+            .unwrap();
+        let font = stream
+            .dict
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"Font"))
+            .and_then(Object::as_dict)
+            .and_then(|fonts| fonts.get(b"Helv"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert_eq!(font.get(b"BaseFont").unwrap().as_name().unwrap(), b"Helvetica");
+    }
+
+    #[test]
+    fn text_field_appearance_keeps_down_appearance() {
+        let (mut doc, _) = create_form_document();
+        let field = doc.get_form_field("person.name").unwrap();
+        let down_id = doc.add_object(Object::Null);
+        doc.get_dictionary_mut(field.id)
+            .unwrap()
+            .set("AP", dictionary! { "N" => down_id, "D" => down_id });
+        doc.update_form_field_appearance(&field).unwrap();
+
+        let ap = doc
+            .get_dictionary(field.id)
+            .unwrap()
+            .get(b"AP")
+            .unwrap()
+            .as_dict()
+            .unwrap();
+        assert_eq!(ap.get(b"D").unwrap().as_reference().unwrap(), down_id);
+        assert_ne!(ap.get(b"N").unwrap().as_reference().unwrap(), down_id);
+    }
+
+    #[test]
+    fn checkbox_appearance_generated_when_missing() {
+        let (mut doc, _) = create_form_document();
+        let agree = doc.get_form_field("agree").unwrap();
+        doc.get_dictionary_mut(agree.id).unwrap().remove(b"AP");
+        doc.update_form_appearances().unwrap();
+
+        let widget = doc.get_dictionary(agree.id).unwrap();
+        let normal = widget
+            .get(b"AP")
+            .and_then(Object::as_dict)
+            .and_then(|ap| ap.get(b"N"))
+            .unwrap();
+        assert!(normal.as_dict().unwrap().has(b"Yes"));
+        assert_eq!(widget.get(b"AS").unwrap().as_name().unwrap(), b"Off");
+    }
+}
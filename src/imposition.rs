@@ -0,0 +1,422 @@
+use crate::content::{Content, Operation};
+use crate::{BoxKind, Dictionary, Document, Error, Object, ObjectId, Permissions, Rect, Result, Stream, xobject};
+
+/// Options for [`Document::n_up`].
+#[derive(Debug, Clone)]
+pub struct NUpOptions {
+    /// Space between neighbouring cells.
+    pub gutter: f32,
+    /// Space between the cells and the sheet edges.
+    pub margin: f32,
+    /// Draw crop marks at the corners of every placed page.
+    pub crop_marks: bool,
+    /// Length of the crop mark lines.
+    pub crop_mark_length: f32,
+    /// Rotate pages by 90 degrees when their orientation doesn't match the cell orientation.
+    pub auto_rotate: bool,
+}
+
+impl Default for NUpOptions {
+    fn default() -> Self {
+        NUpOptions {
+            gutter: 0.0,
+            margin: 0.0,
+            crop_marks: false,
+            crop_mark_length: 10.0,
+            auto_rotate: true,
+        }
+    }
+}
+
+/// Transformation matrix `[a b c d e f]`.
+type Matrix = [f32; 6];
+
+fn multiply(m1: Matrix, m2: Matrix) -> Matrix {
+    [
+        m1[0] * m2[0] + m1[1] * m2[2],
+        m1[0] * m2[1] + m1[1] * m2[3],
+        m1[2] * m2[0] + m1[3] * m2[2],
+        m1[2] * m2[1] + m1[3] * m2[3],
+        m1[4] * m2[0] + m1[5] * m2[2] + m2[4],
+        m1[4] * m2[1] + m1[5] * m2[3] + m2[5],
+    ]
+}
+
+fn translate(x: f32, y: f32) -> Matrix {
+    [1.0, 0.0, 0.0, 1.0, x, y]
+}
+
+fn scale(factor: f32) -> Matrix {
+    [factor, 0.0, 0.0, factor, 0.0, 0.0]
+}
+
+/// Matrix rotating a `width` x `height` box clockwise by `rotation` degrees,
+/// keeping the result in the positive quadrant.
+fn rotate(rotation: i64, width: f32, height: f32) -> Matrix {
+    match rotation {
+        90 => [0.0, -1.0, 1.0, 0.0, 0.0, width],
+        180 => [-1.0, 0.0, 0.0, -1.0, width, height],
+        270 => [0.0, 1.0, -1.0, 0.0, height, 0.0],
+        _ => [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+    }
+}
+
+fn transform_point(m: Matrix, x: f32, y: f32) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+/// Compute the matrix placing `source` (displayed with `rotation`) centered in `target`,
+/// scaled to fit. Returns the matrix and the rectangle covered by the placed page.
+fn fit_matrix(source: Rect, rotation: i64, target: Rect) -> Result<(Matrix, Rect)> {
+    let (width, height) = (source.width(), source.height());
+    if width <= 0.0 || height <= 0.0 {
+        return Err(Error::InvalidImposition("page has an empty crop box".to_string()));
+    }
+    if target.width() <= 0.0 || target.height() <= 0.0 {
+        return Err(Error::InvalidImposition("target box is empty".to_string()));
+    }
+    let (shown_width, shown_height) = if rotation % 180 == 0 {
+        (width, height)
+    } else {
+        (height, width)
+    };
+    let factor = (target.width() / shown_width).min(target.height() / shown_height);
+    let x = target.llx + (target.width() - shown_width * factor) / 2.0;
+    let y = target.lly + (target.height() - shown_height * factor) / 2.0;
+    let matrix = multiply(
+        multiply(
+            multiply(translate(-source.llx, -source.lly), rotate(rotation, width, height)),
+            scale(factor),
+        ),
+        translate(x, y),
+    );
+    Ok((
+        matrix,
+        Rect::new(x, y, x + shown_width * factor, y + shown_height * factor),
+    ))
+}
+
+fn place_xobject(operations: &mut Vec<Operation>, matrix: Matrix, name: &str) {
+    operations.push(Operation::new("q", vec![]));
+    operations.push(Operation::new("cm", matrix.iter().map(|v| Object::Real(*v)).collect()));
+    operations.push(Operation::new("Do", vec![Object::Name(name.as_bytes().to_vec())]));
+    operations.push(Operation::new("Q", vec![]));
+}
+
+fn draw_crop_marks(operations: &mut Vec<Operation>, trim: Rect, length: f32) {
+    let mut line = |x1: f32, y1: f32, x2: f32, y2: f32| {
+        operations.push(Operation::new("m", vec![x1.into(), y1.into()]));
+        operations.push(Operation::new("l", vec![x2.into(), y2.into()]));
+    };
+    for (x, dx) in [(trim.llx, -length), (trim.urx, length)] {
+        for (y, dy) in [(trim.lly, -length), (trim.ury, length)] {
+            line(x, y, x + dx, y);
+            line(x, y, x, y + dy);
+        }
+    }
+    operations.push(Operation::new("S", vec![]));
+}
+
+impl Document {
+    /// Convert a page into a Form XObject.
+    ///
+    /// The bounding box of the form is the page's crop box (or media box) and its resources
+    /// are copied from the page, resolving inheritance. Page rotation is not applied.
+    pub fn page_to_form_xobject(&self, page_id: ObjectId) -> Result<Stream> {
+        let area = self.page_box(page_id, BoxKind::Crop)?;
+
+        let mut content = Vec::new();
+        for object_id in self.get_page_contents(page_id) {
+            if let Ok(stream) = self.get_object(object_id).and_then(Object::as_stream) {
+                content.extend(stream.get_plain_content()?);
+                content.push(b'\n');
+            }
+        }
+
+        let mut form = xobject::form(
+            vec![area.llx, area.lly, area.urx, area.ury],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            content,
+        );
+        if let Ok(resources) = self.get_inherited_page_attribute(page_id, b"Resources") {
+            form.dict.set("Resources", resources.clone());
+        }
+        if let Ok(group) = self.get_dictionary(page_id).and_then(|page| page.get(b"Group")) {
+            form.dict.set("Group", group.clone());
+        }
+        Ok(form)
+    }
+
+    /// Impose all pages onto new sheets, `cols` x `rows` pages per sheet.
+    ///
+    /// Pages are placed left to right, top to bottom, scaled to fit their cell and centered in it.
+    /// Their annotations move to the sheets, with rectangles transformed along with the content.
+    /// The sheets replace the pages of the document; the original page objects are left
+    /// unreferenced and can be removed with [`Document::prune_objects`].
+    /// Returns the object IDs of the new sheets.
+    pub fn n_up(
+        &mut self, cols: u32, rows: u32, sheet_size: (f32, f32), options: &NUpOptions,
+    ) -> Result<Vec<ObjectId>> {
+        self.check_permissions(Permissions::ASSEMBLABLE)?;
+        let (sheet_width, sheet_height) = sheet_size;
+        if cols == 0 || rows == 0 {
+            return Err(Error::InvalidImposition("grid must have at least one cell".to_string()));
+        }
+        let cell_width = (sheet_width - 2.0 * options.margin - (cols - 1) as f32 * options.gutter) / cols as f32;
+        let cell_height = (sheet_height - 2.0 * options.margin - (rows - 1) as f32 * options.gutter) / rows as f32;
+        if cell_width <= 0.0 || cell_height <= 0.0 {
+            return Err(Error::InvalidImposition(
+                "margins and gutters exceed sheet size".to_string(),
+            ));
+        }
+
+        let pages_id = self.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+        let page_ids: Vec<ObjectId> = self.get_pages().into_values().collect();
+        let mut placed = Vec::with_capacity(page_ids.len());
+        for page_id in page_ids {
+            let area = self.page_box(page_id, BoxKind::Crop)?;
+            let rotation = self.page_rotation(page_id)?;
+            let form_id = self.add_object(self.page_to_form_xobject(page_id)?);
+            let annotation_ids = self.page_annotation_ids(page_id)?;
+            placed.push((form_id, area, rotation, annotation_ids));
+        }
+
+        let cells_per_sheet = (cols * rows) as usize;
+        let mut sheet_ids = Vec::new();
+        for sheet in placed.chunks(cells_per_sheet) {
+            let mut operations = Vec::new();
+            let mut xobjects = Dictionary::new();
+            let mut trims = Vec::new();
+            let mut annotations = Vec::new();
+            for (index, (form_id, area, rotation, annotation_ids)) in sheet.iter().enumerate() {
+                let col = (index % cols as usize) as f32;
+                let row = (index / cols as usize) as f32;
+                let x = options.margin + col * (cell_width + options.gutter);
+                let y = sheet_height - options.margin - (row + 1.0) * cell_height - row * options.gutter;
+                let cell = Rect::new(x, y, x + cell_width, y + cell_height);
+
+                let mut rotation = *rotation;
+                let shown_landscape = (area.width() > area.height()) == (rotation % 180 == 0);
+                if options.auto_rotate && shown_landscape != (cell_width > cell_height) {
+                    rotation = (rotation + 90) % 360;
+                }
+                let (matrix, trim) = fit_matrix(*area, rotation, cell)?;
+                let name = format!("X{}", form_id.0);
+                place_xobject(&mut operations, matrix, &name);
+                xobjects.set(name, *form_id);
+                trims.push(trim);
+                self.transform_annotations(annotation_ids, matrix);
+                annotations.extend(annotation_ids);
+            }
+            if options.crop_marks {
+                operations.push(Operation::new("q", vec![]));
+                operations.push(Operation::new("G", vec![0.into()]));
+                operations.push(Operation::new("w", vec![Object::Real(0.25)]));
+                for trim in trims {
+                    draw_crop_marks(&mut operations, trim, options.crop_mark_length);
+                }
+                operations.push(Operation::new("Q", vec![]));
+            }
+
+            let content_id = self.add_object(Stream::new(Dictionary::new(), Content { operations }.encode()?));
+            let sheet_id = self.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => Rect::from_size(sheet_width, sheet_height),
+                "Resources" => dictionary! {
+                    "XObject" => xobjects,
+                },
+                "Contents" => content_id,
+            });
+            if !annotations.is_empty() {
+                for annotation_id in &annotations {
+                    if let Ok(annotation) = self.get_dictionary_mut(*annotation_id) {
+                        annotation.set("P", sheet_id);
+                    }
+                }
+                let sheet = self.get_dictionary_mut(sheet_id)?;
+                sheet.set(
+                    "Annots",
+                    annotations.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+                );
+            }
+            sheet_ids.push(sheet_id);
+        }
+
+        let pages = self.get_dictionary_mut(pages_id)?;
+        pages.set(
+            "Kids",
+            sheet_ids.iter().map(|id| Object::Reference(*id)).collect::<Vec<_>>(),
+        );
+        pages.set("Count", sheet_ids.len() as i64);
+        Ok(sheet_ids)
+    }
+
+    fn page_annotation_ids(&self, page_id: ObjectId) -> Result<Vec<ObjectId>> {
+        Ok(self
+            .get_dictionary(page_id)?
+            .get(b"Annots")
+            .and_then(|annots| self.dereference(annots))
+            .and_then(|(_, annots)| annots.as_array())
+            .map(|annots| annots.iter().filter_map(|annot| annot.as_reference().ok()).collect())
+            .unwrap_or_default())
+    }
+
+    /// Move annotation rectangles along with page content placed with `matrix`.
+    fn transform_annotations(&mut self, annotation_ids: &[ObjectId], matrix: Matrix) {
+        for annotation_id in annotation_ids {
+            if let Ok(annotation) = self.get_dictionary_mut(*annotation_id) {
+                if let Ok(rect) = annotation.get(b"Rect").and_then(Rect::from_object) {
+                    let (x1, y1) = transform_point(matrix, rect.llx, rect.lly);
+                    let (x2, y2) = transform_point(matrix, rect.urx, rect.ury);
+                    annotation.set("Rect", Rect::new(x1, y1, x2, y2));
+                }
+            }
+        }
+    }
+
+    /// Scale a page by `factor`, resizing its media box accordingly.
+    pub fn scale_page(&mut self, page_id: ObjectId, factor: f32) -> Result<()> {
+        let area = self.page_box(page_id, BoxKind::Crop)?;
+        self.fit_page_to(page_id, Rect::from_size(area.width() * factor, area.height() * factor))
+    }
+
+    /// Scale a page to fit `media_box`, keeping its aspect ratio and centering it.
+    ///
+    /// The page content is wrapped into a Form XObject and the page's media box is replaced.
+    /// Crop, bleed, trim and art boxes are removed, and annotation rectangles are transformed
+    /// along with the content.
+    pub fn fit_page_to(&mut self, page_id: ObjectId, media_box: Rect) -> Result<()> {
+        let area = self.page_box(page_id, BoxKind::Crop)?;
+        let form_id = self.add_object(self.page_to_form_xobject(page_id)?);
+        let name = format!("X{}", form_id.0);
+        let (matrix, _) = fit_matrix(area, 0, media_box)?;
+
+        let mut operations = Vec::new();
+        place_xobject(&mut operations, matrix, &name);
+        let content_id = self.add_object(Stream::new(Dictionary::new(), Content { operations }.encode()?));
+
+        let annotation_ids = self.page_annotation_ids(page_id)?;
+        self.transform_annotations(&annotation_ids, matrix);
+
+        let page = self.get_dictionary_mut(page_id)?;
+        page.set("MediaBox", media_box);
+        for key in [b"CropBox".as_slice(), b"BleedBox", b"TrimBox", b"ArtBox"] {
+            page.remove(key);
+        }
+        page.set(
+            "Resources",
+            dictionary! { "XObject" => dictionary! { name => form_id } },
+        );
+        page.set("Contents", content_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NUpOptions;
+    use crate::content::Content;
+    use crate::creator::tests::create_document_with_texts;
+    use crate::{Object, Rect};
+
+    #[test]
+    fn two_up_on_landscape_sheet() {
+        let mut doc = create_document_with_texts(&["one", "two", "three"]);
+        let options = NUpOptions {
+            gutter: 10.0,
+            crop_marks: true,
+            ..NUpOptions::default()
+        };
+        let sheets = doc.n_up(2, 1, (842.0, 595.0), &options).unwrap();
+        assert_eq!(sheets.len(), 2);
+        assert_eq!(doc.get_pages().len(), 2);
+
+        let sheet = doc.get_dictionary(sheets[0]).unwrap();
+        let media_box = Rect::from_object(sheet.get(b"MediaBox").unwrap()).unwrap();
+        assert_eq!(media_box, Rect::from_size(842.0, 595.0));
+        let xobjects = sheet
+            .get_deref(b"Resources", &doc)
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert_eq!(xobjects.len(), 2);
+
+        let content = Content::decode(&doc.get_page_content(sheets[0]).unwrap()).unwrap();
+        let placements: Vec<_> = content.operations.iter().filter(|op| op.operator == "cm").collect();
+        assert_eq!(placements.len(), 2);
+        // Portrait pages fit into portrait cells without rotation.
+        let matrix: Vec<f32> = placements[0].operands.iter().map(|o| o.as_float().unwrap()).collect();
+        assert_eq!(matrix[1], 0.0);
+        assert!((matrix[0] - 416.0 / 595.0).abs() < 1e-4);
+        assert!(content.operations.iter().any(|op| op.operator == "S"));
+    }
+
+    #[test]
+    fn auto_rotate_into_landscape_cell() {
+        let mut doc = create_document_with_texts(&["one"]);
+        let sheets = doc.n_up(1, 1, (842.0, 595.0), &NUpOptions::default()).unwrap();
+        let content = Content::decode(&doc.get_page_content(sheets[0]).unwrap()).unwrap();
+        let cm = content.operations.iter().find(|op| op.operator == "cm").unwrap();
+        let matrix: Vec<f32> = cm.operands.iter().map(|o| o.as_float().unwrap()).collect();
+        assert_eq!(matrix[..4], [0.0, -1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn n_up_moves_annotations_to_sheets() {
+        let mut doc = create_document_with_texts(&["one", "two"]);
+        let page_id = *doc.get_pages().get(&2).unwrap();
+        let annotation_id = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Square",
+            "Rect" => Rect::new(0.0, 0.0, 595.0, 842.0),
+            "P" => page_id,
+        });
+        doc.get_dictionary_mut(page_id)
+            .unwrap()
+            .set("Annots", vec![annotation_id.into()]);
+
+        let sheets = doc.n_up(2, 1, (842.0, 595.0), &NUpOptions::default()).unwrap();
+        let sheet = doc.get_dictionary(sheets[0]).unwrap();
+        let annots = sheet.get(b"Annots").and_then(Object::as_array).unwrap();
+        assert_eq!(annots, &vec![Object::Reference(annotation_id)]);
+        let annotation = doc.get_dictionary(annotation_id).unwrap();
+        assert_eq!(annotation.get(b"P").unwrap(), &Object::Reference(sheets[0]));
+        // The second page fills the right cell.
+        let rect = Rect::from_object(annotation.get(b"Rect").unwrap()).unwrap();
+        assert!((rect.llx - 421.0).abs() < 0.5 && (rect.urx - 842.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn empty_page_box_is_rejected() {
+        let mut doc = create_document_with_texts(&["one"]);
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        doc.get_dictionary_mut(page_id)
+            .unwrap()
+            .set("MediaBox", Rect::new(0.0, 0.0, 0.0, 842.0));
+        assert!(doc.n_up(2, 1, (842.0, 595.0), &NUpOptions::default()).is_err());
+        assert!(doc.scale_page(page_id, 0.0).is_err());
+    }
+
+    #[test]
+    fn scale_page_resizes_media_box() {
+        let mut doc = create_document_with_texts(&["one"]);
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        doc.scale_page(page_id, 0.5).unwrap();
+
+        let page = doc.get_dictionary(page_id).unwrap();
+        let media_box = Rect::from_object(page.get(b"MediaBox").unwrap()).unwrap();
+        assert_eq!(media_box, Rect::from_size(297.5, 421.0));
+        let xobjects = page
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        let form_id = xobjects.iter().next().unwrap().1.as_reference().unwrap();
+        let form = doc.get_object(form_id).and_then(Object::as_stream).unwrap();
+        // Resources inherited from the page tree are carried over to the form.
+        assert!(form.dict.get(b"Resources").is_ok());
+    }
+}
@@ -1,5 +1,20 @@
+use std::io::Write;
+
 use crate::{Dictionary, Document, Object, ObjectId, Result};
 
+/// Trailer keys describing the cross-reference section itself, which are not carried over to the
+/// trailer of an update.
+const XREF_TRAILER_KEYS: [&[u8]; 8] = [
+    b"Prev",
+    b"XRefStm",
+    b"Type",
+    b"W",
+    b"Index",
+    b"Length",
+    b"Filter",
+    b"DecodeParms",
+];
+
 #[derive(Debug, Clone)]
 pub struct IncrementalDocument {
     /// The raw data for the files read from input.
@@ -11,6 +26,9 @@ pub struct IncrementalDocument {
 
     /// A new document appended to the previously loaded file.
     pub new_document: Document,
+
+    /// Working copy of the whole document used for change tracking, created on first access.
+    document: Option<Document>,
 }
 
 impl IncrementalDocument {
@@ -20,6 +38,7 @@ pub fn new() -> Self {
             bytes_documents: Vec::new(),
             prev_documents: Document::new(),
             new_document: Document::new(),
+            document: None,
         }
     }
 
@@ -32,6 +51,7 @@ pub fn create_from(prev_bytes: Vec<u8>, prev_documents: Document) -> Self {
             bytes_documents: prev_bytes,
             new_document: Document::new_from_prev(&prev_documents),
             prev_documents,
+            document: None,
         }
     }
 
@@ -45,6 +65,53 @@ pub fn get_prev_documents_bytes(&self) -> &[u8] {
         &self.bytes_documents
     }
 
+    /// Get the working copy of the whole document.
+    pub fn document(&self) -> &Document {
+        self.document.as_ref().unwrap_or(&self.prev_documents)
+    }
+
+    /// Get the working copy of the whole document for editing through the normal [`Document`] API.
+    ///
+    /// Changes are detected by [`IncrementalDocument::save_incremental`], so objects don't need to be
+    /// copied to `new_document` first.
+    pub fn document_mut(&mut self) -> &mut Document {
+        self.document.get_or_insert_with(|| self.prev_documents.clone())
+    }
+
+    /// Append an incremental update with the changes made to the working copy.
+    ///
+    /// Objects that were added or changed are written, objects that were removed are marked as free,
+    /// and the trailer of the working copy is written with `/Prev` pointing to the previous
+    /// cross-reference section. Objects put into `new_document` directly are written as well, unless
+    /// the working copy has a different version of them.
+    pub fn save_incremental<W: Write>(&mut self, target: &mut W) -> Result<()> {
+        let mut freed = vec![];
+        if let Some(document) = &self.document {
+            for (id, object) in &document.objects {
+                if self.prev_documents.objects.get(id) != Some(object) {
+                    self.new_document.objects.insert(*id, object.clone());
+                }
+            }
+            freed.extend(
+                self.prev_documents
+                    .objects
+                    .keys()
+                    .filter(|id| !document.objects.contains_key(id))
+                    .copied(),
+            );
+
+            let mut trailer = document.trailer.clone();
+            for key in XREF_TRAILER_KEYS {
+                trailer.remove(key);
+            }
+            trailer.set("Prev", Object::Integer(self.prev_documents.xref_start as i64));
+            self.new_document.trailer = trailer;
+            self.new_document.max_id = self.new_document.max_id.max(document.max_id);
+        }
+        self.save_internal(target, &freed)?;
+        Ok(())
+    }
+
     /// Clone Object from previous document to new document.
     /// If the object already exists nothing is done.
     ///
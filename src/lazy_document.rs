@@ -0,0 +1,594 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use log::warn;
+
+use crate::encryption;
+use crate::error::{ParseError, XrefError};
+use crate::object_stream::ObjectStream;
+use crate::parser::{self, ParserInput};
+use crate::xref::XrefEntry;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Reader, Result};
+
+/// Bytes read at a time when looking for an object or cross-reference section.
+const WINDOW_SIZE: usize = 4096;
+
+/// Default byte budget of the stream cache.
+const DEFAULT_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// A PDF document whose objects are read from the source on first access.
+///
+/// Only the cross-reference sections are read when the document is opened. Objects are parsed
+/// when they are requested and kept in [`LazyDocument::document`], so that the usual
+/// [`Document`] getters work on everything loaded so far. Streams are stored decompressed and
+/// evicted least recently used first once their total size exceeds the cache size; they are read
+/// again when needed.
+pub struct LazyDocument<R> {
+    source: R,
+    /// Position of the `%PDF-` header in the source; offsets in the file are relative to it.
+    base: u64,
+    document: Document,
+    /// Object ID of the encryption dictionary, which is not encrypted itself.
+    encrypt_id: Option<ObjectId>,
+    /// Sorted offsets of all objects and cross-reference sections, and the length of the file.
+    /// An object can't extend past the next of these offsets.
+    boundaries: Vec<u64>,
+    streams: StreamCache,
+}
+
+/// Least recently used accounting of the streams in the loaded document.
+struct StreamCache {
+    capacity: usize,
+    size: usize,
+    /// Incremented on each public call; streams used by the current call are never evicted.
+    tick: u64,
+    entries: HashMap<ObjectId, (usize, u64)>,
+}
+
+impl LazyDocument<File> {
+    /// Open a PDF file for lazy loading.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(File::open(path)?)
+    }
+
+    /// Open an encrypted PDF file for lazy loading with the user or owner password.
+    pub fn open_with_password<P: AsRef<Path>>(path: P, password: &str) -> Result<Self> {
+        Self::new_with_password(File::open(path)?, password)
+    }
+}
+
+impl<R: Read + Seek> LazyDocument<R> {
+    /// Read the header and cross-reference sections of a PDF document.
+    ///
+    /// Encrypted documents are decrypted with the empty user password; if they have another
+    /// password, this fails and [`LazyDocument::new_with_password`] has to be used instead.
+    pub fn new(source: R) -> Result<Self> {
+        Self::new_with_password(source, "")
+    }
+
+    /// Read the header and cross-reference sections of a PDF document, which is decrypted with the
+    /// user or owner password if it is encrypted.
+    pub fn new_with_password(mut source: R, password: &str) -> Result<Self> {
+        let mut head = vec![];
+        source.seek(SeekFrom::Start(0))?;
+        source.by_ref().take(1024).read_to_end(&mut head)?;
+        let base = head.windows(5).position(|w| w == b"%PDF-").unwrap_or(0);
+        let version =
+            parser::header(ParserInput::new_extra(&head[base..], "header")).ok_or(ParseError::InvalidFileHeader)?;
+
+        let mut lazy = LazyDocument {
+            source,
+            base: base as u64,
+            document: Document::new(),
+            encrypt_id: None,
+            boundaries: Vec::new(),
+            streams: StreamCache {
+                capacity: DEFAULT_CACHE_SIZE,
+                size: 0,
+                tick: 0,
+                entries: HashMap::new(),
+            },
+        };
+        lazy.document.version = version;
+        lazy.read_xref()?;
+
+        // The encryption dictionary is not loaded yet, so `Document::is_encrypted` can't resolve it.
+        if lazy.document.trailer.has(b"Encrypt") {
+            lazy.encrypt_id = lazy
+                .document
+                .trailer
+                .get(b"Encrypt")
+                .and_then(Object::as_reference)
+                .ok();
+            if let Some(id) = lazy.encrypt_id {
+                lazy.load(id)?;
+            }
+            let state = lazy.document.password_encryption_state(password)?;
+            lazy.document.trailer.remove(b"Encrypt");
+            if let Some(id) = lazy.encrypt_id {
+                lazy.document.objects.remove(&id);
+            }
+            lazy.document.encryption_state = Some(state);
+        }
+        Ok(lazy)
+    }
+
+    /// Set the byte budget for cached streams.
+    pub fn with_cache_size(mut self, bytes: usize) -> Self {
+        self.streams.capacity = bytes;
+        self
+    }
+
+    /// The document with the objects loaded so far.
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Consume the lazy document and return the objects loaded so far.
+    pub fn into_document(self) -> Document {
+        self.document
+    }
+
+    /// Get an object, reading it from the source if it isn't loaded yet.
+    pub fn get_object(&mut self, id: ObjectId) -> Result<&Object> {
+        self.streams.tick += 1;
+        self.load(id)?;
+        self.evict();
+        self.document.get_object(id)
+    }
+
+    /// Get a dictionary object, reading it from the source if it isn't loaded yet.
+    pub fn get_dictionary(&mut self, id: ObjectId) -> Result<&Dictionary> {
+        self.get_object(id).and_then(Object::as_dict)
+    }
+
+    /// Load the page tree and return the page numbers and object IDs of all pages, like
+    /// [`Document::get_pages`].
+    pub fn get_pages(&mut self) -> Result<BTreeMap<u32, ObjectId>> {
+        self.streams.tick += 1;
+        self.load_page_tree()?;
+        Ok(self.document.get_pages())
+    }
+
+    /// Load everything a page needs to be displayed: the page, its ancestors in the page tree and
+    /// all objects they refer to, like contents, resources and annotations.
+    ///
+    /// Other pages are not loaded. The returned document can be used for page-level operations
+    /// like [`Document::get_page_content`] or [`Document::extract_text`].
+    pub fn load_page(&mut self, page_id: ObjectId) -> Result<&Document> {
+        self.streams.tick += 1;
+        self.load_page_tree()?;
+
+        let mut pending = vec![Object::Reference(page_id)];
+        let mut seen = HashSet::new();
+        let mut node = Some(page_id);
+        while let Some(node_id) = node {
+            if !seen.insert(node_id) {
+                return Err(Error::ReferenceCycle(node_id));
+            }
+            let dict = self.document.get_dictionary(node_id)?;
+            // The page itself is followed below; ancestors only for inheritable attributes.
+            if node_id != page_id {
+                pending.extend(
+                    dict.iter()
+                        .filter(|(key, _)| !matches!(key.as_slice(), b"Kids" | b"Parent"))
+                        .map(|(_, value)| value.clone()),
+                );
+            }
+            node = dict.get(b"Parent").and_then(Object::as_reference).ok();
+        }
+
+        let mut visited = HashSet::new();
+        while let Some(object) = pending.pop() {
+            match object {
+                Object::Reference(id) => {
+                    if !visited.insert(id) || self.load(id).is_err() {
+                        continue;
+                    }
+                    let object = self.document.get_object(id)?;
+                    let other_page = id != page_id
+                        && object
+                            .type_name()
+                            .is_ok_and(|name| [b"Page".as_slice(), b"Pages", b"Catalog"].contains(&name));
+                    match object {
+                        // Only the dictionary of a stream can refer to other objects.
+                        Object::Stream(stream) => pending.push(Object::Dictionary(stream.dict.clone())),
+                        _ if !other_page => pending.push(object.clone()),
+                        _ => {}
+                    }
+                }
+                Object::Array(array) => pending.extend(array),
+                Object::Dictionary(dict) => pending.extend(
+                    dict.into_iter()
+                        .filter(|(key, _)| key.as_slice() != b"Parent")
+                        .map(|(_, value)| value),
+                ),
+                Object::Stream(stream) => pending.extend(stream.dict.into_iter().map(|(_, value)| value)),
+                _ => {}
+            }
+        }
+        self.evict();
+        Ok(&self.document)
+    }
+
+    /// Load the catalog and all nodes of the page tree.
+    fn load_page_tree(&mut self) -> Result<()> {
+        let catalog_id = self.document.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let mut pending = vec![self.load(catalog_id)?.as_dict()?.get(b"Pages")?.clone()];
+        let mut seen = HashSet::new();
+        while let Some(node) = pending.pop() {
+            let Ok(id) = node.as_reference() else {
+                continue;
+            };
+            if !seen.insert(id) {
+                continue;
+            }
+            let Ok(dict) = self.load(id).and_then(Object::as_dict) else {
+                continue;
+            };
+            let kids = dict.get(b"Kids").cloned();
+            match kids {
+                Ok(Object::Array(kids)) => pending.extend(kids),
+                Ok(Object::Reference(kids_id)) => {
+                    if let Ok(kids) = self.load(kids_id).and_then(Object::as_array) {
+                        pending.extend(kids.iter().cloned());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure an object is loaded and return it.
+    fn load(&mut self, id: ObjectId) -> Result<&Object> {
+        if self.document.objects.contains_key(&id) {
+            if let Some(entry) = self.streams.entries.get_mut(&id) {
+                entry.1 = self.streams.tick;
+            }
+            return self.document.get_object(id);
+        }
+
+        let entry = self.document.reference_table.get(id.0).cloned();
+        let mut object = match entry {
+            Some(XrefEntry::Normal { offset, generation }) if generation == id.1 => {
+                self.read_object(id, offset as u64)?
+            }
+            Some(XrefEntry::Compressed { container, .. }) if id.1 == 0 => {
+                self.load_object_stream(container)?;
+                return self.document.get_object(id);
+            }
+            _ => return Err(Error::ObjectNotFound(id)),
+        };
+
+        if let Some(state) = &self.document.encryption_state {
+            if Some(id) != self.encrypt_id {
+                encryption::decrypt_object(state, id, &mut object)?;
+            }
+        }
+        if let Object::Stream(stream) = &mut object {
+            let _ = stream.decompress();
+            self.streams.size += stream.content.len();
+            self.streams
+                .entries
+                .insert(id, (stream.content.len(), self.streams.tick));
+        }
+        self.document.objects.insert(id, object);
+        self.document.get_object(id)
+    }
+
+    /// Read an object stream and add the objects it holds according to the cross-reference table.
+    fn load_object_stream(&mut self, container: u32) -> Result<()> {
+        let id = (container, 0);
+        let offset = match self.document.reference_table.get(container) {
+            Some(&XrefEntry::Normal { offset, generation: 0 }) => offset,
+            _ => return Err(Error::ObjectNotFound(id)),
+        };
+        let mut object = self.read_object(id, offset as u64)?;
+        if let Some(state) = &self.document.encryption_state {
+            encryption::decrypt_object(state, id, &mut object)?;
+        }
+        let object_stream = ObjectStream::new(object.as_stream_mut()?)?;
+        for (object_id, object) in object_stream.objects {
+            let listed = matches!(
+                self.document.reference_table.get(object_id.0),
+                Some(&XrefEntry::Compressed { container: c, .. }) if c == container && object_id.1 == 0
+            );
+            if listed {
+                self.document.objects.entry(object_id).or_insert(object);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove least recently used streams from the document until the cache fits its budget.
+    fn evict(&mut self) {
+        while self.streams.size > self.streams.capacity {
+            let oldest = self
+                .streams
+                .entries
+                .iter()
+                .filter(|(_, (_, tick))| *tick < self.streams.tick)
+                .min_by_key(|(_, (_, tick))| *tick)
+                .map(|(id, _)| *id);
+            let Some(id) = oldest else {
+                break;
+            };
+            let (size, _) = self.streams.entries.remove(&id).unwrap_or_default();
+            self.streams.size -= size;
+            self.document.objects.remove(&id);
+        }
+    }
+
+    /// Read up to `len` bytes at an offset relative to the PDF header.
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(len);
+        self.source.seek(SeekFrom::Start(self.base + offset))?;
+        self.source.by_ref().take(len as u64).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Parse the indirect object at an offset, reading larger windows until it is complete or
+    /// reaches the next object.
+    fn read_object(&mut self, id: ObjectId, offset: u64) -> Result<Object> {
+        let next = self.boundaries.partition_point(|&boundary| boundary <= offset);
+        let limit = self.boundaries.get(next).map_or(u64::MAX, |boundary| boundary - offset);
+        let mut window = WINDOW_SIZE;
+        loop {
+            window = window.min(usize::try_from(limit).unwrap_or(usize::MAX));
+            let bytes = self.read_at(offset, window)?;
+            let at_end = bytes.len() < window || window as u64 == limit;
+            // A window that doesn't reach `endobj` could cut a trailing number short.
+            let has_end = bytes.windows(6).any(|w| w == b"endobj");
+            let reader = Reader {
+                buffer: &bytes,
+                document: Document::new(),
+                stop: Arc::new(AtomicBool::new(false)),
+            };
+            let result = parser::indirect_object(
+                ParserInput::new_extra(&bytes, "indirect object"),
+                0,
+                Some(id),
+                &reader,
+                &mut HashSet::new(),
+            );
+            match result {
+                Ok((_, mut object)) if has_end || at_end => {
+                    if let Object::Stream(stream) = &mut object {
+                        if let Some(start) = stream.start_position.filter(|_| stream.content.is_empty()) {
+                            // The length is an indirect object, which the parser couldn't resolve.
+                            let length_id = stream.dict.get(b"Length").and_then(Object::as_reference)?;
+                            let length = self.load(length_id)?.as_i64()?;
+                            let length = usize::try_from(length).map_err(|e| Error::NumericCast(e.to_string()))?;
+                            let content = self.read_at(offset + start as u64, length)?;
+                            if content.len() < length {
+                                return Err(Error::InvalidStream("stream extends after document end.".to_string()));
+                            }
+                            stream.set_content(content);
+                        }
+                    }
+                    return Ok(object);
+                }
+                Err(Error::ObjectIdMismatch) => return Err(Error::ObjectIdMismatch),
+                Err(err) if at_end => return Err(err),
+                _ => window *= 4,
+            }
+        }
+    }
+
+    /// Read the cross-reference sections and trailer, following `/Prev` and `/XRefStm`.
+    fn read_xref(&mut self) -> Result<()> {
+        let length = self.source.seek(SeekFrom::End(0))? - self.base;
+        let tail_start = length.saturating_sub(1024);
+        let tail = self.read_at(tail_start, 1024)?;
+        let xref_start = Reader::get_xref_start(&tail)? as u64;
+        if xref_start > length {
+            return Err(Error::Xref(XrefError::Start));
+        }
+        self.document.xref_start = xref_start as usize;
+
+        let (mut xref, mut trailer) = self.read_hybrid_xref_section(xref_start)?;
+        let mut section_starts = vec![xref_start];
+        let mut already_seen = HashSet::new();
+        let mut prev_xref_start = trailer.remove(b"Prev");
+        trailer.remove(b"XRefStm");
+        while let Some(prev) = prev_xref_start.and_then(|offset| offset.as_i64().ok()) {
+            if !already_seen.insert(prev) {
+                break;
+            }
+            if prev < 0 || prev as u64 > length {
+                return Err(Error::Xref(XrefError::PrevStart));
+            }
+            let (prev_xref, prev_trailer) = self.read_hybrid_xref_section(prev as u64)?;
+            section_starts.push(prev as u64);
+            xref.merge(prev_xref);
+            prev_xref_start = prev_trailer.get(b"Prev").cloned().ok();
+        }
+
+        let xref_entry_count = xref.max_id().checked_add(1).ok_or(ParseError::InvalidXref)?;
+        if xref.size != xref_entry_count {
+            warn!(
+                "Size entry of trailer dictionary is {}, correct value is {}.",
+                xref.size, xref_entry_count
+            );
+            xref.size = xref_entry_count;
+        }
+        self.document.max_id = xref.size - 1;
+        self.document.trailer = trailer;
+
+        self.boundaries = xref
+            .entries
+            .values()
+            .filter_map(|entry| match *entry {
+                XrefEntry::Normal { offset, .. } => Some(u64::from(offset)),
+                _ => None,
+            })
+            .chain(section_starts)
+            .chain([length])
+            .collect();
+        self.boundaries.sort_unstable();
+        self.boundaries.dedup();
+        self.document.reference_table = xref;
+        Ok(())
+    }
+
+    /// Read a cross-reference section together with the `/XRefStm` stream of its trailer, if any.
+    fn read_hybrid_xref_section(&mut self, offset: u64) -> Result<(crate::xref::Xref, Dictionary)> {
+        let (mut xref, trailer) = self.read_xref_section(offset)?;
+        if let Ok(stream_start) = trailer.get(b"XRefStm").and_then(Object::as_i64) {
+            if stream_start < 0 {
+                return Err(Error::Xref(XrefError::StreamStart));
+            }
+            let (stream_xref, _) = self.read_xref_section(stream_start as u64)?;
+            xref.merge_hybrid(stream_xref);
+        }
+        Ok((xref, trailer))
+    }
+
+    fn read_xref_section(&mut self, offset: u64) -> Result<(crate::xref::Xref, Dictionary)> {
+        let mut window = WINDOW_SIZE;
+        loop {
+            let bytes = self.read_at(offset, window)?;
+            let at_end = bytes.len() < window;
+            let reader = Reader {
+                buffer: &bytes,
+                document: Document::new(),
+                stop: Arc::new(AtomicBool::new(false)),
+            };
+            match parser::xref_and_trailer(ParserInput::new_extra(&bytes, "xref"), &reader) {
+                Ok(section) => return Ok(section),
+                Err(err) if at_end => return Err(err),
+                Err(_) => window *= 4,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::LazyDocument;
+    use crate::creator::tests::create_document_with_texts;
+    use crate::{Document, Object};
+
+    #[test]
+    fn lazy_objects_match_eager_loading() {
+        for bytes in [
+            include_bytes!("../assets/example.pdf").as_slice(),
+            include_bytes!("../assets/Incremental.pdf").as_slice(),
+        ] {
+            let eager = Document::load_mem(bytes).unwrap();
+            let mut lazy = LazyDocument::new(Cursor::new(bytes)).unwrap();
+            assert!(lazy.document().objects.is_empty());
+            assert_eq!(lazy.document().trailer, eager.trailer);
+            assert_eq!(lazy.get_pages().unwrap(), eager.get_pages());
+
+            for (id, object) in &eager.objects {
+                match (lazy.get_object(*id).unwrap(), object) {
+                    (Object::Stream(lazy), Object::Stream(eager)) => {
+                        assert_eq!(
+                            lazy.content,
+                            eager.decompressed_content().unwrap_or(eager.content.clone())
+                        );
+                    }
+                    (lazy, eager) => assert_eq!(lazy, eager),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn load_page_only() {
+        let mut bytes = vec![];
+        create_document_with_texts(&["first", "second"])
+            .save_to(&mut bytes)
+            .unwrap();
+        let mut lazy = LazyDocument::new(Cursor::new(bytes)).unwrap().with_cache_size(0);
+        let pages = lazy.get_pages().unwrap();
+        assert_eq!(pages.len(), 2);
+
+        let first = lazy.load_page(pages[&1]).unwrap();
+        assert_eq!(first.extract_text(&[1]).unwrap().trim(), "first");
+        let first_contents = first.get_page_contents(pages[&1]);
+        let second_contents = first.get_page_contents(pages[&2]);
+        assert!(!first.objects.contains_key(&second_contents[0]));
+
+        let second = lazy.load_page(pages[&2]).unwrap();
+        assert_eq!(second.extract_text(&[2]).unwrap().trim(), "second");
+        // The content of the first page was evicted to make room.
+        assert!(!second.objects.contains_key(&first_contents[0]));
+        assert!(lazy.get_object(first_contents[0]).is_ok());
+    }
+
+    /// Source that records how far it has been read.
+    struct TrackedCursor {
+        inner: Cursor<Vec<u8>>,
+        bytes_read: usize,
+    }
+
+    impl std::io::Read for TrackedCursor {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.bytes_read += read;
+            Ok(read)
+        }
+    }
+
+    impl std::io::Seek for TrackedCursor {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn corrupt_object_is_not_read_past_the_next_object() {
+        let mut doc = create_document_with_texts(&["text"]);
+        let broken_id = doc.add_object(Object::string_literal("broken"));
+        let large_id = doc.add_object(crate::Stream::new(crate::Dictionary::new(), vec![b'x'; 1 << 20]));
+        let mut bytes = vec![];
+        doc.save_to(&mut bytes).unwrap();
+        let broken = bytes.windows(8).position(|w| w == b"(broken)").unwrap();
+        bytes[broken..broken + 8].copy_from_slice(b"<broken>");
+
+        let source = TrackedCursor {
+            inner: Cursor::new(bytes),
+            bytes_read: 0,
+        };
+        let mut lazy = LazyDocument::new(source).unwrap();
+        lazy.source.bytes_read = 0;
+        assert!(lazy.get_object(broken_id).is_err());
+        assert!(lazy.source.bytes_read < (1 << 20));
+        assert!(lazy.get_object(large_id).is_ok());
+    }
+
+    #[test]
+    fn open_encrypted_with_password() {
+        use crate::encryption::{EncryptionState, EncryptionVersion, Permissions};
+
+        let mut doc = create_document_with_texts(&["text"]);
+        let secret_id = doc.add_object(Object::string_literal("secret"));
+        let version = EncryptionVersion::V2 {
+            document: &doc,
+            owner_password: "owner",
+            user_password: "user",
+            key_length: 128,
+            permissions: Permissions::all(),
+        };
+        let state = EncryptionState::try_from(version).unwrap();
+        doc.encrypt(&state).unwrap();
+        let mut bytes = vec![];
+        doc.save_to(&mut bytes).unwrap();
+
+        assert!(LazyDocument::new(Cursor::new(bytes.as_slice())).is_err());
+        assert!(LazyDocument::new_with_password(Cursor::new(bytes.as_slice()), "wrong").is_err());
+        for password in ["user", "owner"] {
+            let mut lazy = LazyDocument::new_with_password(Cursor::new(bytes.as_slice()), password).unwrap();
+            assert_eq!(lazy.get_object(secret_id).unwrap().as_str().unwrap(), b"secret");
+        }
+    }
+}
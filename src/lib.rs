@@ -16,7 +16,10 @@
 mod object;
 mod document;
 mod incremental_document;
+mod lazy_document;
+mod linearization;
 
+mod annotation;
 mod bookmarks;
 mod cmap_section;
 mod common_data_structures;
@@ -25,8 +28,16 @@
 mod destinations;
 mod encodings;
 mod error;
+mod fdf;
+mod flatten;
+mod form;
+mod imposition;
 mod outlines;
+mod page_boxes;
+mod page_labels;
 mod processor;
+mod revision;
+mod signature;
 mod toc;
 mod writer;
 
@@ -38,16 +49,28 @@
 pub use document::Document;
 pub use object::{Dictionary, Object, ObjectId, Stream, StringFormat};
 
+pub use annotation::{Annotation, AnnotationCommon};
 pub use bookmarks::Bookmark;
-pub use common_data_structures::{decode_text_string, text_string};
+pub use common_data_structures::{decode_text_string, text_string, NameTree, NumberTree, Rect, Tree, TreeKey};
 pub use destinations::Destination;
 pub use encodings::{encode_utf16_be, encode_utf8, Encoding};
-pub use encryption::{EncryptionState, EncryptionVersion, Permissions};
+pub use encryption::{
+    EncryptionState, EncryptionVersion, Permissions, Recipient, RecipientDecryptor, RecipientEncryptor,
+};
 pub use error::{Error, Result};
+pub use fdf::{Fdf, FdfAnnotation, FdfField};
+pub use flatten::FlattenOptions;
+pub use form::{FieldFlags, FieldType, FieldValue, FormField};
+pub use imposition::NUpOptions;
 pub use incremental_document::IncrementalDocument;
+pub use lazy_document::LazyDocument;
 pub use object_stream::ObjectStream;
 pub use outlines::Outline;
+pub use page_boxes::BoxKind;
+pub use page_labels::{PageLabelRange, PageLabelStyle};
 pub use reader::Reader;
+pub use revision::Revision;
+pub use signature::{SignatureInfo, SignatureOptions, SignatureVerification, SignatureVerifier, Signer};
 pub use toc::Toc;
 
 pub use parser_aux::substring;
@@ -0,0 +1,595 @@
+//! Linearized ("Fast Web View") output, ISO 32000-1 Annex F.
+//!
+//! The file is laid out as: header, linearization parameter dictionary, first-page cross-reference
+//! section, catalog, primary hint stream, first-page objects, the objects of the remaining pages
+//! one page at a time, objects shared between those pages, all other objects and finally the main
+//! cross-reference section. Objects of the first-page section get the highest object numbers so
+//! that each cross-reference section covers a contiguous range.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::encryption::encrypt_object;
+use crate::writer::{Writer, file_id};
+use crate::{Dictionary, Document, EncryptionState, Error, Object, ObjectId, Result, Stream};
+
+/// Inheritable page attributes whose values belong to the page they are inherited by.
+const INHERITED_ATTRIBUTES: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Width reserved for the linearization parameter dictionary, so that it can be written before
+/// the offsets it contains are known.
+const PARAMETERS_WIDTH: usize = 200;
+
+impl Document {
+    /// Save the document linearized to the specified file path.
+    pub fn save_linearized<P: AsRef<Path>>(&mut self, path: P) -> Result<File> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.save_linearized_to(&mut file)?;
+        Ok(file.into_inner().map_err(|err| err.into_error())?)
+    }
+
+    /// Save the document linearized, so that a viewer can display the first page before the
+    /// whole file has been downloaded.
+    ///
+    /// Objects are renumbered and written uncompressed with cross-reference tables; object streams
+    /// are not used. A document that was decrypted is encrypted again with the security settings
+    /// it was decrypted with, under the new object numbers. A document that is still encrypted
+    /// can't be renumbered and fails with [`Error::AlreadyEncrypted`].
+    pub fn save_linearized_to<W: Write>(&mut self, target: &mut W) -> Result<()> {
+        // The keys of encrypted strings and streams depend on their object numbers.
+        if self.trailer.has(b"Encrypt") {
+            return Err(Error::AlreadyEncrypted);
+        }
+        // The identifier is written in the first-page section, before the rest of the file, so it
+        // is computed from the objects rather than the bytes written.
+        let first_id = file_id(&self.trailer);
+        let digest = self.generate_file_id();
+        self.update_file_id(first_id, digest);
+        let Some(state) = self.encryption_state.clone() else {
+            return self.write_linearized(target, None);
+        };
+        let max_id = self.max_id;
+        let encrypt_id = self.add_object(state.encode()?);
+        self.trailer.set("Encrypt", encrypt_id);
+        let result = self.write_linearized(target, Some((&state, encrypt_id)));
+        self.trailer.remove(b"Encrypt");
+        self.objects.remove(&encrypt_id);
+        self.max_id = max_id;
+        result
+    }
+
+    fn write_linearized<W: Write>(
+        &self, target: &mut W, encryption: Option<(&EncryptionState, ObjectId)>,
+    ) -> Result<()> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        self.get_dictionary(catalog_id)?;
+        let page_ids: Vec<ObjectId> = self.get_pages().into_values().collect();
+        if page_ids.is_empty() {
+            return Err(Error::PageNumberNotFound(1));
+        }
+
+        // Partition the objects by the pages that use them.
+        let page_objects: Vec<Vec<ObjectId>> = page_ids.iter().map(|&id| self.page_objects(id)).collect();
+        let first_page: Vec<ObjectId> = page_objects[0].clone();
+        let in_first_page: HashSet<ObjectId> = first_page.iter().copied().collect();
+        let mut usage: HashMap<ObjectId, usize> = HashMap::new();
+        for objects in &page_objects[1..] {
+            for id in objects.iter().filter(|id| !in_first_page.contains(id)) {
+                *usage.entry(*id).or_default() += 1;
+            }
+        }
+        let private_pages: Vec<Vec<ObjectId>> = page_objects[1..]
+            .iter()
+            .map(|objects| objects.iter().copied().filter(|id| usage.get(id) == Some(&1)).collect())
+            .collect();
+        let mut shared: Vec<ObjectId> = vec![];
+        for objects in &page_objects[1..] {
+            for id in objects {
+                if usage.get(id).is_some_and(|&count| count > 1) && !shared.contains(id) {
+                    shared.push(*id);
+                }
+            }
+        }
+        let placed: HashSet<ObjectId> = page_objects.iter().flatten().copied().chain([catalog_id]).collect();
+        let others: Vec<ObjectId> = self
+            .objects
+            .iter()
+            .filter(|(id, object)| !placed.contains(id) && !is_skipped(object))
+            .map(|(id, _)| *id)
+            .collect();
+
+        // Number the objects after the first page from 1 and the first-page section after them.
+        let low: Vec<ObjectId> = private_pages
+            .iter()
+            .flatten()
+            .chain(&shared)
+            .chain(&others)
+            .copied()
+            .collect();
+        let first_number = low.len() as u32 + 1;
+        let mut numbers: HashMap<ObjectId, u32> = HashMap::new();
+        for (index, id) in low.iter().enumerate() {
+            numbers.insert(*id, index as u32 + 1);
+        }
+        let parameters_number = first_number;
+        numbers.insert(catalog_id, first_number + 1);
+        let hint_number = first_number + 2;
+        for (index, id) in first_page.iter().enumerate() {
+            numbers.insert(*id, first_number + 3 + index as u32);
+        }
+        let size = first_number + 3 + first_page.len() as u32;
+
+        let render = |id: &ObjectId| -> Result<Vec<u8>> {
+            let mut object = self.objects[id].clone();
+            renumber(&mut object, &numbers);
+            match encryption {
+                Some((state, encrypt_id)) if encrypt_id != *id => {
+                    encrypt_object(state, (numbers[id], 0), &mut object)?;
+                }
+                _ => {}
+            }
+            render_object(numbers[id], &object)
+        };
+        let catalog = render(&catalog_id)?;
+        let first_page_bodies = first_page.iter().map(render).collect::<Result<Vec<_>>>()?;
+        let page_bodies = private_pages
+            .iter()
+            .map(|objects| objects.iter().map(render).collect::<Result<Vec<_>>>())
+            .collect::<Result<Vec<_>>>()?;
+        let shared_bodies = shared.iter().map(render).collect::<Result<Vec<_>>>()?;
+        let other_bodies = others.iter().map(render).collect::<Result<Vec<_>>>()?;
+
+        let mut header = format!("%PDF-{}\n", self.version).into_bytes();
+        Writer::write_binary_mark(&mut header, &self.binary_mark)?;
+
+        let mut trailer = Dictionary::new();
+        trailer.set("Size", i64::from(size));
+        for key in [b"Root".as_slice(), b"Info", b"ID", b"Encrypt"] {
+            if let Ok(value) = self.trailer.get(key) {
+                let mut value = value.clone();
+                renumber(&mut value, &numbers);
+                trailer.set(key, value);
+            }
+        }
+        let first_xref_count = size - first_number;
+        let tail = b"\nstartxref\n0\n%%EOF\n";
+        // Room for the trailer once `/Prev` has been added to it.
+        let first_xref_width = format!("xref\n{first_number} {first_xref_count}\n").len()
+            + 20 * first_xref_count as usize
+            + render_trailer(&trailer)?.len()
+            + format!("/Prev {}", u64::MAX).len()
+            + tail.len();
+
+        // Offsets as if the hint stream were absent, which is how the hint tables record them.
+        let body_start = header.len() + PARAMETERS_WIDTH + first_xref_width;
+        let mut offset = body_start + catalog.len();
+        let first_page_offsets = layout(&mut offset, &first_page_bodies);
+        let first_page_end = offset;
+        let page_offsets: Vec<Vec<usize>> = page_bodies.iter().map(|bodies| layout(&mut offset, bodies)).collect();
+        let shared_offsets = layout(&mut offset, &shared_bodies);
+        let other_offsets = layout(&mut offset, &other_bodies);
+
+        // Shared object identifiers: objects of the first page come first, then the shared objects.
+        let mut shared_ids: HashMap<ObjectId, u32> = HashMap::new();
+        for (index, id) in first_page.iter().chain(&shared).enumerate() {
+            shared_ids.insert(*id, index as u32);
+        }
+        let mut pages = vec![PageHint {
+            objects: first_page.len() as u32,
+            length: (first_page_end - first_page_offsets[0]) as u32,
+            shared: vec![],
+        }];
+        for (objects, bodies) in page_objects[1..].iter().zip(&page_bodies) {
+            pages.push(PageHint {
+                objects: bodies.len() as u32,
+                length: bodies.iter().map(Vec::len).sum::<usize>() as u32,
+                shared: objects
+                    .iter()
+                    .filter(|id| usage.get(id) != Some(&1))
+                    .map(|id| shared_ids[id])
+                    .collect(),
+            });
+        }
+        let group_lengths: Vec<u32> = first_page_bodies
+            .iter()
+            .chain(&shared_bodies)
+            .map(|body| body.len() as u32)
+            .collect();
+        let (hint_data, shared_table) = hint_tables(
+            &pages,
+            first_page_offsets[0] as u32,
+            &group_lengths,
+            first_page.len() as u32,
+            shared.first().map_or(0, |id| numbers[id]),
+            shared_offsets.first().map_or(0, |&offset| offset as u32),
+        );
+        let mut hint = Stream::new(dictionary! { "S" => shared_table as i64 }, hint_data);
+        hint.allows_compression = false;
+        let mut hint = Object::Stream(hint);
+        if let Some((state, _)) = encryption {
+            encrypt_object(state, (hint_number, 0), &mut hint)?;
+        }
+        let hint = render_object(hint_number, &hint)?;
+
+        // Final offsets, with the hint stream in place.
+        let hint_offset = body_start + catalog.len();
+        let shift = |offsets: &[usize]| offsets.iter().map(|offset| offset + hint.len()).collect::<Vec<_>>();
+        let first_page_end = first_page_end + hint.len();
+        let main_xref = offset + hint.len();
+        let main_xref_head = format!("xref\n0 {first_number}");
+        let mut main_section = format!("{main_xref_head}\n0000000000 65535 f \n").into_bytes();
+        for offset in page_offsets
+            .iter()
+            .flatten()
+            .chain(&shared_offsets)
+            .chain(&other_offsets)
+        {
+            writeln!(main_section, "{:010} 00000 n ", offset + hint.len())?;
+        }
+        write!(
+            main_section,
+            "trailer\n<</Size {first_number}>>\nstartxref\n{}\n%%EOF\n",
+            header.len() + PARAMETERS_WIDTH
+        )?;
+        let file_length = main_xref + main_section.len();
+
+        let mut parameters = format!(
+            "{parameters_number} 0 obj\n<</Linearized 1/L {file_length}/H [{hint_offset} {}]/O {}/E {first_page_end}/N {}/T {}>>\nendobj\n",
+            hint.len(),
+            numbers[&page_ids[0]],
+            page_ids.len(),
+            main_xref + main_xref_head.len(),
+        )
+        .into_bytes();
+        // Pad with white space before `endobj`.
+        let padding = PARAMETERS_WIDTH - parameters.len();
+        let at = parameters.len() - "endobj\n".len();
+        parameters.splice(at..at, std::iter::repeat_n(b' ', padding));
+
+        let mut first_section = format!("xref\n{first_number} {first_xref_count}\n").into_bytes();
+        let first_offsets = [header.len(), body_start, hint_offset]
+            .into_iter()
+            .chain(shift(&first_page_offsets));
+        for offset in first_offsets {
+            writeln!(first_section, "{offset:010} 00000 n ")?;
+        }
+        trailer.set("Prev", main_xref as i64);
+        first_section.extend(render_trailer(&trailer)?);
+        first_section.resize(first_xref_width - tail.len(), b' ');
+        first_section.extend_from_slice(tail);
+
+        target.write_all(&header)?;
+        target.write_all(&parameters)?;
+        target.write_all(&first_section)?;
+        target.write_all(&catalog)?;
+        target.write_all(&hint)?;
+        for body in first_page_bodies
+            .iter()
+            .chain(page_bodies.iter().flatten())
+            .chain(&shared_bodies)
+            .chain(&other_bodies)
+        {
+            target.write_all(body)?;
+        }
+        target.write_all(&main_section)?;
+        Ok(())
+    }
+
+    /// Objects used by a page, starting with the page object itself, in the order they are
+    /// reached. Other pages, the page tree and the catalog are not followed.
+    fn page_objects(&self, page_id: ObjectId) -> Vec<ObjectId> {
+        let mut objects = vec![page_id];
+        let mut seen: HashSet<ObjectId> = objects.iter().copied().collect();
+        let mut pending: Vec<&Object> = vec![];
+        if let Ok(page) = self.get_dictionary(page_id) {
+            for key in INHERITED_ATTRIBUTES {
+                if !page.has(key) {
+                    pending.extend(self.get_inherited_page_attribute(page_id, key).ok());
+                }
+            }
+            pending.extend(
+                page.iter()
+                    .filter(|(key, _)| key.as_slice() != b"Parent")
+                    .map(|(_, value)| value),
+            );
+        }
+        pending.reverse();
+
+        while let Some(object) = pending.pop() {
+            let children: Vec<&Object> = match object {
+                Object::Reference(id) => {
+                    let Ok(target) = self.get_object(*id) else {
+                        continue;
+                    };
+                    let stop = target
+                        .type_name()
+                        .is_ok_and(|name| [b"Page".as_slice(), b"Pages", b"Catalog"].contains(&name));
+                    if stop || is_skipped(target) || !seen.insert(*id) {
+                        continue;
+                    }
+                    objects.push(*id);
+                    vec![target]
+                }
+                Object::Array(array) => array.iter().collect(),
+                Object::Dictionary(dict) => dict
+                    .iter()
+                    .filter(|(key, _)| key.as_slice() != b"Parent")
+                    .map(|(_, value)| value)
+                    .collect(),
+                Object::Stream(stream) => stream.dict.iter().map(|(_, value)| value).collect(),
+                _ => vec![],
+            };
+            pending.extend(children.into_iter().rev());
+        }
+        objects
+    }
+}
+
+/// Objects that are regenerated or dropped when saving.
+fn is_skipped(object: &Object) -> bool {
+    object
+        .type_name()
+        .is_ok_and(|name| [b"ObjStm".as_slice(), b"XRef", b"Linearized"].contains(&name))
+}
+
+fn renumber(object: &mut Object, numbers: &HashMap<ObjectId, u32>) {
+    match object {
+        Object::Reference(id) => {
+            *object = match numbers.get(id) {
+                Some(&number) => Object::Reference((number, 0)),
+                None => Object::Null,
+            }
+        }
+        Object::Array(array) => array.iter_mut().for_each(|item| renumber(item, numbers)),
+        Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, value)| renumber(value, numbers)),
+        Object::Stream(stream) => stream.dict.iter_mut().for_each(|(_, value)| renumber(value, numbers)),
+        _ => {}
+    }
+}
+
+/// Offsets of consecutive objects starting at `offset`, which is advanced past them.
+fn layout(offset: &mut usize, bodies: &[Vec<u8>]) -> Vec<usize> {
+    bodies
+        .iter()
+        .map(|body| {
+            let start = *offset;
+            *offset += body.len();
+            start
+        })
+        .collect()
+}
+
+fn render_object(number: u32, object: &Object) -> Result<Vec<u8>> {
+    let mut bytes = format!("{number} 0 obj\n").into_bytes();
+    Writer::write_object(&mut bytes, object)?;
+    bytes.extend_from_slice(b"\nendobj\n");
+    Ok(bytes)
+}
+
+fn render_trailer(trailer: &Dictionary) -> Result<Vec<u8>> {
+    let mut bytes = b"trailer\n".to_vec();
+    Writer::write_object(&mut bytes, &Object::Dictionary(trailer.clone()))?;
+    Ok(bytes)
+}
+
+/// Per-page entry of the page offset hint table.
+struct PageHint {
+    objects: u32,
+    length: u32,
+    /// Identifiers of the shared objects the page references.
+    shared: Vec<u32>,
+}
+
+/// Writes values most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, width: u32) {
+        for bit in (0..width).rev() {
+            if self.bits % 8 == 0 {
+                self.bytes.push(0);
+            }
+            if value >> bit & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+
+    /// Continue at the next byte boundary.
+    fn align(&mut self) {
+        self.bits = self.bytes.len() as u32 * 8;
+    }
+}
+
+/// Number of bits needed to represent `value`.
+fn bit_width(value: u32) -> u32 {
+    u32::BITS - value.leading_zeros()
+}
+
+/// The page offset hint table followed by the shared object hint table (ISO 32000-1 §F.4), and
+/// the offset of the latter in the stream data.
+fn hint_tables(
+    pages: &[PageHint], first_page_offset: u32, group_lengths: &[u32], first_page_groups: u32,
+    first_shared_number: u32, first_shared_offset: u32,
+) -> (Vec<u8>, usize) {
+    let least_objects = pages.iter().map(|page| page.objects).min().unwrap_or(0);
+    let most_objects = pages.iter().map(|page| page.objects).max().unwrap_or(0);
+    let least_length = pages.iter().map(|page| page.length).min().unwrap_or(0);
+    let most_length = pages.iter().map(|page| page.length).max().unwrap_or(0);
+    let most_shared = pages.iter().map(|page| page.shared.len() as u32).max().unwrap_or(0);
+    let greatest_identifier = pages
+        .iter()
+        .flat_map(|page| page.shared.iter().copied())
+        .max()
+        .unwrap_or(0);
+    let objects_bits = bit_width(most_objects - least_objects);
+    let length_bits = bit_width(most_length - least_length);
+    let shared_bits = bit_width(most_shared);
+    let identifier_bits = bit_width(greatest_identifier);
+
+    let mut writer = BitWriter::default();
+    writer.write(least_objects, 32);
+    writer.write(first_page_offset, 32);
+    writer.write(objects_bits, 16);
+    writer.write(least_length, 32);
+    writer.write(length_bits, 16);
+    // Content stream offsets and lengths, and fractional positions of shared objects, are not
+    // recorded.
+    writer.write(0, 32);
+    writer.write(0, 16);
+    writer.write(0, 32);
+    writer.write(0, 16);
+    writer.write(shared_bits, 16);
+    writer.write(identifier_bits, 16);
+    writer.write(0, 16);
+    writer.write(1, 16);
+    for page in pages {
+        writer.write(page.objects - least_objects, objects_bits);
+    }
+    writer.align();
+    for page in pages {
+        writer.write(page.length - least_length, length_bits);
+    }
+    writer.align();
+    for page in pages {
+        writer.write(page.shared.len() as u32, shared_bits);
+    }
+    writer.align();
+    for page in pages {
+        for &identifier in &page.shared {
+            writer.write(identifier, identifier_bits);
+        }
+    }
+    writer.align();
+
+    let shared_table = writer.bytes.len();
+    let least_group = group_lengths.iter().copied().min().unwrap_or(0);
+    let group_bits = bit_width(group_lengths.iter().copied().max().unwrap_or(0) - least_group);
+    writer.write(first_shared_number, 32);
+    writer.write(first_shared_offset, 32);
+    writer.write(first_page_groups, 32);
+    writer.write(group_lengths.len() as u32, 32);
+    // Every group holds a single object.
+    writer.write(0, 16);
+    writer.write(least_group, 32);
+    writer.write(group_bits, 16);
+    for length in group_lengths {
+        writer.write(length - least_group, group_bits);
+    }
+    writer.align();
+    // No group has a signature.
+    for _ in group_lengths {
+        writer.write(0, 1);
+    }
+    writer.align();
+    (writer.bytes, shared_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::creator::tests::create_document_with_texts;
+    use crate::xref::XrefEntry;
+    use crate::{Document, Object};
+
+    #[test]
+    fn save_linearized() {
+        let mut doc = create_document_with_texts(&["first", "second", "third"]);
+        let mut bytes = vec![];
+        doc.save_linearized_to(&mut bytes).unwrap();
+
+        let loaded = Document::load_mem(&bytes).unwrap();
+        assert_eq!(loaded.get_pages().len(), 3);
+        let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap().clone();
+        assert_eq!(id.len(), 2);
+        assert_eq!(loaded.trailer.get(b"ID").unwrap().as_array().unwrap(), &id);
+        assert_eq!(loaded.extract_text(&[1]).unwrap().trim(), "first");
+        assert_eq!(loaded.extract_text(&[3]).unwrap().trim(), "third");
+
+        let offset = |id: (u32, u16)| match loaded.reference_table.get(id.0) {
+            Some(XrefEntry::Normal { offset, .. }) => *offset as usize,
+            entry => panic!("unexpected xref entry {entry:?}"),
+        };
+        let with_key = |key: &[u8]| {
+            let (id, _) = loaded
+                .objects
+                .iter()
+                .find(|(_, object)| match object {
+                    Object::Dictionary(dict) => dict.has(key),
+                    Object::Stream(stream) => stream.dict.has(key),
+                    _ => false,
+                })
+                .unwrap();
+            *id
+        };
+
+        // The linearization parameters are the first object of the file.
+        let parameters_id = with_key(b"Linearized");
+        assert!(offset(parameters_id) < 20);
+        let parameters = loaded.get_dictionary(parameters_id).unwrap();
+        assert_eq!(parameters.get(b"Linearized").unwrap(), &Object::Integer(1));
+        assert_eq!(parameters.get(b"L").unwrap().as_i64().unwrap() as usize, bytes.len());
+        assert_eq!(parameters.get(b"N").unwrap().as_i64().unwrap(), 3);
+        let pages = loaded.get_pages();
+        let first_page = pages[&1];
+        assert_eq!(parameters.get(b"O").unwrap().as_i64().unwrap(), i64::from(first_page.0));
+
+        // The first page comes before the end of the first-page section; the other pages after it.
+        let end = parameters.get(b"E").unwrap().as_i64().unwrap() as usize;
+        assert!(offset(first_page) < end);
+        assert_eq!(offset(pages[&2]), end);
+        assert!(offset(pages[&3]) > offset(pages[&2]));
+
+        // The hint stream starts with the least number of objects in a page.
+        let hint = parameters.get(b"H").unwrap().as_array().unwrap();
+        let hint_id = with_key(b"S");
+        assert_eq!(offset(hint_id), hint[0].as_i64().unwrap() as usize);
+        let hint_stream = loaded.get_object(hint_id).unwrap().as_stream().unwrap();
+        assert_eq!(&hint_stream.content[..4], &[0, 0, 0, 2]);
+        // The main cross-reference table begins at /T.
+        let main_xref = parameters.get(b"T").unwrap().as_i64().unwrap() as usize;
+        assert!(bytes[..main_xref].ends_with(format!("xref\n0 {}", parameters_id.0).as_bytes()));
+    }
+
+    #[test]
+    fn save_linearized_encrypted() {
+        use crate::encryption::{EncryptionState, EncryptionVersion, Permissions};
+
+        let mut doc = create_document_with_texts(&["first", "second"]);
+        let version = EncryptionVersion::V2 {
+            document: &doc,
+            owner_password: "owner",
+            user_password: "user",
+            key_length: 128,
+            permissions: Permissions::all(),
+        };
+        let state = EncryptionState::try_from(version).unwrap();
+        doc.encrypt(&state).unwrap();
+        let mut bytes = vec![];
+        doc.save_to(&mut bytes).unwrap();
+        // The objects are encrypted with keys for their current object numbers.
+        assert!(matches!(
+            doc.save_linearized_to(&mut vec![]),
+            Err(crate::Error::AlreadyEncrypted)
+        ));
+
+        let mut doc = Document::load_mem_with_password(&bytes, "user").unwrap();
+        let first_id = crate::writer::file_id(&doc.trailer);
+        let mut linearized = vec![];
+        doc.save_linearized_to(&mut linearized).unwrap();
+        // The first identifier is part of the encryption key and is kept.
+        assert_eq!(crate::writer::file_id(&doc.trailer), first_id);
+        assert!(doc.trailer.get(b"Encrypt").is_err());
+
+        let loaded = Document::load_mem(&linearized).unwrap();
+        assert!(loaded.is_encrypted());
+        let loaded = Document::load_mem_with_password(&linearized, "user").unwrap();
+        assert_eq!(loaded.extract_text(&[2]).unwrap().trim(), "second");
+    }
+}
@@ -2,6 +2,7 @@
 use crate::encodings::Encoding;
 use crate::encodings::cmap::ToUnicodeCMap;
 use crate::error::DecompressError;
+use crate::filters::{FilterSpec, Predictor};
 use crate::{Document, Error, Result};
 use indexmap::IndexMap;
 use log::warn;
@@ -674,16 +675,164 @@ pub fn compress(&mut self) -> Result<()> {
         Ok(())
     }
 
+    /// Encode the content with a chain of filters and record them, with their parameters, in the
+    /// `/Filter` and `/DecodeParms` entries.
+    ///
+    /// `filters` are given in decoding order, like in the `/Filter` array, so the last one is
+    /// applied first. If the stream is already encoded, the new filters are placed in front of
+    /// the existing ones.
+    pub fn encode_with(&mut self, filters: &[FilterSpec]) -> Result<()> {
+        if filters.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = None;
+        for filter in filters.iter().rev() {
+            let input = content.as_deref().unwrap_or(self.content.as_slice());
+            content = Some(match filter {
+                FilterSpec::Flate(predictor) => Self::compress_zlib(input, predictor.as_ref())?,
+                FilterSpec::Lzw(predictor) => Self::compress_lzw(input, predictor.as_ref())?,
+                FilterSpec::Ascii85 => Self::encode_ascii85(input),
+            });
+        }
+
+        let mut names: Vec<Object> = filters
+            .iter()
+            .map(|filter| Object::Name(filter.name().into()))
+            .collect();
+        let mut params: Vec<Object> = filters
+            .iter()
+            .map(|filter| filter.decode_params().map_or(Object::Null, Object::Dictionary))
+            .collect();
+        if let Ok(existing) = self.filters() {
+            names.extend(existing.into_iter().map(|name| Object::Name(name.to_vec())));
+            match self.dict.remove(b"DecodeParms") {
+                Some(Object::Array(existing)) => params.extend(existing),
+                Some(existing @ Object::Dictionary(_)) => params.push(existing),
+                _ => {}
+            }
+            params.resize(names.len(), Object::Null);
+        }
+
+        if names.len() == 1 {
+            self.dict.set("Filter", names.remove(0));
+        } else {
+            self.dict.set("Filter", names);
+        }
+        if params.iter().all(|params| matches!(params, Object::Null)) {
+            self.dict.remove(b"DecodeParms");
+        } else if params.len() == 1 {
+            self.dict.set("DecodeParms", params.remove(0));
+        } else {
+            self.dict.set("DecodeParms", params);
+        }
+
+        if let Some(content) = content {
+            self.set_content(content);
+        }
+        Ok(())
+    }
+
+    fn compress_zlib(input: &[u8], predictor: Option<&Predictor>) -> Result<Vec<u8>> {
+        use flate2::Compression;
+        use flate2::write::ZlibEncoder;
+        use std::io::prelude::*;
+
+        let input = Self::compress_predictor(input, predictor)?;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&input)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn compress_lzw(input: &[u8], predictor: Option<&Predictor>) -> Result<Vec<u8>> {
+        use weezl::{BitOrder, encode::Encoder};
+        const MIN_BITS: u8 = 9;
+
+        let input = Self::compress_predictor(input, predictor)?;
+        let mut output = vec![];
+        let mut encoder = Encoder::with_tiff_size_switch(BitOrder::Msb, MIN_BITS - 1);
+        let result = encoder.into_stream(&mut output).encode_all(input.as_ref());
+        result
+            .status
+            .map_err(|err| Error::InvalidStream(format!("LZW encoding failed: {err}")))?;
+        Ok(output)
+    }
+
+    fn encode_ascii85(input: &[u8]) -> Vec<u8> {
+        const LINE_LENGTH: usize = 75;
+
+        let mut output = Vec::with_capacity(input.len() * 5 / 4 + input.len() / LINE_LENGTH + 8);
+        let mut line_start = 0;
+        for group in input.chunks(4) {
+            let mut bytes = [0_u8; 4];
+            bytes[..group.len()].copy_from_slice(group);
+            let mut value = u32::from_be_bytes(bytes);
+
+            if group.len() == 4 && value == 0 {
+                output.push(b'z');
+            } else {
+                let mut digits = [0_u8; 5];
+                for digit in digits.iter_mut().rev() {
+                    *digit = (value % 85) as u8 + b'!';
+                    value /= 85;
+                }
+                output.extend_from_slice(&digits[..group.len() + 1]);
+            }
+
+            if output.len() - line_start >= LINE_LENGTH {
+                output.push(b'\n');
+                line_start = output.len();
+            }
+        }
+        output.extend_from_slice(b"~>");
+        output
+    }
+
+    fn compress_predictor<'a>(input: &'a [u8], predictor: Option<&Predictor>) -> Result<std::borrow::Cow<'a, [u8]>> {
+        use crate::filters::png;
+        use std::borrow::Cow;
+
+        let Some(predictor) = predictor else {
+            return Ok(Cow::Borrowed(input));
+        };
+        if predictor.colors == 0
+            || predictor.columns == 0
+            || !matches!(predictor.bits_per_component, 1 | 2 | 4 | 8 | 16)
+        {
+            return Err(Error::InvalidStream(format!(
+                "invalid predictor parameters {predictor:?}"
+            )));
+        }
+        let bytes_per_row = predictor.bytes_per_row();
+        if input.len() % bytes_per_row != 0 {
+            return Err(Error::InvalidStream(format!(
+                "content length {} is not a multiple of the predictor row length {bytes_per_row}",
+                input.len()
+            )));
+        }
+        Ok(Cow::Owned(png::encode_rows(
+            input,
+            predictor.bytes_per_pixel(),
+            bytes_per_row,
+            predictor.filter,
+        )))
+    }
+
     pub fn decompressed_content(&self) -> Result<Vec<u8>> {
-        let params = self.dict.get(b"DecodeParms").and_then(Object::as_dict).ok();
         let filters = self.filters()?;
 
         let mut input = self.content.as_slice();
         let mut output = vec![];
 
         // Filters are in decoding order.
-        for filter in filters {
+        for (index, filter) in filters.into_iter().enumerate() {
+            let params = match self.dict.get(b"DecodeParms") {
+                Ok(Object::Array(params)) => params.get(index).and_then(|params| params.as_dict().ok()),
+                params => params.and_then(Object::as_dict).ok(),
+            };
             output = match filter {
+                // Decryption is done by the security handler when the document is loaded.
+                b"Crypt" => input.to_vec(),
                 b"FlateDecode" => Self::decompress_zlib(input, params)?,
                 b"LZWDecode" => Self::decompress_lzw(input, params)?,
                 b"ASCII85Decode" => Self::decode_ascii85(input)?,
@@ -802,11 +951,12 @@ fn decompress_predictor(mut data: Vec<u8>, params: Option<&Dictionary>) -> Resul
         if let Some(params) = params {
             let predictor = params.get(b"Predictor").and_then(Object::as_i64).unwrap_or(1);
             if (10..=15).contains(&predictor) {
-                let pixels_per_row = max(1, params.get(b"Columns").and_then(Object::as_i64).unwrap_or(1)) as usize;
+                let columns = max(1, params.get(b"Columns").and_then(Object::as_i64).unwrap_or(1)) as usize;
                 let colors = max(1, params.get(b"Colors").and_then(Object::as_i64).unwrap_or(1)) as usize;
-                let bits = max(8, params.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap_or(8)) as usize;
-                let bytes_per_pixel = colors * bits / 8;
-                data = png::decode_frame(data.as_slice(), bytes_per_pixel, pixels_per_row)?;
+                let bits = max(1, params.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap_or(8)) as usize;
+                let bytes_per_pixel = max(1, colors * bits / 8);
+                let bytes_per_row = (colors * bits * columns).div_ceil(8);
+                data = png::decode_rows(data.as_slice(), bytes_per_pixel, bytes_per_row)?;
             }
             Ok(data)
         } else {
@@ -829,7 +979,9 @@ pub fn is_compressed(&self) -> bool {
 
 #[cfg(test)]
 mod test {
-    use crate::{Error, error::DecompressError};
+    use crate::filters::png::FilterType;
+    use crate::filters::{FilterSpec, Predictor};
+    use crate::{Dictionary, Error, Object, error::DecompressError};
 
     use super::Stream;
 
@@ -853,4 +1005,87 @@ fn test_decode_ascii85_overflow() {
         // let expected: Result<Vec<u8>, Error> = Err(Error::ContentDecode);
         assert!(matches!(output, Err(Error::Decompress(DecompressError::Ascii85(_)))));
     }
+
+    #[test]
+    fn test_encode_ascii85() {
+        let expected = "Man is distinguished, not only by his reason, but by this singular passion from other animals, which is a lust of the mind, that by a perseverance of delight in the continued and indefatigable generation of knowledge, exceeds the short vehemence of any carnal pleasure.";
+        let output = Stream::encode_ascii85(expected.as_bytes());
+        assert!(output.starts_with(b"9jqo^BlbD-BleB1DJ+*+F(f,q/0JhKF<GL>Cj@.4Gp$d7F!,L7@<6@)/0JDEF<G%<+EV:2F!,O<\n"));
+        assert!(output.ends_with(b"D.RTpAKYo'+CT/5+Cei#DII?(E,9)oF*2M7/c~>"));
+        assert!(output.split(|&b| b == b'\n').all(|line| line.len() <= 80));
+
+        for content in [
+            &b""[..],
+            b"\0\0\0\0\0",
+            b"\xff\xff\xff\xff\xff\xff",
+            b"\0\0\0\0z\0\0\0\0",
+        ] {
+            let output = Stream::encode_ascii85(content);
+            assert_eq!(Stream::decode_ascii85(&output).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn test_encode_with() {
+        let content: Vec<u8> = (0..32_u32 * 24 * 3).map(|i| ((i / 3) % 32 * 8 + i % 3) as u8).collect();
+        let mut stream = Stream::new(Dictionary::new(), content.clone());
+        stream
+            .encode_with(&[FilterSpec::Ascii85, FilterSpec::Flate(Some(Predictor::new(3, 8, 32)))])
+            .unwrap();
+
+        assert!(
+            stream
+                .content
+                .iter()
+                .all(|&b| b.is_ascii() && (b >= b'!' || b == b'\n'))
+        );
+        assert_eq!(stream.filters().unwrap(), vec![&b"ASCII85Decode"[..], b"FlateDecode"]);
+        let params = stream.dict.get(b"DecodeParms").and_then(Object::as_array).unwrap();
+        assert_eq!(params[0], Object::Null);
+        let params = params[1].as_dict().unwrap();
+        assert_eq!(params.get(b"Predictor").and_then(Object::as_i64).unwrap(), 15);
+        assert_eq!(params.get(b"Columns").and_then(Object::as_i64).unwrap(), 32);
+        assert_eq!(stream.decompressed_content().unwrap(), content);
+
+        let mut plain = Stream::new(Dictionary::new(), content.clone());
+        plain.encode_with(&[FilterSpec::Flate(None)]).unwrap();
+        assert!(plain.dict.get(b"DecodeParms").is_err());
+        let mut predicted = Stream::new(Dictionary::new(), content.clone());
+        predicted
+            .encode_with(&[FilterSpec::Flate(Some(Predictor::new(3, 8, 32)))])
+            .unwrap();
+        assert!(predicted.content.len() < plain.content.len());
+    }
+
+    #[test]
+    fn test_encode_with_lzw() {
+        let content: Vec<u8> = (0..200_u32).map(|i| (i % 7 * 31) as u8).collect();
+        for predictor in [
+            None,
+            Some(Predictor::new(1, 8, 20).with_filter(FilterType::Up)),
+            Some(Predictor::new(1, 1, 80)),
+            Some(Predictor::new(2, 16, 5).with_filter(FilterType::Avg)),
+        ] {
+            let mut stream = Stream::new(Dictionary::new(), content.clone());
+            stream.encode_with(&[FilterSpec::Lzw(predictor)]).unwrap();
+            assert_eq!(stream.filters().unwrap(), vec![&b"LZWDecode"[..]]);
+            assert_eq!(stream.decompressed_content().unwrap(), content);
+        }
+
+        let mut stream = Stream::new(Dictionary::new(), content);
+        let result = stream.encode_with(&[FilterSpec::Lzw(Some(Predictor::new(3, 8, 7)))]);
+        assert!(matches!(result, Err(Error::InvalidStream(_))));
+    }
+
+    #[test]
+    fn test_encode_with_on_encoded_stream() {
+        let content = b"BT /F1 12 Tf 72 712 Td (Hello) Tj ET".repeat(10);
+        let mut stream = Stream::new(Dictionary::new(), content.clone());
+        stream.encode_with(&[FilterSpec::Flate(None)]).unwrap();
+        stream.encode_with(&[FilterSpec::Ascii85]).unwrap();
+
+        assert_eq!(stream.filters().unwrap(), vec![&b"ASCII85Decode"[..], b"FlateDecode"]);
+        assert!(stream.dict.get(b"DecodeParms").is_err());
+        assert_eq!(stream.decompressed_content().unwrap(), content);
+    }
 }
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+
+use crate::{Document, Error, Object, ObjectId, Permissions, Rect, Result};
+
+/// The page boundaries defined in ISO 32000-1 §14.11.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoxKind {
+    Media,
+    Crop,
+    Bleed,
+    Trim,
+    Art,
+}
+
+impl BoxKind {
+    /// Key of the box in the page dictionary.
+    pub fn key(self) -> &'static str {
+        match self {
+            BoxKind::Media => "MediaBox",
+            BoxKind::Crop => "CropBox",
+            BoxKind::Bleed => "BleedBox",
+            BoxKind::Trim => "TrimBox",
+            BoxKind::Art => "ArtBox",
+        }
+    }
+
+    /// Only media box and crop box can be inherited from the page tree.
+    fn is_inheritable(self) -> bool {
+        matches!(self, BoxKind::Media | BoxKind::Crop)
+    }
+}
+
+impl Document {
+    /// Get an inheritable page attribute (`Resources`, `MediaBox`, `CropBox` or `Rotate`),
+    /// looking it up in the ancestors of the page if the page itself doesn't define it.
+    pub fn get_inherited_page_attribute(&self, page_id: ObjectId, key: &[u8]) -> Result<&Object> {
+        let mut node = self.get_dictionary(page_id)?;
+        let mut seen = HashSet::new();
+        loop {
+            if let Ok(value) = node.get(key) {
+                return Ok(value);
+            }
+            let parent_id = match node.get(b"Parent").and_then(Object::as_reference) {
+                Ok(parent_id) => parent_id,
+                Err(_) => return Err(Error::DictKey(String::from_utf8_lossy(key).into_owned())),
+            };
+            if !seen.insert(parent_id) {
+                return Err(Error::ReferenceCycle(parent_id));
+            }
+            node = self.get_dictionary(parent_id)?;
+        }
+    }
+
+    fn declared_page_box(&self, page_id: ObjectId, kind: BoxKind) -> Option<Rect> {
+        let object = if kind.is_inheritable() {
+            self.get_inherited_page_attribute(page_id, kind.key().as_bytes()).ok()?
+        } else {
+            self.get_dictionary(page_id).ok()?.get(kind.key().as_bytes()).ok()?
+        };
+        self.dereference(object)
+            .and_then(|(_, object)| Rect::from_object(object))
+            .ok()
+    }
+
+    /// Get the effective page box, normalized to lower-left and upper-right corner.
+    ///
+    /// Inheritance and defaults are applied: a missing crop box defaults to the media box, and
+    /// missing bleed, trim and art boxes default to the crop box. Every box is clipped to the
+    /// media box (bleed, trim and art box to the crop box).
+    pub fn page_box(&self, page_id: ObjectId, kind: BoxKind) -> Result<Rect> {
+        let parent = match kind {
+            BoxKind::Media => {
+                return self
+                    .declared_page_box(page_id, kind)
+                    .ok_or_else(|| Error::DictKey("MediaBox".to_string()));
+            }
+            BoxKind::Crop => self.page_box(page_id, BoxKind::Media)?,
+            BoxKind::Bleed | BoxKind::Trim | BoxKind::Art => self.page_box(page_id, BoxKind::Crop)?,
+        };
+        Ok(self
+            .declared_page_box(page_id, kind)
+            .and_then(|rect| rect.intersection(&parent))
+            .unwrap_or(parent))
+    }
+
+    /// Set a page box on the page itself.
+    pub fn set_page_box(&mut self, page_id: ObjectId, kind: BoxKind, rect: Rect) -> Result<()> {
+        self.get_dictionary_mut(page_id)?.set(kind.key(), rect);
+        Ok(())
+    }
+
+    /// Get the page rotation in degrees clockwise, one of 0, 90, 180 or 270.
+    ///
+    /// Invalid values are treated as 0.
+    pub fn page_rotation(&self, page_id: ObjectId) -> Result<i64> {
+        let rotation = match self.get_inherited_page_attribute(page_id, b"Rotate") {
+            Ok(object) => self.dereference(object)?.1.as_i64()?,
+            Err(Error::DictKey(_)) => 0,
+            Err(err) => return Err(err),
+        };
+        if rotation % 90 == 0 {
+            Ok(rotation.rem_euclid(360))
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Set the page rotation in degrees clockwise. The angle must be a multiple of 90.
+    pub fn set_page_rotation(&mut self, page_id: ObjectId, rotation: i64) -> Result<()> {
+        self.check_permissions(Permissions::ASSEMBLABLE)?;
+        if rotation % 90 != 0 {
+            return Err(Error::InvalidRotation(rotation));
+        }
+        self.get_dictionary_mut(page_id)?
+            .set("Rotate", rotation.rem_euclid(360));
+        Ok(())
+    }
+
+    /// Rotate a page by `angle` degrees clockwise relative to its current rotation.
+    pub fn rotate_page(&mut self, page_id: ObjectId, angle: i64) -> Result<()> {
+        let rotation = self.page_rotation(page_id)?;
+        self.set_page_rotation(page_id, rotation + angle)
+    }
+
+    /// Get the size of default user space units in multiples of 1/72 inch (PDF 1.6).
+    pub fn page_user_unit(&self, page_id: ObjectId) -> Result<f32> {
+        match self.get_dictionary(page_id)?.get(b"UserUnit") {
+            Ok(object) => self.dereference(object)?.1.as_float(),
+            Err(_) => Ok(1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoxKind;
+    use crate::creator::tests::create_document;
+    use crate::{Object, Rect};
+
+    #[test]
+    fn inherited_and_default_boxes() {
+        let mut doc = create_document();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+
+        let media = Rect::from_size(595.0, 842.0);
+        assert_eq!(doc.page_box(page_id, BoxKind::Media).unwrap(), media);
+        assert_eq!(doc.page_box(page_id, BoxKind::Trim).unwrap(), media);
+
+        doc.set_page_box(page_id, BoxKind::Crop, Rect::new(700.0, 800.0, -20.0, 10.0))
+            .unwrap();
+        let crop = Rect::new(0.0, 10.0, 595.0, 800.0);
+        assert_eq!(doc.page_box(page_id, BoxKind::Crop).unwrap(), crop);
+        assert_eq!(doc.page_box(page_id, BoxKind::Bleed).unwrap(), crop);
+
+        doc.set_page_box(page_id, BoxKind::Trim, Rect::new(10.0, 20.0, 585.0, 822.0))
+            .unwrap();
+        assert_eq!(
+            doc.page_box(page_id, BoxKind::Trim).unwrap(),
+            Rect::new(10.0, 20.0, 585.0, 800.0)
+        );
+    }
+
+    #[test]
+    fn rotation() {
+        let mut doc = create_document();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        assert_eq!(doc.page_rotation(page_id).unwrap(), 0);
+
+        let pages_id = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .unwrap();
+        doc.get_dictionary_mut(pages_id).unwrap().set("Rotate", -90);
+        assert_eq!(doc.page_rotation(page_id).unwrap(), 270);
+
+        doc.rotate_page(page_id, 180).unwrap();
+        assert_eq!(doc.page_rotation(page_id).unwrap(), 90);
+        assert!(doc.set_page_rotation(page_id, 45).is_err());
+        assert_eq!(doc.page_user_unit(page_id).unwrap(), 1.0);
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use crate::{Dictionary, Document, NumberTree, Object, Result, decode_text_string, text_string};
+
+/// Numbering style of a page label range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLabelStyle {
+    /// Decimal arabic numerals (`D`).
+    Decimal,
+    /// Uppercase roman numerals (`R`).
+    UpperRoman,
+    /// Lowercase roman numerals (`r`).
+    LowerRoman,
+    /// Uppercase letters, `A` to `Z`, then `AA` to `ZZ` and so on (`A`).
+    UpperLetters,
+    /// Lowercase letters (`a`).
+    LowerLetters,
+}
+
+impl PageLabelStyle {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"D" => Some(PageLabelStyle::Decimal),
+            b"R" => Some(PageLabelStyle::UpperRoman),
+            b"r" => Some(PageLabelStyle::LowerRoman),
+            b"A" => Some(PageLabelStyle::UpperLetters),
+            b"a" => Some(PageLabelStyle::LowerLetters),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PageLabelStyle::Decimal => "D",
+            PageLabelStyle::UpperRoman => "R",
+            PageLabelStyle::LowerRoman => "r",
+            PageLabelStyle::UpperLetters => "A",
+            PageLabelStyle::LowerLetters => "a",
+        }
+    }
+
+    /// Format a page number in this style.
+    pub fn format(self, value: u32) -> String {
+        match self {
+            PageLabelStyle::Decimal => value.to_string(),
+            PageLabelStyle::UpperRoman => to_roman(value),
+            PageLabelStyle::LowerRoman => to_roman(value).to_lowercase(),
+            PageLabelStyle::UpperLetters => to_letters(value),
+            PageLabelStyle::LowerLetters => to_letters(value).to_lowercase(),
+        }
+    }
+}
+
+fn to_roman(mut value: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut roman = String::new();
+    for (number, numeral) in NUMERALS {
+        while value >= number {
+            roman.push_str(numeral);
+            value -= number;
+        }
+    }
+    roman
+}
+
+fn to_letters(value: u32) -> String {
+    if value == 0 {
+        return String::new();
+    }
+    let letter = (b'A' + ((value - 1) % 26) as u8) as char;
+    std::iter::repeat_n(letter, ((value - 1) / 26 + 1) as usize).collect()
+}
+
+/// A range of pages sharing one labelling scheme.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLabelRange {
+    /// Page number (starting from 1) of the first page in the range.
+    pub first_page: u32,
+    /// Numbering style; without a style, labels consist of the prefix only.
+    pub style: Option<PageLabelStyle>,
+    /// Label prefix.
+    pub prefix: Option<String>,
+    /// Numeric value of the first page in the range.
+    pub start: u32,
+}
+
+impl PageLabelRange {
+    pub fn new(first_page: u32, style: Option<PageLabelStyle>) -> Self {
+        PageLabelRange {
+            first_page,
+            style,
+            prefix: None,
+            start: 1,
+        }
+    }
+
+    fn from_dict(first_page: u32, dict: &Dictionary) -> Self {
+        PageLabelRange {
+            first_page,
+            style: dict
+                .get(b"S")
+                .and_then(Object::as_name)
+                .ok()
+                .and_then(PageLabelStyle::from_name),
+            prefix: dict.get(b"P").and_then(decode_text_string).ok(),
+            start: dict
+                .get(b"St")
+                .and_then(Object::as_i64)
+                .ok()
+                .and_then(|start| u32::try_from(start).ok())
+                .unwrap_or(1),
+        }
+    }
+
+    fn to_dict(&self) -> Dictionary {
+        let mut dict = dictionary! { "Type" => "PageLabel" };
+        if let Some(style) = self.style {
+            dict.set("S", Object::Name(style.name().as_bytes().to_vec()));
+        }
+        if let Some(prefix) = &self.prefix {
+            dict.set("P", text_string(prefix));
+        }
+        if self.start != 1 {
+            dict.set("St", self.start);
+        }
+        dict
+    }
+
+    /// Label of the page `offset` pages after the first page of the range.
+    fn label(&self, offset: u32) -> String {
+        let mut label = self.prefix.clone().unwrap_or_default();
+        if let Some(style) = self.style {
+            label.push_str(&style.format(self.start + offset));
+        }
+        label
+    }
+}
+
+impl Document {
+    /// Get the page label ranges defined in the `/PageLabels` number tree of the catalog,
+    /// ordered by their first page.
+    pub fn get_page_label_ranges(&self) -> Result<Vec<PageLabelRange>> {
+        let tree = match self.catalog()?.get(b"PageLabels") {
+            Ok(tree) => self.dereference(tree)?.1.as_dict()?,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let mut ranges = Vec::new();
+        for (index, value) in NumberTree::load(self, tree)?.iter() {
+            let (Ok(index), Ok(dict)) = (
+                u32::try_from(*index),
+                self.dereference(value).and_then(|(_, v)| v.as_dict()),
+            ) else {
+                continue;
+            };
+            ranges.push(PageLabelRange::from_dict(index + 1, dict));
+        }
+        Ok(ranges)
+    }
+
+    /// Replace the `/PageLabels` number tree of the catalog.
+    /// Passing no ranges removes page labels from the document.
+    pub fn set_page_label_ranges(&mut self, ranges: &[PageLabelRange]) -> Result<()> {
+        if ranges.is_empty() {
+            self.catalog_mut()?.remove(b"PageLabels");
+            return Ok(());
+        }
+        let tree: NumberTree = ranges
+            .iter()
+            .map(|range| {
+                let index = i64::from(range.first_page.saturating_sub(1));
+                (index, Object::Dictionary(range.to_dict()))
+            })
+            .collect();
+        let tree_id = tree.write(self)?;
+        self.catalog_mut()?.set("PageLabels", tree_id);
+        Ok(())
+    }
+
+    /// Get the logical label of every page, keyed by page number.
+    ///
+    /// Pages not covered by any label range are labelled with their page number.
+    pub fn get_page_labels(&self) -> Result<BTreeMap<u32, String>> {
+        let ranges = self.get_page_label_ranges()?;
+        let mut labels = BTreeMap::new();
+        for page_number in self.get_pages().into_keys() {
+            let label = match ranges.iter().rev().find(|range| range.first_page <= page_number) {
+                Some(range) => range.label(page_number - range.first_page),
+                None => page_number.to_string(),
+            };
+            labels.insert(page_number, label);
+        }
+        Ok(labels)
+    }
+
+    /// Find the page number of the first page with the given label.
+    pub fn get_page_number_by_label(&self, label: &str) -> Option<u32> {
+        self.get_page_labels()
+            .ok()?
+            .into_iter()
+            .find(|(_, page_label)| page_label == label)
+            .map(|(page_number, _)| page_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PageLabelRange, PageLabelStyle};
+    use crate::creator::tests::create_document_with_texts;
+
+    #[test]
+    fn format_styles() {
+        assert_eq!(PageLabelStyle::UpperRoman.format(1994), "MCMXCIV");
+        assert_eq!(PageLabelStyle::LowerRoman.format(4), "iv");
+        assert_eq!(PageLabelStyle::UpperLetters.format(1), "A");
+        assert_eq!(PageLabelStyle::UpperLetters.format(28), "BB");
+        assert_eq!(PageLabelStyle::LowerLetters.format(26), "z");
+        assert_eq!(PageLabelStyle::Decimal.format(12), "12");
+    }
+
+    #[test]
+    fn write_and_read_labels() {
+        let mut doc = create_document_with_texts(&["1", "2", "3", "4", "5", "6"]);
+        assert_eq!(doc.get_page_labels().unwrap()[&3], "3");
+
+        let appendix = PageLabelRange {
+            prefix: Some("A-".to_string()),
+            start: 2,
+            ..PageLabelRange::new(5, Some(PageLabelStyle::Decimal))
+        };
+        let ranges = vec![
+            PageLabelRange::new(1, Some(PageLabelStyle::LowerRoman)),
+            PageLabelRange::new(3, Some(PageLabelStyle::Decimal)),
+            appendix,
+        ];
+        doc.set_page_label_ranges(&ranges).unwrap();
+        assert_eq!(doc.get_page_label_ranges().unwrap(), ranges);
+
+        let labels: Vec<String> = doc.get_page_labels().unwrap().into_values().collect();
+        assert_eq!(labels, ["i", "ii", "1", "2", "A-2", "A-3"]);
+        assert_eq!(doc.get_page_number_by_label("A-3"), Some(6));
+        assert_eq!(doc.get_page_number_by_label("iii"), None);
+    }
+}
@@ -420,6 +420,37 @@ pub fn header(input: ParserInput) -> Option<String> {
     ).parse(input))
 }
 
+/// Header version, indirect objects in file order and trailer of an FDF file.
+pub type FdfBody = (String, Vec<(ObjectId, Object)>, Dictionary);
+
+/// Parse an FDF file.
+///
+/// FDF files usually have no cross-reference table, so the objects are read sequentially.
+pub fn fdf(input: ParserInput, reader: &Reader) -> crate::Result<FdfBody> {
+    let invalid = |i: ParserInput| Error::InvalidFdf(format!("unexpected data at offset {}", i.location_offset()));
+    let (mut i, version) = map_res(
+        delimited(tag(&b"%FDF-"[..]), take_while(|c: u8| !b"\r\n".contains(&c)), eol),
+        |v: ParserInput| str::from_utf8(&v).map(String::from),
+    ).parse(input)
+    .map_err(|_| Error::InvalidFdf("missing header".to_string()))?;
+
+    let mut objects = vec![];
+    loop {
+        (i, _) = space(i).map_err(|_| invalid(i))?;
+        if let Ok((_, trailer)) = trailer(i) {
+            return Ok((version, objects, trailer));
+        }
+        let (rest, id) = terminated(object_id, pair(tag(&b"obj"[..]), space)).parse(i).map_err(|_| invalid(i))?;
+        let (rest, object) = terminated(
+            |i| object(i, reader, &mut HashSet::new()),
+            (space, opt(tag(&b"endobj"[..]))),
+        ).parse(rest)
+        .map_err(|_| invalid(i))?;
+        objects.push((id, object));
+        i = rest;
+    }
+}
+
 pub fn binary_mark(input: ParserInput) -> Option<Vec<u8>> {
     strip_nom(map_res(
         delimited(
@@ -455,6 +486,9 @@ fn xref(input: ParserInput) -> NomResult<Xref> {
                         if let Ok(generation) = generation.try_into() {
                             xref.insert((start + index) as u32, XrefEntry::Normal { offset, generation });
                         }
+                    } else if start + index != 0 {
+                        // Keep free entries so that they hide the object in earlier revisions.
+                        xref.insert((start + index) as u32, XrefEntry::Free);
                     }
                 }
                 xref
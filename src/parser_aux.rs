@@ -1,6 +1,6 @@
 use log::warn;
 
-use crate::{Dictionary, Object, ObjectId, Stream, parser};
+use crate::{Dictionary, Object, ObjectId, Permissions, Stream, parser};
 use crate::{
     Error, Result,
     content::{Content, Operation},
@@ -9,6 +9,7 @@
     error::ParseError,
     object::Object::Name,
     parser::ParserInput,
+    xobject::ImageXObject,
     xref::{Xref, XrefEntry, XrefType},
 };
 use std::{
@@ -57,6 +58,12 @@ pub fn extract_text(&self, page_numbers: &[u32]) -> Result<String> {
     }
 
     pub fn extract_text_chunks(&self, page_numbers: &[u32]) -> Vec<Result<String>> {
+        if self.check_permissions(Permissions::COPYABLE).is_err() {
+            return page_numbers
+                .iter()
+                .map(|_| Err(Error::PermissionDenied(Permissions::COPYABLE)))
+                .collect();
+        }
         let pages: BTreeMap<u32, (u32, u16)> = self.get_pages();
         page_numbers
             .iter()
@@ -123,10 +130,8 @@ fn extract_text_chunks_from_page(
                     }
                     None => warn!("Could not decode extracted text"),
                 },
-                "ET" => {
-                    if !current_text.ends_with('\n') {
-                        current_text.push('\n')
-                    }
+                "ET" if !current_text.ends_with('\n') => {
+                    current_text.push('\n')
                 }
                 _ => {}
             }
@@ -235,9 +240,9 @@ pub fn replace_partial_text(
     }
 
     pub fn insert_image(
-        &mut self, page_id: ObjectId, img_object: Stream, position: (f32, f32), size: (f32, f32),
+        &mut self, page_id: ObjectId, img_object: impl Into<ImageXObject>, position: (f32, f32), size: (f32, f32),
     ) -> Result<()> {
-        let img_id = self.add_object(img_object);
+        let img_id = img_object.into().add_to(self);
         let img_name = format!("X{}", img_id.0);
 
         self.add_xobject(page_id, img_name.as_bytes(), img_id)?;
@@ -289,10 +294,8 @@ fn collect_text(text: &mut String, encoding: &Encoding, operands: &[Object]) ->
                 collect_text(text, encoding, arr)?;
                 text.push(' ');
             }
-            Object::Integer(i) => {
-                if *i < -100 {
-                    text.push(' ');
-                }
+            Object::Integer(i) if *i < -100 => {
+                text.push(' ');
             }
             _ => {}
         }
@@ -491,6 +494,9 @@ pub fn decode_xref_stream(mut stream: Stream) -> Result<(Xref, Dictionary)> {
                         // free object
                         read_big_endian_integer(&mut reader, bytes2.as_mut_slice())?;
                         read_big_endian_integer(&mut reader, bytes3.as_mut_slice())?;
+                        if start + j != 0 {
+                            xref.insert((start + j) as u32, XrefEntry::Free);
+                        }
                     }
                     1 => {
                         // normal object
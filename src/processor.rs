@@ -1,5 +1,5 @@
 use crate::Result;
-use crate::{Document, Object, ObjectId};
+use crate::{Document, Object, ObjectId, Permissions};
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
@@ -40,7 +40,8 @@ pub fn decompress(&mut self) {
     }
 
     /// Delete pages.
-    pub fn delete_pages(&mut self, page_numbers: &[u32]) {
+    pub fn delete_pages(&mut self, page_numbers: &[u32]) -> Result<()> {
+        self.check_permissions(Permissions::ASSEMBLABLE)?;
         let pages = self.get_pages();
         for page_number in page_numbers {
             if let Some(page) = pages.get(page_number).and_then(|page_id| self.delete_object(*page_id)) {
@@ -60,6 +61,7 @@ pub fn delete_pages(&mut self, page_numbers: &[u32]) {
                 }
             }
         }
+        Ok(())
     }
 
     /// Prune all unused objects.
@@ -19,11 +19,12 @@
 #[cfg(feature = "async")]
 use tokio::pin;
 
+use crate::encryption::{self, EncryptionState};
 use crate::error::{ParseError, XrefError};
 use crate::object_stream::ObjectStream;
 use crate::parser::{self, ParserInput};
-use crate::xref::XrefEntry;
-use crate::{Document, Error, IncrementalDocument, Object, ObjectId, Result};
+use crate::xref::{Xref, XrefEntry};
+use crate::{Dictionary, Document, Error, IncrementalDocument, Object, ObjectId, Result, Revision};
 
 type FilterFunc = fn((u32, u16), &mut Object) -> Option<((u32, u16), Object)>;
 
@@ -34,24 +35,45 @@ impl Document {
     pub fn load<P: AsRef<Path>>(path: P, stop: Arc<AtomicBool>) -> Result<Document> {
         let file = File::open(path)?;
         let capacity = Some(file.metadata()?.len() as usize);
-        Self::load_internal(file, capacity, None, stop)
+        Self::load_internal(file, capacity, None, None, stop)
     }
 
     #[inline]
     pub fn load_filtered<P: AsRef<Path>>(path: P, filter_func: FilterFunc, stop: Arc<AtomicBool>) -> Result<Document> {
         let file = File::open(path)?;
         let capacity = Some(file.metadata()?.len() as usize);
-        Self::load_internal(file, capacity, Some(filter_func), stop)
+        Self::load_internal(file, capacity, Some(filter_func), None, stop)
+    }
+
+    /// Load an encrypted PDF document from a specified file path, decrypting its objects with
+    /// `password` (the user or the owner password) as they are read.
+    #[inline]
+    pub fn load_with_password<P: AsRef<Path>>(path: P, password: &str, stop: Arc<AtomicBool>) -> Result<Document> {
+        let file = File::open(path)?;
+        let capacity = Some(file.metadata()?.len() as usize);
+        Self::load_internal(file, capacity, None, Some(password), stop)
+    }
+
+    /// Like [`Document::load_with_password`], with the objects passed through `filter_func` once
+    /// decrypted, including those from object streams.
+    #[inline]
+    pub fn load_filtered_with_password<P: AsRef<Path>>(
+        path: P, filter_func: FilterFunc, password: &str, stop: Arc<AtomicBool>,
+    ) -> Result<Document> {
+        let file = File::open(path)?;
+        let capacity = Some(file.metadata()?.len() as usize);
+        Self::load_internal(file, capacity, Some(filter_func), Some(password), stop)
     }
 
     /// Load a PDF document from an arbitrary source.
     #[inline]
     pub fn load_from<R: Read>(source: R, stop: Arc<AtomicBool>) -> Result<Document> {
-        Self::load_internal(source, None, None, stop)
+        Self::load_internal(source, None, None, None, stop)
     }
 
     fn load_internal<R: Read>(
-        mut source: R, capacity: Option<usize>, filter_func: Option<FilterFunc>, stop: Arc<AtomicBool>,
+        mut source: R, capacity: Option<usize>, filter_func: Option<FilterFunc>, password: Option<&str>,
+        stop: Arc<AtomicBool>,
     ) -> Result<Document> {
         let mut buffer = capacity.map(Vec::with_capacity).unwrap_or_default();
         source.read_to_end(&mut buffer)?;
@@ -65,13 +87,24 @@ fn load_internal<R: Read>(
             document: Document::new(),
             stop,
         }
-        .read(filter_func)
+        .read_internal(filter_func, password)
     }
 
     /// Load a PDF document from a memory slice.
     pub fn load_mem(buffer: &[u8]) -> Result<Document> {
         buffer.try_into()
     }
+
+    /// Load an encrypted PDF document from a memory slice, decrypting its objects with `password`
+    /// as they are read.
+    pub fn load_mem_with_password(buffer: &[u8], password: &str) -> Result<Document> {
+        Reader {
+            buffer,
+            document: Document::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+        .read_with_password(None, password)
+    }
 }
 
 #[cfg(feature = "async")]
@@ -80,18 +113,38 @@ pub async fn load<P: AsRef<Path>>(path: P) -> Result<Document> {
         let file = File::open(path).await?;
         let metadata = file.metadata().await?;
         let capacity = Some(metadata.len() as usize);
-        Self::load_internal(file, capacity, None).await
+        Self::load_internal(file, capacity, None, None).await
     }
 
     pub async fn load_filtered<P: AsRef<Path>>(path: P, filter_func: FilterFunc) -> Result<Document> {
         let file = File::open(path).await?;
         let metadata = file.metadata().await?;
         let capacity = Some(metadata.len() as usize);
-        Self::load_internal(file, capacity, Some(filter_func)).await
+        Self::load_internal(file, capacity, Some(filter_func), None).await
+    }
+
+    /// Load an encrypted PDF document from a specified file path, decrypting its objects with
+    /// `password` (the user or the owner password) as they are read.
+    pub async fn load_with_password<P: AsRef<Path>>(path: P, password: &str) -> Result<Document> {
+        let file = File::open(path).await?;
+        let metadata = file.metadata().await?;
+        let capacity = Some(metadata.len() as usize);
+        Self::load_internal(file, capacity, None, Some(password)).await
+    }
+
+    /// Like [`Document::load_with_password`], with the objects passed through `filter_func` once
+    /// decrypted, including those from object streams.
+    pub async fn load_filtered_with_password<P: AsRef<Path>>(
+        path: P, filter_func: FilterFunc, password: &str,
+    ) -> Result<Document> {
+        let file = File::open(path).await?;
+        let metadata = file.metadata().await?;
+        let capacity = Some(metadata.len() as usize);
+        Self::load_internal(file, capacity, Some(filter_func), Some(password)).await
     }
 
     async fn load_internal<R: AsyncRead>(
-        source: R, capacity: Option<usize>, filter_func: Option<FilterFunc>,
+        source: R, capacity: Option<usize>, filter_func: Option<FilterFunc>, password: Option<&str>,
     ) -> Result<Document> {
         pin!(source);
 
@@ -101,14 +154,26 @@ async fn load_internal<R: AsyncRead>(
         Reader {
             buffer: &buffer,
             document: Document::new(),
+            stop: Arc::new(AtomicBool::new(false)),
         }
-        .read(filter_func)
+        .read_internal(filter_func, password)
     }
 
     /// Load a PDF document from a memory slice.
     pub fn load_mem(buffer: &[u8]) -> Result<Document> {
         buffer.try_into()
     }
+
+    /// Load an encrypted PDF document from a memory slice, decrypting its objects with `password`
+    /// as they are read.
+    pub fn load_mem_with_password(buffer: &[u8], password: &str) -> Result<Document> {
+        Reader {
+            buffer,
+            document: Document::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+        .read_with_password(None, password)
+    }
 }
 
 impl TryInto<Document> for &[u8] {
@@ -186,6 +251,7 @@ async fn load_internal<R: AsyncRead>(source: R, capacity: Option<usize>) -> Resu
         let document = Reader {
             buffer: &buffer,
             document: Document::new(),
+            stop: Arc::new(AtomicBool::new(false)),
         }
         .read(None)?;
 
@@ -224,7 +290,21 @@ pub struct Reader<'a> {
 
 impl Reader<'_> {
     /// Read whole document.
-    pub fn read(mut self, filter_func: Option<FilterFunc>) -> Result<Document> {
+    ///
+    /// An encrypted document is decrypted if its user password is empty, otherwise it is left
+    /// encrypted and only the objects outside of object streams are read.
+    pub fn read(self, filter_func: Option<FilterFunc>) -> Result<Document> {
+        self.read_internal(filter_func, None)
+    }
+
+    /// Read whole document, decrypting the objects of an encrypted document with `password` (the
+    /// user or the owner password) as they are read, so that `filter_func` gets the decrypted
+    /// objects and the objects stored in object streams.
+    pub fn read_with_password(self, filter_func: Option<FilterFunc>, password: &str) -> Result<Document> {
+        self.read_internal(filter_func, Some(password))
+    }
+
+    fn read_internal(mut self, filter_func: Option<FilterFunc>, password: Option<&str>) -> Result<Document> {
         let offset = self.buffer.windows(5).position(|w| w == b"%PDF-").unwrap_or(0);
         self.buffer = &self.buffer[offset..];
 
@@ -251,8 +331,9 @@ pub fn read(mut self, filter_func: Option<FilterFunc>) -> Result<Document> {
         }
         self.document.xref_start = xref_start;
 
-        let (mut xref, mut trailer) =
-            parser::xref_and_trailer(ParserInput::new_extra(&self.buffer[xref_start..], "xref"), &self)?;
+        let (mut xref, mut trailer) = self.read_xref_section(xref_start)?;
+        trailer.remove(b"XRefStm");
+        let mut revisions = vec![Revision::new(self.buffer, xref_start, &xref, trailer.clone())];
 
         // Read previous Xrefs of linearized or incremental updated document.
         let mut already_seen = HashSet::new();
@@ -266,21 +347,15 @@ pub fn read(mut self, filter_func: Option<FilterFunc>) -> Result<Document> {
                 return Err(Error::Xref(XrefError::PrevStart));
             }
 
-            let (prev_xref, prev_trailer) =
-                parser::xref_and_trailer(ParserInput::new_extra(&self.buffer[prev as usize..], ""), &self)?;
-            xref.merge(prev_xref);
-
-            // Read xref stream in hybrid-reference file
-            let prev_xref_stream_start = trailer.remove(b"XRefStm");
-            if let Some(prev) = prev_xref_stream_start.and_then(|offset| offset.as_i64().ok()) {
-                if prev < 0 || prev as usize > self.buffer.len() {
-                    return Err(Error::Xref(XrefError::StreamStart));
-                }
-
-                let (prev_xref, _) =
-                    parser::xref_and_trailer(ParserInput::new_extra(&self.buffer[prev as usize..], ""), &self)?;
-                xref.merge(prev_xref);
+            let (prev_xref, prev_trailer) = self.read_xref_section(prev as usize)?;
+            let revision = Revision::new(self.buffer, prev as usize, &prev_xref, prev_trailer.clone());
+            match revisions.last_mut() {
+                // The main section of a linearized file follows its first-page section, both
+                // belong to the same revision.
+                Some(last) if revision.xref_start > last.xref_start => last.merge(revision),
+                _ => revisions.push(revision),
             }
+            xref.merge(prev_xref);
 
             prev_xref_start = prev_trailer.get(b"Prev").cloned().ok();
         }
@@ -297,11 +372,21 @@ pub fn read(mut self, filter_func: Option<FilterFunc>) -> Result<Document> {
         self.document.max_id = xref.size - 1;
         self.document.trailer = trailer;
         self.document.reference_table = xref;
+        revisions.reverse();
+        self.document.revisions = revisions;
 
         let is_encrypted = self.document.trailer.get(b"Encrypt").is_ok();
+        let encryption = if is_encrypted {
+            self.read_encryption_state(password)?
+        } else {
+            None
+        };
+        // Object streams can only be read once decrypted.
+        let read_object_streams = !is_encrypted || encryption.is_some();
 
         let zero_length_streams = Mutex::new(vec![]);
         let object_streams = Mutex::new(vec![]);
+        let decryption_error = Mutex::new(None);
 
         let entries_filter_map = |(_, entry): (&_, &_)| {
             if self.stop.load(Ordering::SeqCst) {
@@ -312,29 +397,27 @@ pub fn read(mut self, filter_func: Option<FilterFunc>) -> Result<Document> {
                     .read_object(offset as usize, None, &mut HashSet::new())
                     .map_err(|e| error!("Object load error: {e:?}"))
                     .ok()?;
+                // Streams with an indirect length get their content, and are decrypted, later on.
+                let has_content = object
+                    .as_stream()
+                    .map_or(true, |stream| stream.start_position.is_none());
+                if let (Some((state, encrypt_id)), true) = (&encryption, has_content) {
+                    if let Err(err) = Self::decrypt_object(state, *encrypt_id, object_id, &mut object) {
+                        decryption_error.lock().unwrap().get_or_insert(err);
+                        return None;
+                    }
+                }
                 if let Some(filter_func) = filter_func {
                     filter_func(object_id, &mut object)?;
                 }
 
                 if let Ok(ref mut stream) = object.as_stream_mut() {
-                    if stream.dict.has_type(b"ObjStm") && !is_encrypted {
-                        let obj_stream = ObjectStream::new(stream).ok()?;
-                        let mut object_streams = object_streams.lock().unwrap();
-                        // TODO: Is insert and replace intended behavior?
-                        // See https://github.com/J-F-Liu/lopdf/issues/160 for more info
-                        if let Some(filter_func) = filter_func {
-                            let objects: BTreeMap<(u32, u16), Object> = obj_stream
-                                .objects
-                                .into_iter()
-                                .filter_map(|(object_id, mut object)| filter_func(object_id, &mut object))
-                                .collect();
-                            object_streams.extend(objects);
-                        } else {
-                            object_streams.extend(obj_stream.objects);
-                        }
-                    } else if stream.content.is_empty() {
+                    if stream.content.is_empty() {
                         let mut zero_length_streams = zero_length_streams.lock().unwrap();
                         zero_length_streams.push(object_id);
+                    } else if stream.dict.has_type(b"ObjStm") && read_object_streams {
+                        let objects = Self::read_object_stream(stream, filter_func).ok()?;
+                        object_streams.lock().unwrap().extend(objects);
                     }
                 }
 
@@ -363,24 +446,103 @@ pub fn read(mut self, filter_func: Option<FilterFunc>) -> Result<Document> {
                 .filter_map(entries_filter_map)
                 .collect();
         }
+        if let Some(err) = decryption_error.into_inner().unwrap() {
+            return Err(err);
+        }
+
         // Only add entries, but never replace entries
         for (id, entry) in object_streams.into_inner().unwrap() {
             self.document.objects.entry(id).or_insert(entry);
         }
 
+        let mut object_streams = BTreeMap::new();
         for object_id in zero_length_streams.into_inner().unwrap() {
-            let _ = self.read_stream_content(object_id);
+            if self.read_stream_content(object_id).is_err() {
+                continue;
+            }
+            let object = self.document.get_object_mut(object_id)?;
+            if let Some((state, encrypt_id)) = &encryption {
+                Self::decrypt_object(state, *encrypt_id, object_id, object)?;
+            }
+            if let Ok(stream) = object.as_stream_mut() {
+                if stream.dict.has_type(b"ObjStm") && read_object_streams {
+                    if let Ok(objects) = Self::read_object_stream(stream, filter_func) {
+                        object_streams.extend(objects);
+                    }
+                }
+            }
+        }
+        for (id, entry) in object_streams {
+            self.document.objects.entry(id).or_insert(entry);
         }
 
         let mut document = self.document;
 
-        if document.authenticate_password("").is_ok() {
-            document.decrypt("")?;
+        if let Some((state, encrypt_id)) = encryption {
+            document.trailer.remove(b"Encrypt");
+            if let Some(encrypt_id) = encrypt_id {
+                document.objects.remove(&encrypt_id);
+            }
+            document.encryption_state = Some(state);
         }
 
         Ok(document)
     }
 
+    /// Read the encryption dictionary and decode the encryption state of the document with
+    /// `password`, or with the empty user password if there is none, in which case the document
+    /// is left encrypted if the password doesn't match. Also returns the ID of the encryption
+    /// dictionary, which is not encrypted.
+    fn read_encryption_state(&mut self, password: Option<&str>) -> Result<Option<(EncryptionState, Option<ObjectId>)>> {
+        let encrypt_id = self
+            .document
+            .trailer
+            .get(b"Encrypt")
+            .and_then(Object::as_reference)
+            .ok();
+        if let Some(encrypt_id) = encrypt_id {
+            let object = self.get_object(encrypt_id, &mut HashSet::new())?;
+            self.document.objects.insert(encrypt_id, object);
+        }
+
+        let state = match password {
+            Some(password) => self.document.password_encryption_state(password)?,
+            None => match self.document.password_encryption_state("") {
+                Ok(state) => state,
+                Err(_) => return Ok(None),
+            },
+        };
+
+        Ok(Some((state, encrypt_id)))
+    }
+
+    fn decrypt_object(
+        state: &EncryptionState, encrypt_id: Option<ObjectId>, object_id: ObjectId, object: &mut Object,
+    ) -> Result<()> {
+        // The encryption dictionary is not encrypted, leave it alone
+        if encrypt_id != Some(object_id) {
+            encryption::decrypt_object(state, object_id, object)?;
+        }
+        Ok(())
+    }
+
+    /// The objects of an object stream, passed through `filter_func`.
+    fn read_object_stream(
+        stream: &mut crate::Stream, filter_func: Option<FilterFunc>,
+    ) -> Result<BTreeMap<ObjectId, Object>> {
+        let obj_stream = ObjectStream::new(stream)?;
+        // TODO: Is insert and replace intended behavior?
+        // See https://github.com/J-F-Liu/lopdf/issues/160 for more info
+        Ok(match filter_func {
+            Some(filter_func) => obj_stream
+                .objects
+                .into_iter()
+                .filter_map(|(object_id, mut object)| filter_func(object_id, &mut object))
+                .collect(),
+            None => obj_stream.objects,
+        })
+    }
+
     fn read_stream_content(&mut self, object_id: ObjectId) -> Result<()> {
         let length = self.get_stream_length(object_id)?;
         let stream = self
@@ -459,7 +621,23 @@ fn read_object(
         )
     }
 
-    fn get_xref_start(buffer: &[u8]) -> Result<usize> {
+    /// Read a cross-reference section together with the `/XRefStm` stream of its trailer in
+    /// hybrid-reference files.
+    fn read_xref_section(&self, start: usize) -> Result<(Xref, Dictionary)> {
+        let (mut xref, trailer) =
+            parser::xref_and_trailer(ParserInput::new_extra(&self.buffer[start..], "xref"), self)?;
+        if let Ok(stream_start) = trailer.get(b"XRefStm").and_then(Object::as_i64) {
+            if stream_start < 0 || stream_start as usize > self.buffer.len() {
+                return Err(Error::Xref(XrefError::StreamStart));
+            }
+            let (stream_xref, _) =
+                parser::xref_and_trailer(ParserInput::new_extra(&self.buffer[stream_start as usize..], ""), self)?;
+            xref.merge_hybrid(stream_xref);
+        }
+        Ok((xref, trailer))
+    }
+
+    pub(crate) fn get_xref_start(buffer: &[u8]) -> Result<usize> {
         let seek_pos = buffer.len() - cmp::min(buffer.len(), 512);
         Self::search_substring(buffer, b"%%EOF", seek_pos)
             .and_then(|eof_pos| if eof_pos > 25 { Some(eof_pos) } else { None })
@@ -542,8 +720,7 @@ fn load_document_with_preceding_bytes() {
 
 #[test]
 fn load_many_shallow_brackets() {
-    let content: String = std::iter::repeat("()")
-        .take(MAX_BRACKET * 10)
+    let content: String = std::iter::repeat_n("()", MAX_BRACKET * 10)
         .flat_map(|x| x.chars())
         .collect();
     const STREAM_CRUFT: usize = 33;
@@ -588,9 +765,8 @@ fn load_many_shallow_brackets() {
 
 #[test]
 fn load_too_deep_brackets() {
-    let content: Vec<u8> = std::iter::repeat(b'(')
-        .take(MAX_BRACKET + 1)
-        .chain(std::iter::repeat(b')').take(MAX_BRACKET + 1))
+    let content: Vec<u8> = std::iter::repeat_n(b'(', MAX_BRACKET + 1)
+        .chain(std::iter::repeat_n(b')', MAX_BRACKET + 1))
         .collect();
     let content = String::from_utf8(content).unwrap();
     const STREAM_CRUFT: usize = 33;
@@ -639,3 +815,150 @@ fn load_too_deep_brackets() {
     let pages = doc.get_pages().keys().cloned().collect::<Vec<_>>();
     assert_eq!("Hello World!\n", doc.extract_text(&pages).unwrap());
 }
+
+#[test]
+fn load_encrypted_document_with_password() {
+    use crate::encryption::crypt_filters::{Aes128CryptFilter, CryptFilter};
+    use crate::encryption::{EncryptionVersion, Permissions};
+
+    let mut doc = crate::creator::tests::create_document();
+    let object_stream = crate::Stream::new(
+        dictionary! { "Type" => "ObjStm", "N" => 1, "First" => 6 },
+        b"100 0 (secret)".to_vec(),
+    );
+    doc.add_object(object_stream);
+    let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+    let version = EncryptionVersion::V4 {
+        document: &doc,
+        encrypt_metadata: true,
+        crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+        stream_filter: b"StdCF".to_vec(),
+        string_filter: b"StdCF".to_vec(),
+        owner_password: "owner",
+        user_password: "user",
+        permissions: Permissions::all(),
+    };
+    let state = EncryptionState::try_from(version).unwrap();
+    doc.encrypt(&state).unwrap();
+
+    // The writer leaves out object streams, so the file is written here.
+    let mut bytes = b"%PDF-1.5\n".to_vec();
+    let mut offsets = vec![];
+    for (&(id, generation), object) in &doc.objects {
+        assert_eq!(id as usize, offsets.len() + 1);
+        offsets.push(bytes.len());
+        bytes.extend(format!("{id} {generation} obj\n").bytes());
+        crate::writer::Writer::write_object(&mut bytes, object).unwrap();
+        bytes.extend(b"\nendobj\n");
+    }
+    let xref_start = bytes.len();
+    bytes.extend(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).bytes());
+    for offset in offsets {
+        bytes.extend(format!("{offset:010} 00000 n \n").bytes());
+    }
+    doc.trailer.set("Size", doc.max_id as i64 + 1);
+    bytes.extend(b"trailer\n");
+    crate::writer::Writer::write_object(&mut bytes, &Object::Dictionary(doc.trailer.clone())).unwrap();
+    bytes.extend(format!("\nstartxref\n{xref_start}\n%%EOF").bytes());
+
+    // Without the password, the objects in object streams can't be read.
+    let doc = Document::load_mem(&bytes).unwrap();
+    assert!(doc.is_encrypted());
+    assert!(doc.get_object((100, 0)).is_err());
+
+    let doc = Document::load_mem_with_password(&bytes, "user").unwrap();
+    assert!(!doc.is_encrypted());
+    assert!(doc.encryption_state.is_some());
+    assert_eq!(doc.get_object((100, 0)).unwrap().as_str().unwrap(), b"secret");
+    assert!(doc.get_pages().len() == 1);
+
+    assert!(Document::load_mem_with_password(&bytes, "wrong").is_err());
+
+    // The filter function sees the decrypted objects from object streams.
+    fn filter_func(object_id: ObjectId, object: &mut Object) -> Option<(ObjectId, Object)> {
+        match object {
+            Object::String(content, _) if content == b"secret" => None,
+            _ => Some((object_id, object.clone())),
+        }
+    }
+    for password in ["user", "owner"] {
+        let reader = Reader {
+            buffer: &bytes,
+            document: Document::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        let doc = reader.read_with_password(Some(filter_func), password).unwrap();
+        assert!(doc.get_object((100, 0)).is_err());
+    }
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn load_hybrid_reference_update() {
+    use crate::writer::Writer;
+
+    let mut doc = crate::creator::tests::create_document();
+    let mut bytes = vec![];
+    doc.save_to(&mut bytes).unwrap();
+    let prev_start = Reader::get_xref_start(&bytes).unwrap();
+
+    // The update stores a string in an object stream, which its table lists as free.
+    let (container, hidden, xref_stream) = (doc.max_id + 1, doc.max_id + 2, doc.max_id + 3);
+    let container_offset = bytes.len();
+    bytes.extend(format!("{container} 0 obj\n").bytes());
+    let header = format!("{hidden} 0 ");
+    let object_stream = crate::Stream::new(
+        dictionary! { "Type" => "ObjStm", "N" => 1, "First" => header.len() as i64 },
+        format!("{header}(hidden)").into_bytes(),
+    );
+    Writer::write_object(&mut bytes, &Object::Stream(object_stream)).unwrap();
+    bytes.extend(b"\nendobj\n");
+
+    let xref_stream_offset = bytes.len();
+    let mut entries = vec![];
+    for (kind, field2, field3) in [
+        (1, container_offset as u32, 0_u16),
+        (2, container, 0),
+        (1, xref_stream_offset as u32, 0),
+    ] {
+        entries.push(kind);
+        entries.extend(field2.to_be_bytes());
+        entries.extend(field3.to_be_bytes());
+    }
+    let stream = crate::Stream::new(
+        dictionary! {
+            "Type" => "XRef",
+            "Size" => xref_stream + 1,
+            "W" => vec![1.into(), 4.into(), 2.into()],
+            "Index" => vec![container.into(), 3.into()]
+        },
+        entries,
+    );
+    bytes.extend(format!("{xref_stream} 0 obj\n").bytes());
+    Writer::write_object(&mut bytes, &Object::Stream(stream)).unwrap();
+    bytes.extend(b"\nendobj\n");
+
+    let xref_start = bytes.len();
+    bytes.extend(format!("xref\n0 1\n0000000000 65535 f \n{container} 3\n").bytes());
+    bytes.extend(format!("{container_offset:010} 00000 n \n0000000000 00000 f \n").bytes());
+    bytes.extend(format!("{xref_stream_offset:010} 00000 n \n").bytes());
+    let trailer = dictionary! {
+        "Size" => xref_stream + 1,
+        "Root" => doc.trailer.get(b"Root").unwrap().clone(),
+        "Prev" => prev_start as i64,
+        "XRefStm" => xref_stream_offset as i64
+    };
+    bytes.extend(b"trailer\n");
+    Writer::write_object(&mut bytes, &Object::Dictionary(trailer)).unwrap();
+    bytes.extend(format!("\nstartxref\n{xref_start}\n%%EOF\n").bytes());
+
+    let loaded = Document::load_mem(&bytes).unwrap();
+    assert_eq!(loaded.get_object((hidden, 0)).unwrap().as_str().unwrap(), b"hidden");
+    assert_eq!(loaded.get_pages().len(), 1);
+    assert!(!loaded.trailer.has(b"XRefStm"));
+    assert_eq!(loaded.revisions().len(), 2);
+    assert!(loaded.revisions()[1].changed_objects.contains(&(hidden, 0)));
+
+    let mut lazy = crate::LazyDocument::new(std::io::Cursor::new(bytes)).unwrap();
+    assert_eq!(lazy.get_object((hidden, 0)).unwrap().as_str().unwrap(), b"hidden");
+}
@@ -0,0 +1,130 @@
+use crate::xref::{Xref, XrefEntry};
+use crate::{Dictionary, Document, Error, IncrementalDocument, ObjectId, Result};
+
+/// One revision of a PDF file: the original file or one of its incremental updates.
+///
+/// Offsets are relative to the `%PDF-` header, like [`Document::xref_start`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    /// Offset of the cross-reference section (the `startxref` value).
+    pub xref_start: usize,
+    /// Offset just past the `%%EOF` marker of the revision and its end-of-line marker.
+    pub end: usize,
+    /// Trailer dictionary of the revision, including `/Prev`.
+    pub trailer: Dictionary,
+    /// Objects defined or redefined by the revision.
+    pub changed_objects: Vec<ObjectId>,
+}
+
+impl Revision {
+    pub(crate) fn new(buffer: &[u8], xref_start: usize, xref: &Xref, trailer: Dictionary) -> Self {
+        let end = buffer[xref_start..]
+            .windows(5)
+            .position(|window| window == b"%%EOF")
+            .map(|position| {
+                let end = xref_start + position + 5;
+                let eol = buffer[end..]
+                    .iter()
+                    .take(2)
+                    .take_while(|&&byte| byte == b'\r' || byte == b'\n')
+                    .count();
+                end + eol
+            })
+            .unwrap_or(buffer.len());
+        let changed_objects = xref
+            .entries
+            .iter()
+            .filter_map(|(&id, entry)| match *entry {
+                XrefEntry::Normal { generation, .. } => Some((id, generation)),
+                XrefEntry::Compressed { .. } => Some((id, 0)),
+                XrefEntry::Free | XrefEntry::UnusableFree => None,
+            })
+            .collect();
+        Revision {
+            xref_start,
+            end,
+            trailer,
+            changed_objects,
+        }
+    }
+
+    /// Add the objects of another cross-reference section of the same revision.
+    pub(crate) fn merge(&mut self, other: Revision) {
+        self.end = self.end.max(other.end);
+        self.changed_objects.extend(other.changed_objects);
+        self.changed_objects.sort_unstable();
+        self.changed_objects.dedup();
+    }
+}
+
+impl Document {
+    /// The revisions of the file the document was read from, oldest first.
+    ///
+    /// Empty for documents that were not read from a file.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+}
+
+impl IncrementalDocument {
+    /// Load the document as it was at the given revision (0 is the original file), by reading the
+    /// file up to the end of that revision.
+    pub fn load_revision(&self, index: usize) -> Result<Document> {
+        let revisions = self.get_prev_documents().revisions();
+        let revision = revisions.get(index).ok_or(Error::RevisionNotFound(index))?;
+        let bytes = self.get_prev_documents_bytes();
+        let base = bytes.windows(5).position(|w| w == b"%PDF-").unwrap_or(0);
+        Document::load_mem(&bytes[..(base + revision.end).min(bytes.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::creator::tests::create_document;
+    use crate::{Document, IncrementalDocument, Object};
+
+    #[test]
+    fn revisions_of_incremental_updates() {
+        let mut bytes = vec![];
+        create_document().save_to(&mut bytes).unwrap();
+        let original = Document::load_mem(&bytes).unwrap();
+        assert_eq!(original.revisions().len(), 1);
+        let original_objects = original.revisions()[0].changed_objects.len();
+
+        let mut update = IncrementalDocument::create_from(bytes.clone(), original);
+        let info_id = update
+            .new_document
+            .add_object(dictionary! { "Title" => Object::string_literal("v2") });
+        update.new_document.trailer.set("Info", info_id);
+        let mut updated = vec![];
+        update.save_to(&mut updated).unwrap();
+
+        let incremental = IncrementalDocument::create_from(updated.clone(), Document::load_mem(&updated).unwrap());
+        let revisions = incremental.get_prev_documents().revisions();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(updated[..revisions[0].end].trim_ascii_end(), bytes.trim_ascii_end());
+        assert_eq!(revisions[0].changed_objects.len(), original_objects);
+        assert!(revisions[1].changed_objects.contains(&info_id));
+        assert!(!revisions[0].changed_objects.contains(&info_id));
+        assert!(revisions[1].trailer.has(b"Prev"));
+
+        let first = incremental.load_revision(0).unwrap();
+        assert_ne!(first.trailer.get(b"Info").ok(), Some(&Object::Reference(info_id)));
+        assert!(!first.objects.contains_key(&info_id));
+        assert!(incremental.load_revision(1).unwrap().objects.contains_key(&info_id));
+        assert!(incremental.load_revision(2).is_err());
+    }
+
+    #[test]
+    fn linearized_file_is_one_revision() {
+        let mut doc = create_document();
+        let mut bytes = vec![];
+        doc.save_linearized_to(&mut bytes).unwrap();
+
+        let loaded = Document::load_mem(&bytes).unwrap();
+        let revisions = loaded.revisions();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].end, bytes.len());
+        assert_eq!(revisions[0].changed_objects.len(), loaded.objects.len());
+    }
+}
@@ -0,0 +1,540 @@
+use sha2::{Digest, Sha256};
+
+use crate::writer::Writer;
+use crate::xref::XrefEntry;
+use crate::{
+    Dictionary, Document, Error, FieldType, IncrementalDocument, Object, ObjectId, Rect, Result, StringFormat,
+    decode_text_string, text_string, xobject,
+};
+
+/// Placeholder written for each `/ByteRange` number; wide enough for files of up to 10 GB.
+const BYTE_RANGE_PLACEHOLDER: i64 = 9_999_999_999;
+
+/// Creates the CMS signature of a PDF, e.g. by calling out to an HSM.
+pub trait Signer {
+    /// Number of bytes reserved in the file for the signature. Must be at least the length of the
+    /// blob returned by [`Signer::sign`].
+    fn reserved_size(&self) -> usize {
+        16384
+    }
+
+    /// Create a detached CMS `SignedData` (DER encoded) whose `messageDigest` attribute is the given
+    /// SHA-256 digest of the signed byte ranges.
+    fn sign(&self, digest: &[u8; 32]) -> Result<Vec<u8>>;
+}
+
+/// Options for [`IncrementalDocument::sign`].
+#[derive(Debug, Clone)]
+pub struct SignatureOptions {
+    /// Name of the signature field to create.
+    pub field_name: String,
+    /// Page number (1-based) of the signature widget.
+    pub page: u32,
+    /// Location of the widget on the page; an empty rectangle makes the signature invisible.
+    pub rect: Rect,
+    /// Value of `/SubFilter`.
+    pub sub_filter: String,
+    /// Name of the signer (`/Name`).
+    pub name: Option<String>,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+    pub contact_info: Option<String>,
+    /// Signing time (`/M`) as a PDF date string, e.g. converted from a `chrono`, `time` or `jiff` date.
+    pub signing_time: Option<Object>,
+}
+
+impl Default for SignatureOptions {
+    fn default() -> Self {
+        SignatureOptions {
+            field_name: "Signature1".to_string(),
+            page: 1,
+            rect: Rect::new(0.0, 0.0, 0.0, 0.0),
+            sub_filter: "ETSI.CAdES.detached".to_string(),
+            name: None,
+            reason: None,
+            location: None,
+            contact_info: None,
+            signing_time: None,
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| position + from)
+}
+
+impl IncrementalDocument {
+    /// Sign the document and return the bytes of the signed file.
+    ///
+    /// A signature field with an empty `/Contents` placeholder is added in a new incremental update.
+    /// After saving, the SHA-256 digest of the whole file except the placeholder is passed to the
+    /// signer and the returned CMS blob is written into the placeholder.
+    pub fn sign(mut self, options: &SignatureOptions, signer: &dyn Signer) -> Result<Vec<u8>> {
+        let prev = self.get_prev_documents();
+        if prev.trailer.has(b"Encrypt") {
+            return Err(Error::Signature(
+                "signing encrypted documents is not supported".to_string(),
+            ));
+        }
+        if prev
+            .get_form_fields()?
+            .iter()
+            .any(|field| field.name == options.field_name)
+        {
+            return Err(Error::Signature(format!(
+                "field \"{}\" already exists",
+                options.field_name
+            )));
+        }
+        let page_id = *prev
+            .get_pages()
+            .get(&options.page)
+            .ok_or(Error::PageNumberNotFound(options.page))?;
+        let catalog_id = prev.trailer.get(b"Root").and_then(Object::as_reference)?;
+
+        let mut byte_range = vec![Object::Integer(BYTE_RANGE_PLACEHOLDER); 4];
+        byte_range[0] = Object::Integer(0);
+        let mut signature = dictionary! {
+            "Type" => "Sig",
+            "Filter" => "Adobe.PPKLite",
+            "SubFilter" => Object::Name(options.sub_filter.as_bytes().to_vec()),
+            "ByteRange" => byte_range,
+            "Contents" => Object::String(vec![0; signer.reserved_size()], StringFormat::Hexadecimal),
+        };
+        if let Some(signing_time) = &options.signing_time {
+            signature.set("M", signing_time.clone());
+        }
+        for (key, value) in [
+            ("Name", &options.name),
+            ("Reason", &options.reason),
+            ("Location", &options.location),
+            ("ContactInfo", &options.contact_info),
+        ] {
+            if let Some(value) = value {
+                signature.set(key, text_string(value));
+            }
+        }
+        let signature_id = self.new_document.add_object(signature);
+
+        // An empty appearance, as required for widgets even if the signature is invisible.
+        let appearance_id = self.new_document.add_object(xobject::form(
+            vec![0.0, 0.0, options.rect.width(), options.rect.height()],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            vec![],
+        ));
+
+        // The field is merged with its widget annotation; print and locked flags are set.
+        let field_id = self.new_document.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "FT" => "Sig",
+            "T" => text_string(&options.field_name),
+            "V" => signature_id,
+            "Rect" => options.rect,
+            "F" => 132,
+            "P" => page_id,
+            "AP" => dictionary! { "N" => appearance_id },
+        });
+        self.add_to_array(page_id, b"Annots", field_id)?;
+
+        self.opt_clone_object_to_new_document(catalog_id)?;
+        let acro_form_id = match self.new_document.get_dictionary(catalog_id)?.get(b"AcroForm") {
+            Ok(Object::Reference(id)) => *id,
+            Ok(Object::Dictionary(acro_form)) => {
+                // Move the AcroForm out of the catalog so that it can be handled like a referenced one.
+                let acro_form = acro_form.clone();
+                self.new_document.add_object(acro_form)
+            }
+            _ => self.new_document.add_object(Dictionary::new()),
+        };
+        self.new_document
+            .get_dictionary_mut(catalog_id)?
+            .set("AcroForm", acro_form_id);
+        self.add_to_array(acro_form_id, b"Fields", field_id)?;
+        // SignaturesExist | AppendOnly
+        self.new_document.get_dictionary_mut(acro_form_id)?.set("SigFlags", 3);
+
+        let mut bytes = vec![];
+        let xref = self.save_internal(&mut bytes, &[])?;
+
+        // Locate the placeholders from the offset the signature dictionary was written at.
+        let not_found = || Error::Signature("signature placeholder not found".to_string());
+        let Some(XrefEntry::Normal { offset, .. }) = xref.get(signature_id.0) else {
+            return Err(not_found());
+        };
+        let dict_start = *offset as usize + format!("{} {} obj\n", signature_id.0, signature_id.1).len();
+        let signature = self.new_document.get_dictionary(signature_id)?;
+        let (serialized, ranges) = Writer::dictionary_value_ranges(signature)?;
+        if bytes.get(dict_start..dict_start + serialized.len()) != Some(serialized.as_slice()) {
+            return Err(not_found());
+        }
+        let value_range = |key: &[u8]| {
+            let index = signature.iter().position(|(name, _)| name == key)?;
+            Some(dict_start + ranges[index].start..dict_start + ranges[index].end)
+        };
+        let byte_range_value = value_range(b"ByteRange").ok_or_else(not_found)?;
+        // The numbers inside the brackets are overwritten.
+        let (range_start, range_end) = (byte_range_value.start + 1, byte_range_value.end - 1);
+        let contents = value_range(b"Contents").ok_or_else(not_found)?;
+        let (contents_start, contents_end) = (contents.start, contents.end);
+
+        let byte_range = format!("0 {} {} {}", contents_start, contents_end, bytes.len() - contents_end);
+        let placeholder_len = range_end - range_start;
+        if byte_range.len() > placeholder_len {
+            return Err(Error::Signature(
+                "file too large for the byte range placeholder".to_string(),
+            ));
+        }
+        bytes[range_start..range_end].copy_from_slice(format!("{byte_range:placeholder_len$}").as_bytes());
+
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes[..contents_start]);
+        hasher.update(&bytes[contents_end..]);
+        let cms = signer.sign(&hasher.finalize().into())?;
+        if cms.len() > signer.reserved_size() {
+            return Err(Error::Signature(format!(
+                "signature of {} bytes exceeds the {} reserved bytes",
+                cms.len(),
+                signer.reserved_size()
+            )));
+        }
+        let hex: String = cms.iter().map(|byte| format!("{byte:02X}")).collect();
+        bytes[contents_start + 1..contents_start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+        Ok(bytes)
+    }
+
+    /// Append a reference to an array entry of a dictionary, copying the dictionary (and the array
+    /// if it is referenced) into the new document.
+    fn add_to_array(&mut self, dict_id: ObjectId, key: &[u8], item: ObjectId) -> Result<()> {
+        self.opt_clone_object_to_new_document(dict_id)?;
+        match self.new_document.get_dictionary(dict_id)?.get(key) {
+            Ok(Object::Reference(array_id)) => {
+                let array_id = *array_id;
+                self.opt_clone_object_to_new_document(array_id)?;
+                self.new_document
+                    .get_object_mut(array_id)?
+                    .as_array_mut()?
+                    .push(item.into());
+            }
+            Ok(Object::Array(_)) => {
+                self.new_document
+                    .get_dictionary_mut(dict_id)?
+                    .get_mut(key)?
+                    .as_array_mut()?
+                    .push(item.into());
+            }
+            _ => self
+                .new_document
+                .get_dictionary_mut(dict_id)?
+                .set(key, vec![Object::Reference(item)]),
+        }
+        Ok(())
+    }
+}
+
+/// A signature of a signature field, as found in the signature dictionary (`/V`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureInfo {
+    /// Fully qualified name of the signature field.
+    pub field_name: String,
+    pub field_id: ObjectId,
+    pub filter: Option<String>,
+    pub sub_filter: Option<String>,
+    /// Offset and length pairs of the signed parts of the file.
+    pub byte_range: Vec<(usize, usize)>,
+    /// The CMS or PKCS#7 blob, including the zero padding of the placeholder.
+    pub contents: Vec<u8>,
+    /// Signing time (`/M`) as a PDF date string.
+    pub signing_time: Option<String>,
+    pub name: Option<String>,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+    pub contact_info: Option<String>,
+}
+
+impl SignatureInfo {
+    /// Concatenate the signed parts of the file. Fails if a range is out of bounds.
+    pub fn signed_data(&self, file: &[u8]) -> Result<Vec<u8>> {
+        let mut data = vec![];
+        for &(offset, length) in &self.byte_range {
+            let range = file
+                .get(offset..offset.saturating_add(length))
+                .ok_or_else(|| Error::Signature(format!("byte range {offset}+{length} outside of file")))?;
+            data.extend_from_slice(range);
+        }
+        Ok(data)
+    }
+}
+
+/// Checks the cryptographic part of a signature, e.g. the CMS structure and certificate chain.
+pub trait SignatureVerifier {
+    /// Return whether the signature is valid for the signed data, i.e. the concatenated byte ranges.
+    fn verify(&self, signature: &SignatureInfo, signed_data: &[u8]) -> Result<bool>;
+}
+
+/// Result of [`IncrementalDocument::verify_signatures`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureVerification {
+    pub signature: SignatureInfo,
+    /// The byte ranges cover a whole revision of the file except for the `/Contents` string.
+    pub byte_range_valid: bool,
+    /// Incremental updates were appended after the signed revision.
+    pub modified_after_signing: bool,
+    /// Result of the [`SignatureVerifier`], if one was given.
+    pub signature_valid: Option<bool>,
+}
+
+impl Document {
+    /// List the signed signature fields with their signature dictionaries.
+    pub fn get_signatures(&self) -> Result<Vec<SignatureInfo>> {
+        let mut signatures = vec![];
+        for field in self.get_form_fields()? {
+            if field.field_type != FieldType::Signature {
+                continue;
+            }
+            let Ok(value) = self.get_dictionary(field.id)?.get(b"V") else {
+                continue;
+            };
+            let signature = self.dereference(value)?.1.as_dict()?;
+            let name = |key: &[u8]| {
+                signature
+                    .get(key)
+                    .and_then(Object::as_name)
+                    .map(|name| String::from_utf8_lossy(name).into_owned())
+                    .ok()
+            };
+            let text = |key: &[u8]| signature.get(key).and_then(decode_text_string).ok();
+            let byte_range = signature
+                .get(b"ByteRange")
+                .and_then(|range| self.dereference(range))
+                .and_then(|(_, range)| range.as_array())
+                .map(|range| {
+                    range
+                        .chunks_exact(2)
+                        .filter_map(|pair| {
+                            let offset = usize::try_from(pair[0].as_i64().ok()?).ok()?;
+                            Some((offset, usize::try_from(pair[1].as_i64().ok()?).ok()?))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            signatures.push(SignatureInfo {
+                field_name: field.name,
+                field_id: field.id,
+                filter: name(b"Filter"),
+                sub_filter: name(b"SubFilter"),
+                byte_range,
+                contents: signature
+                    .get(b"Contents")
+                    .and_then(Object::as_str)
+                    .unwrap_or_default()
+                    .to_vec(),
+                signing_time: signature
+                    .get(b"M")
+                    .and_then(Object::as_str)
+                    .map(|date| String::from_utf8_lossy(date).into_owned())
+                    .ok(),
+                name: text(b"Name"),
+                reason: text(b"Reason"),
+                location: text(b"Location"),
+                contact_info: text(b"ContactInfo"),
+            });
+        }
+        Ok(signatures)
+    }
+}
+
+impl IncrementalDocument {
+    /// Check the signatures of the loaded file.
+    ///
+    /// For each signature this verifies that the byte ranges cover a complete revision of the file
+    /// apart from the `/Contents` hole, and whether later incremental updates modified the file.
+    /// The signed data is passed to the verifier, if any, for the cryptographic checks.
+    pub fn verify_signatures(&self, verifier: Option<&dyn SignatureVerifier>) -> Result<Vec<SignatureVerification>> {
+        let file = self.get_prev_documents_bytes();
+        // Revision offsets are relative to the header.
+        let base = find(file, b"%PDF-", 0).unwrap_or(0);
+        let revision_ends: Vec<usize> = self
+            .get_prev_documents()
+            .revisions()
+            .iter()
+            .map(|revision| base + revision.end)
+            .collect();
+
+        let mut verifications = vec![];
+        for signature in self.get_prev_documents().get_signatures()? {
+            // The signed data ends at the end of a revision, with or without the final end-of-line marker.
+            let signed_end = signature.byte_range.last().map(|(offset, length)| offset + length);
+            let revision_end = signed_end.and_then(|signed_end| {
+                revision_ends.iter().copied().find(|&end| {
+                    file.get(signed_end..end)
+                        .is_some_and(|eol| eol.len() <= 2 && eol.iter().all(|&byte| byte == b'\r' || byte == b'\n'))
+                })
+            });
+            let byte_range_valid = match (signature.byte_range.as_slice(), revision_end) {
+                ([(0, first), (second, _)], Some(_)) => {
+                    // The hole must be exactly the hexadecimal `/Contents` string.
+                    let hole = file.get(*first..*second).unwrap_or_default();
+                    hole.len() >= 2
+                        && hole[0] == b'<'
+                        && hole[hole.len() - 1] == b'>'
+                        && hex_decode(&hole[1..hole.len() - 1]).as_ref() == Some(&signature.contents)
+                }
+                _ => false,
+            };
+            let modified_after_signing =
+                revision_end.is_none_or(|revision_end| revision_ends.iter().any(|&end| end > revision_end));
+            let signature_valid = match verifier {
+                Some(verifier) => Some(verifier.verify(&signature, &signature.signed_data(file)?)?),
+                None => None,
+            };
+            verifications.push(SignatureVerification {
+                signature,
+                byte_range_valid,
+                modified_after_signing,
+                signature_valid,
+            });
+        }
+        Ok(verifications)
+    }
+}
+
+fn hex_decode(hex: &[u8]) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = hex.iter().filter(|byte| !byte.is_ascii_whitespace()).copied().collect();
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = if pair.len() == 1 {
+                [pair[0], b'0']
+            } else {
+                [pair[0], pair[1]]
+            };
+            u8::from_str_radix(std::str::from_utf8(&pair).ok()?, 16).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::{SignatureInfo, SignatureOptions, SignatureVerifier, Signer};
+    use crate::creator::tests::create_document;
+    use crate::{Document, FieldType, IncrementalDocument, Object, Result};
+
+    /// Returns the digest itself in place of a CMS blob.
+    struct DigestSigner;
+
+    impl Signer for DigestSigner {
+        fn reserved_size(&self) -> usize {
+            64
+        }
+
+        fn sign(&self, digest: &[u8; 32]) -> Result<Vec<u8>> {
+            Ok(digest.to_vec())
+        }
+    }
+
+    /// Checks the digest written by [`DigestSigner`].
+    struct DigestVerifier;
+
+    impl SignatureVerifier for DigestVerifier {
+        fn verify(&self, signature: &SignatureInfo, signed_data: &[u8]) -> Result<bool> {
+            Ok(signature.contents[..32] == *Sha256::digest(signed_data))
+        }
+    }
+
+    fn incremental(bytes: &[u8]) -> IncrementalDocument {
+        IncrementalDocument::create_from(bytes.to_vec(), Document::load_mem(bytes).unwrap())
+    }
+
+    #[test]
+    fn sign_document() {
+        let mut original = vec![];
+        create_document().save_to(&mut original).unwrap();
+        let incremental = incremental(&original);
+        let options = SignatureOptions {
+            reason: Some("Approved".to_string()),
+            ..SignatureOptions::default()
+        };
+        let signed = incremental.sign(&options, &DigestSigner).unwrap();
+        assert!(signed.starts_with(&original));
+
+        let doc = Document::load_mem(&signed).unwrap();
+        let field = doc.get_form_field("Signature1").unwrap();
+        assert_eq!(field.field_type, FieldType::Signature);
+        let widget = doc.get_dictionary(field.id).unwrap();
+        let appearance_id = widget
+            .get_deref(b"AP", &doc)
+            .and_then(|ap| ap.as_dict().unwrap().get(b"N"))
+            .and_then(Object::as_reference)
+            .unwrap();
+        assert!(doc.get_object(appearance_id).and_then(Object::as_stream).is_ok());
+        let signature_id = doc
+            .get_dictionary(field.id)
+            .unwrap()
+            .get(b"V")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let signature = doc.get_dictionary(signature_id).unwrap();
+        let byte_range: Vec<usize> = signature
+            .get(b"ByteRange")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_i64().unwrap() as usize)
+            .collect();
+        assert_eq!(byte_range[0], 0);
+        assert_eq!(byte_range[2] + byte_range[3], signed.len());
+        assert_eq!(signed[byte_range[1]], b'<');
+        assert_eq!(signed[byte_range[2] - 1], b'>');
+
+        let mut hasher = Sha256::new();
+        hasher.update(&signed[..byte_range[1]]);
+        hasher.update(&signed[byte_range[2]..]);
+        let contents = signature.get(b"Contents").unwrap().as_str().unwrap();
+        assert_eq!(contents.len(), 64);
+        assert_eq!(&contents[..32], hasher.finalize().as_slice());
+        assert!(contents[32..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn verify_signature() {
+        let mut original = vec![];
+        create_document().save_to(&mut original).unwrap();
+        let mut signed = incremental(&original)
+            .sign(&SignatureOptions::default(), &DigestSigner)
+            .unwrap();
+
+        let verifications = incremental(&signed).verify_signatures(Some(&DigestVerifier)).unwrap();
+        assert_eq!(verifications.len(), 1);
+        assert_eq!(
+            verifications[0].signature.sub_filter.as_deref(),
+            Some("ETSI.CAdES.detached")
+        );
+        assert!(verifications[0].byte_range_valid);
+        assert!(!verifications[0].modified_after_signing);
+        assert_eq!(verifications[0].signature_valid, Some(true));
+
+        // Append an update after signing.
+        let mut update = incremental(&signed);
+        update.new_document.trailer.set("Info", Object::Null);
+        let mut updated = vec![];
+        update.save_to(&mut updated).unwrap();
+        let verifications = incremental(&updated).verify_signatures(Some(&DigestVerifier)).unwrap();
+        assert!(verifications[0].byte_range_valid);
+        assert!(verifications[0].modified_after_signing);
+        assert_eq!(verifications[0].signature_valid, Some(true));
+
+        // Tamper with the signed revision.
+        let position = signed.windows(3).position(|w| w == b"595").unwrap();
+        signed[position] = b'6';
+        let verifications = incremental(&signed).verify_signatures(Some(&DigestVerifier)).unwrap();
+        assert_eq!(verifications[0].signature_valid, Some(false));
+    }
+}
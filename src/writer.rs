@@ -1,31 +1,65 @@
 use std::fs::File;
 use std::io::{BufWriter, Result, Write};
+use std::ops::Range;
 use std::path::Path;
 use std::vec;
 
 use super::Object::*;
-use super::{Dictionary, Document, Object, Stream, StringFormat};
+use super::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use crate::encryption::{encrypt_object, stream_encryption, EncryptionState};
 use crate::{xref::*, IncrementalDocument};
+use md5::{Digest as _, Md5};
 
 impl Document {
     /// Save PDF document to specified file path.
     #[inline]
     pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<File> {
         let mut file = BufWriter::new(File::create(path)?);
-        self.save_internal(&mut file)?;
+        self.save_internal(&mut file, None)?;
         Ok(file.into_inner()?)
     }
 
     /// Save PDF to arbitrary target
     #[inline]
     pub fn save_to<W: Write>(&mut self, target: &mut W) -> Result<()> {
-        self.save_internal(target)
+        self.save_internal(target, None)
     }
 
-    fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
+    /// Save PDF document to specified file path, encrypted again with the security settings it was
+    /// decrypted with.
+    #[inline]
+    pub fn save_encrypted<P: AsRef<Path>>(&mut self, path: P) -> crate::Result<File> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.save_encrypted_to(&mut file)?;
+        Ok(file.into_inner().map_err(|err| err.into_error())?)
+    }
+
+    /// Save PDF to arbitrary target, encrypted again with the security settings it was decrypted
+    /// with.
+    ///
+    /// The stored [`EncryptionState`] is applied unchanged: the encryption dictionary keeps its
+    /// `/O`, `/U`, `/Perms` values and the file encryption key, and the trailer `/ID` is kept, so
+    /// the same passwords open the saved file. The document itself stays decrypted.
+    pub fn save_encrypted_to<W: Write>(&mut self, target: &mut W) -> crate::Result<()> {
+        let state = self.encryption_state.clone().ok_or(crate::Error::NotEncrypted)?;
+        let max_id = self.max_id;
+        let encrypt_id = self.add_object(state.encode()?);
+        self.trailer.set("Encrypt", Reference(encrypt_id));
+        let result = self.save_internal(target, Some((&state, encrypt_id)));
+        self.trailer.remove(b"Encrypt");
+        self.objects.remove(&encrypt_id);
+        // The encryption dictionary only exists in the saved file.
+        self.max_id = max_id;
+        Ok(result?)
+    }
+
+    fn save_internal<W: Write>(
+        &mut self, target: &mut W, encryption: Option<(&EncryptionState, ObjectId)>,
+    ) -> Result<()> {
         let mut target = CountingWrite {
             inner: target,
             bytes_written: 0,
+            digest: Md5::new(),
         };
 
         let mut xref = Xref::new(self.max_id + 1, self.reference_table.cross_reference_type);
@@ -40,11 +74,18 @@ fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
                 .ok()
                 != Some(true)
             {
-                Writer::write_indirect_object(&mut target, id, generation, object, &mut xref)?;
+                match encryption {
+                    Some((state, encrypt_id)) if encrypt_id != (id, generation) => {
+                        Writer::write_encrypted_indirect_object(&mut target, state, id, generation, object, &mut xref)?;
+                    }
+                    _ => Writer::write_indirect_object(&mut target, id, generation, object, &mut xref)?,
+                }
             }
         }
 
         let xref_start = target.bytes_written;
+        let first_id = file_id(&self.trailer);
+        self.update_file_id(first_id, target.digest.clone().finalize().to_vec());
 
         // Pick right cross reference stream.
         match xref.cross_reference_type {
@@ -115,6 +156,33 @@ fn write_cross_reference_stream<W: Write>(
         Ok(())
     }
 
+    /// Set the trailer `/ID` for the file being written: the first identifier is kept, or set to
+    /// `digest` if there is none yet, and the second identifier is set to `digest`.
+    pub(crate) fn update_file_id(&mut self, first: Option<Vec<u8>>, digest: Vec<u8>) {
+        let first = first.unwrap_or_else(|| digest.clone());
+        self.trailer.set(
+            "ID",
+            Array(vec![
+                String(first, StringFormat::Hexadecimal),
+                String(digest, StringFormat::Hexadecimal),
+            ]),
+        );
+    }
+
+    /// Create a file identifier from the content of the document, for documents that don't have
+    /// one yet.
+    pub(crate) fn generate_file_id(&self) -> Vec<u8> {
+        let mut digest = Md5::new();
+        digest.update(self.version.as_bytes());
+        for (&(id, generation), object) in &self.objects {
+            let mut bytes = format!("{id} {generation} obj\n").into_bytes();
+            // Writing to a vector doesn't fail.
+            let _ = Writer::write_object(&mut bytes, object);
+            digest.update(&bytes);
+        }
+        digest.finalize().to_vec()
+    }
+
     fn write_trailer(&mut self, file: &mut dyn Write) -> Result<()> {
         self.trailer.set("Size", i64::from(self.max_id + 1));
         file.write_all(b"trailer\n")?;
@@ -128,20 +196,24 @@ impl IncrementalDocument {
     #[inline]
     pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<File> {
         let mut file = BufWriter::new(File::create(path)?);
-        self.save_internal(&mut file)?;
+        self.save_internal(&mut file, &[])?;
         Ok(file.into_inner()?)
     }
 
     /// Save PDF to arbitrary target
     #[inline]
     pub fn save_to<W: Write>(&mut self, target: &mut W) -> Result<()> {
-        self.save_internal(target)
+        self.save_internal(target, &[])?;
+        Ok(())
     }
 
-    fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
+    /// Append the objects of `new_document` to the previous documents, marking `freed` objects as
+    /// free in the new cross-reference section, which is returned.
+    pub(crate) fn save_internal<W: Write>(&mut self, target: &mut W, freed: &[ObjectId]) -> Result<Xref> {
         let mut target = CountingWrite {
             inner: target,
             bytes_written: 0,
+            digest: Md5::new(),
         };
 
         // Write previous document versions.
@@ -161,9 +233,6 @@ fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
                 writeln!(target)?;
             }
         }
-        writeln!(target, "%PDF-{}", self.new_document.version)?;
-
-        Writer::write_binary_mark(&mut target, &self.new_document.binary_mark)?;
 
         for (&(id, generation), object) in &self.new_document.objects {
             if object
@@ -175,8 +244,16 @@ fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
                 Writer::write_indirect_object(&mut target, id, generation, object, &mut xref)?;
             }
         }
+        for &(id, _) in freed {
+            xref.insert(id, XrefEntry::Free);
+        }
 
         let xref_start = target.bytes_written;
+        // The first identifier is the one of the original file.
+        let first_id =
+            file_id(&self.new_document.trailer).or_else(|| file_id(&self.get_prev_documents().trailer));
+        self.new_document
+            .update_file_id(first_id, target.digest.clone().finalize().to_vec());
 
         // Pick right cross reference stream.
         match xref.cross_reference_type {
@@ -193,7 +270,16 @@ fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
         // Write `startxref` part of trailer
         write!(target, "\nstartxref\n{xref_start}\n%%EOF")?;
 
-        Ok(())
+        Ok(xref)
+    }
+}
+
+/// The first identifier of the trailer `/ID`, if it is valid.
+pub(crate) fn file_id(trailer: &Dictionary) -> Option<Vec<u8>> {
+    let id = trailer.get(b"ID").and_then(Object::as_array).ok()?;
+    match id.as_slice() {
+        [String(first, _), String(_, _)] => Some(first.clone()),
+        _ => None,
     }
 }
 
@@ -297,8 +383,7 @@ fn create_xref_steam(xref: &Xref, filter: XRefStreamFilter) -> Result<(Vec<u8>,
             xref_index.push(Integer(section.starting_id as i64));
             xref_index.push(Integer(section.entries.len() as i64));
             // Add entries to stream
-            let mut obj_id = section.starting_id;
-            for entry in section.entries {
+            for (obj_id, entry) in (section.starting_id..).zip(section.entries) {
                 match entry {
                     XrefEntry::Free => {
                         // Type 0
@@ -325,7 +410,6 @@ fn create_xref_steam(xref: &Xref, filter: XRefStreamFilter) -> Result<(Vec<u8>,
                         xref_stream.extend(index.to_be_bytes());
                     }
                 }
-                obj_id += 1;
             }
         }
 
@@ -363,6 +447,51 @@ fn write_indirect_object<W: Write>(
         Ok(())
     }
 
+    /// Write an object encrypted with `state`. The content of a stream is encrypted while it is
+    /// written, if the length of the ciphertext is known in advance, instead of encrypting a copy.
+    fn write_encrypted_indirect_object<W: Write>(
+        file: &mut CountingWrite<&mut W>, state: &EncryptionState, id: u32, generation: u16, object: &Object,
+        xref: &mut Xref,
+    ) -> Result<()> {
+        let mut streaming = None;
+        if let Stream(stream) = object {
+            match stream_encryption(state, (id, generation), stream).map_err(std::io::Error::other)? {
+                Some((crypt_filter, key)) => {
+                    if let Some(len) = crypt_filter.ciphertext_len(stream.content.len()) {
+                        streaming = Some((stream, crypt_filter, key, len));
+                    }
+                }
+                // The stream is written as it is.
+                None => return Writer::write_indirect_object(file, id, generation, object, xref),
+            }
+        }
+
+        let Some((stream, crypt_filter, key, len)) = streaming else {
+            let mut object = object.clone();
+            encrypt_object(state, (id, generation), &mut object).map_err(std::io::Error::other)?;
+            return Writer::write_indirect_object(file, id, generation, &object, xref);
+        };
+
+        // Only the strings of the stream dictionary are encrypted beforehand.
+        let mut dict = stream.dict.clone();
+        for (_, value) in dict.iter_mut() {
+            encrypt_object(state, (id, generation), value).map_err(std::io::Error::other)?;
+        }
+        dict.set("Length", len as i64);
+
+        let offset = file.bytes_written as u32;
+        xref.insert(id, XrefEntry::Normal { offset, generation });
+        writeln!(file, "{id} {generation} obj")?;
+        Writer::write_dictionary(file, &dict)?;
+        file.write_all(b"stream\n")?;
+        crypt_filter
+            .encrypt_reader(&key, &mut stream.content.as_slice(), file)
+            .map_err(std::io::Error::other)?;
+        file.write_all(b"\nendstream")?;
+        writeln!(file, "\nendobj")?;
+        Ok(())
+    }
+
     pub fn write_object(file: &mut dyn Write, object: &Object) -> Result<()> {
         match object {
             Null => file.write_all(b"null"),
@@ -481,6 +610,24 @@ fn write_dictionary(file: &mut dyn Write, dictionary: &Dictionary) -> Result<()>
         Ok(())
     }
 
+    /// Serialize a dictionary like [`Writer::write_object`], together with the position of each
+    /// value in the output, in the order of the entries.
+    pub(crate) fn dictionary_value_ranges(dictionary: &Dictionary) -> Result<(Vec<u8>, Vec<Range<usize>>)> {
+        let mut bytes = b"<<".to_vec();
+        let mut ranges = vec![];
+        for (key, value) in dictionary {
+            Writer::write_name(&mut bytes, key)?;
+            if Writer::need_separator(value) {
+                bytes.push(b' ');
+            }
+            let start = bytes.len();
+            Writer::write_object(&mut bytes, value)?;
+            ranges.push(start..bytes.len());
+        }
+        bytes.extend(b">>");
+        Ok((bytes, ranges))
+    }
+
     fn write_stream(file: &mut dyn Write, stream: &Stream) -> Result<()> {
         Writer::write_dictionary(file, &stream.dict)?;
         file.write_all(b"stream\n")?;
@@ -493,7 +640,7 @@ fn write_stream(file: &mut dyn Write, stream: &Stream) -> Result<()> {
     ///
     /// Note: Specified in  ISO 19005-2:2011, ISO 19005-3:2012
     /// headerByte1 > 127 && headerByte2 > 127 && headerByte3 > 127 && headerByte4 > 127
-    fn write_binary_mark(file: &mut dyn Write, binary_mark: &[u8]) -> Result<()> {
+    pub(crate) fn write_binary_mark(file: &mut dyn Write, binary_mark: &[u8]) -> Result<()> {
         if binary_mark.iter().all(|&byte| byte >= 128) {
             file.write_all(b"%")?;
             file.write_all(binary_mark)?;
@@ -512,6 +659,8 @@ fn write_binary_mark(file: &mut dyn Write, binary_mark: &[u8]) -> Result<()> {
 pub struct CountingWrite<W: Write> {
     inner: W,
     bytes_written: usize,
+    /// Digest of the bytes written, from which the trailer `/ID` is derived.
+    digest: Md5,
 }
 
 impl<W: Write> Write for CountingWrite<W> {
@@ -520,6 +669,7 @@ fn write(&mut self, buffer: &[u8]) -> Result<usize> {
         let result = self.inner.write(buffer);
         if let Ok(bytes) = result {
             self.bytes_written += bytes;
+            self.digest.update(&buffer[..bytes]);
         }
         result
     }
@@ -527,6 +677,7 @@ fn write(&mut self, buffer: &[u8]) -> Result<usize> {
     #[inline]
     fn write_all(&mut self, buffer: &[u8]) -> Result<()> {
         self.bytes_written += buffer.len();
+        self.digest.update(buffer);
         // If this returns `Err` we can’t know how many bytes were actually written (if any)
         // but that doesn’t matter since we’re gonna abort the entire PDF generation anyway.
         self.inner.write_all(buffer)
@@ -575,3 +726,39 @@ fn save_document() {
     // Check if the file is above 400 bytes (should be about 610 bytes)
     assert!(file_path.metadata().unwrap().len() > 400);
 }
+
+#[test]
+fn file_id_is_generated_and_updated() {
+    let id = |doc: &Document| -> Vec<Vec<u8>> {
+        let id = doc.trailer.get(b"ID").and_then(Object::as_array).unwrap();
+        id.iter().map(|part| part.as_str().unwrap().to_vec()).collect()
+    };
+
+    let mut doc = crate::creator::tests::create_document();
+    doc.trailer.remove(b"ID");
+    let mut first = vec![];
+    doc.save_to(&mut first).unwrap();
+    let saved = Document::load_mem(&first).unwrap();
+    let [first_id, second_id] = id(&saved).try_into().unwrap();
+    // Both identifiers are the same when a file is first written.
+    assert_eq!(first_id.len(), 16);
+    assert_eq!(first_id, second_id);
+
+    doc.add_object(Integer(1));
+    let mut second = vec![];
+    doc.save_to(&mut second).unwrap();
+    let saved_again = Document::load_mem(&second).unwrap();
+    let [first_again, second_again] = id(&saved_again).try_into().unwrap();
+    assert_eq!(first_again, first_id);
+    assert_ne!(second_again, second_id);
+
+    // Incremental updates keep the first identifier of the original file.
+    let mut incremental = IncrementalDocument::create_from(first.clone(), saved);
+    incremental.new_document.add_object(Integer(1));
+    let mut updated = vec![];
+    incremental.save_to(&mut updated).unwrap();
+    let updated = Document::load_mem(&updated).unwrap();
+    let [first_updated, second_updated] = id(&updated).try_into().unwrap();
+    assert_eq!(first_updated, first_id);
+    assert_ne!(second_updated, second_id);
+}
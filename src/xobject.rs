@@ -7,8 +7,9 @@
 #[cfg(feature = "embed_image")]
 use std::path::Path;
 
-#[cfg(feature = "embed_image")]
-use crate::Result;
+mod encoded;
+
+pub use encoded::{EncodedFormat, ImageHeader};
 
 #[derive(Debug, Clone)]
 pub struct PdfImage<'a> {
@@ -39,8 +40,158 @@ pub fn form(boundingbox: Vec<f32>, matrix: Vec<f32>, content: Vec<u8>) -> Stream
     xobject
 }
 
+/// An image XObject together with the streams its dictionary refers to.
+#[derive(Debug, Clone)]
+pub struct ImageXObject {
+    /// The image stream. Its `/ColorSpace` is a device color space until the image is added to a document.
+    pub image: Stream,
+    /// ICC profile of the image, referenced as an `/ICCBased` color space once added to a document.
+    pub icc_profile: Option<Stream>,
+    /// DeviceGray image with the alpha channel, referenced as `/SMask` once added to a document.
+    pub soft_mask: Option<Stream>,
+}
+
+impl ImageXObject {
+    /// Add the image and the streams it refers to to the document, returning the ID of the image.
+    pub fn add_to(self, doc: &mut Document) -> ObjectId {
+        let mut image = self.image;
+        if let Some(icc_profile) = self.icc_profile {
+            let profile_id = doc.add_object(icc_profile);
+            image.dict.set(
+                "ColorSpace",
+                vec![Object::Name(b"ICCBased".to_vec()), profile_id.into()],
+            );
+        }
+        if let Some(soft_mask) = self.soft_mask {
+            let soft_mask_id = doc.add_object(soft_mask);
+            image.dict.set("SMask", soft_mask_id);
+        }
+        doc.add_object(image)
+    }
+}
+
+impl From<Stream> for ImageXObject {
+    fn from(image: Stream) -> Self {
+        ImageXObject {
+            image,
+            icc_profile: None,
+            soft_mask: None,
+        }
+    }
+}
+
+/// Embed a JPEG or JPEG 2000 image as it is, reading only its header.
+///
+/// JPEG images are embedded with `/DCTDecode`. CMYK and YCCK images written by Adobe applications store
+/// inverted components, which get an inverted `/Decode` array. An embedded ICC profile is kept if it
+/// matches the number of components. JPEG 2000 images are embedded with `/JPXDecode`, leaving the color
+/// space to the JP2 header if there is one.
+pub fn encoded_image(buffer: Vec<u8>) -> Result<ImageXObject> {
+    let header = ImageHeader::read(&buffer)?;
+    Ok(encoded_xobject(buffer, encoded_image_dict(header)?))
+}
+
+/// The image dictionary and ICC profile to embed an image with the given header as it is.
+fn encoded_image_dict(header: ImageHeader) -> Result<(Dictionary, Option<Stream>)> {
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Image".to_vec()));
+    dict.set("Width", header.width);
+    dict.set("Height", header.height);
+
+    let mut icc_profile = None;
+    match header.format {
+        EncodedFormat::Jpeg => {
+            if header.bits_per_component != 8 {
+                return Err(Error::Unimplemented("DCTDecode only supports 8 bits per component"));
+            }
+            let color_space = device_color_space(header.components)?;
+            dict.set("ColorSpace", Object::Name(color_space.to_vec()));
+            dict.set("BitsPerComponent", 8);
+            if header.components == 4 && header.adobe_transform.is_some() {
+                dict.set("Decode", [1, 0, 1, 0, 1, 0, 1, 0].map(Object::Integer).to_vec());
+            }
+            dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+            icc_profile = header
+                .icc_profile
+                .filter(|profile| icc_profile_matches(profile, header.components))
+                .map(|profile| icc_profile_stream(profile, header.components, color_space));
+        }
+        EncodedFormat::J2k => {
+            let color_space = device_color_space(header.components)?;
+            dict.set("ColorSpace", Object::Name(color_space.to_vec()));
+            dict.set("BitsPerComponent", header.bits_per_component);
+            dict.set("Filter", Object::Name(b"JPXDecode".to_vec()));
+        }
+        EncodedFormat::Jp2 => {
+            dict.set("Filter", Object::Name(b"JPXDecode".to_vec()));
+        }
+    }
+
+    Ok((dict, icc_profile))
+}
+
+fn encoded_xobject(buffer: Vec<u8>, (dict, icc_profile): (Dictionary, Option<Stream>)) -> ImageXObject {
+    ImageXObject {
+        image: Stream::new(dict, buffer).with_compression(false),
+        icc_profile,
+        soft_mask: None,
+    }
+}
+
+fn device_color_space(components: u16) -> Result<&'static [u8]> {
+    match components {
+        1 => Ok(b"DeviceGray"),
+        3 => Ok(b"DeviceRGB"),
+        4 => Ok(b"DeviceCMYK"),
+        _ => Err(Error::InvalidImage(format!(
+            "unsupported number of color components {components}"
+        ))),
+    }
+}
+
+/// Check the color space signature in the profile header against the number of components.
+fn icc_profile_matches(profile: &[u8], components: u16) -> bool {
+    let signature: &[u8] = match components {
+        1 => b"GRAY",
+        3 => b"RGB ",
+        4 => b"CMYK",
+        _ => return false,
+    };
+    profile.get(16..20) == Some(signature)
+}
+
+fn icc_profile_stream(profile: Vec<u8>, components: u16, alternate: &[u8]) -> Stream {
+    let mut dict = Dictionary::new();
+    dict.set("N", components);
+    dict.set("Alternate", Object::Name(alternate.to_vec()));
+    let mut stream = Stream::new(dict, profile);
+    // Ignore any compression error.
+    let _ = stream.compress();
+    stream
+}
+
+/// Embed an image file as an image XObject stream.
+///
+/// ICC profiles and alpha channels, which need streams of their own, are dropped.
 #[cfg(feature = "embed_image")]
+#[deprecated(note = "use `image_xobject`, which keeps ICC profiles and alpha channels")]
 pub fn image<P: AsRef<Path>>(path: P) -> Result<Stream> {
+    Ok(image_xobject(path)?.image)
+}
+
+/// Embed an image as an image XObject stream.
+///
+/// ICC profiles and alpha channels, which need streams of their own, are dropped.
+#[cfg(feature = "embed_image")]
+#[deprecated(note = "use `image_xobject_from`, which keeps ICC profiles and alpha channels")]
+pub fn image_from(buffer: Vec<u8>) -> Result<Stream> {
+    Ok(image_xobject_from(buffer)?.image)
+}
+
+/// Embed an image file, see [`image_xobject_from`].
+#[cfg(feature = "embed_image")]
+pub fn image_xobject<P: AsRef<Path>>(path: P) -> Result<ImageXObject> {
     use std::fs::File;
     use std::io::prelude::*;
 
@@ -48,11 +199,22 @@ pub fn image<P: AsRef<Path>>(path: P) -> Result<Stream> {
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
 
-    image_from(buffer)
+    image_xobject_from(buffer)
 }
 
+/// Embed an image together with its ICC profile and alpha channel.
+///
+/// JPEG and JPEG 2000 images are embedded as they are when PDF filters can read them, see
+/// [`encoded_image`]. Indexed PNGs keep their palette; other images are decoded.
 #[cfg(feature = "embed_image")]
-pub fn image_from(buffer: Vec<u8>) -> Result<Stream> {
+pub fn image_xobject_from(buffer: Vec<u8>) -> Result<ImageXObject> {
+    if let Ok(encoded) = ImageHeader::read(&buffer).and_then(encoded_image_dict) {
+        return Ok(encoded_xobject(buffer, encoded));
+    }
+    if let Some(image) = indexed_png(&buffer)? {
+        return Ok(image);
+    }
+
     let ((width, height), color_type) = get_dimensions_and_color_type(&buffer)?;
 
     let (bpc, color_space) = match color_type {
@@ -86,68 +248,160 @@ pub fn image_from(buffer: Vec<u8>) -> Result<Stream> {
     dict.set("ColorSpace", Object::Name(color_space));
     dict.set("BitsPerComponent", bpc);
 
-    let format = image::guess_format(&buffer)?;
-    if format == ImageFormat::Jpeg {
-        // JPEG do not need to be decoded
+    // JPEGs not embedded above, e.g. with 12 bits per component, are still passed through.
+    if image::guess_format(&buffer).ok() == Some(ImageFormat::Jpeg) {
         dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
-        Ok(Stream::new(dict, buffer))
-    } else {
-        // Other formats need to be decoded
-        let img = image::load_from_memory(&buffer)?;
-        let content = match img.color() {
-            // can be used directly
-            ColorType::L8 => img.into_bytes(),
-            // need to remove alpha channel
-            ColorType::La8 => img.into_luma8().into_raw(),
-            // can be used directly
-            ColorType::Rgb8 => img.into_bytes(),
-            // need to remove alpha channel
-            ColorType::Rgba8 => img.into_rgb8().into_raw(),
-            // need to convert each 16-bit pixel to big-endian bytes
-            ColorType::L16 => img
-                .into_luma16()
-                .into_raw()
-                .iter()
-                .flat_map(|&pixel| pixel.to_be_bytes()) // convert each 16-bit pixel to big-endian bytes
-                .collect(),
-            // need to remove alpha channel, then convert each 16-bit pixel to big-endian bytes
-            ColorType::La16 => img
-                .into_luma16() // remove alpha channel
-                .into_raw()
-                .iter()
-                .flat_map(|&pixel| pixel.to_be_bytes()) // convert each 16-bit pixel to big-endian bytes
-                .collect(),
-            // need to convert each 16-bit pixel to big-endian bytes
-            ColorType::Rgb16 => img
-                .into_rgb16()
-                .into_raw()
-                .iter()
-                .flat_map(|&pixel| pixel.to_be_bytes()) // convert each 16-bit pixel to big-endian bytes
-                .collect(),
-            // need to remove alpha channel, then convert each 16-bit pixel to big-endian bytes
-            ColorType::Rgba16 => img
-                .into_rgb16() // remove alpha channel
-                .into_raw()
-                .iter()
-                .flat_map(|&pixel| pixel.to_be_bytes()) // convert each 16-bit pixel to big-endian bytes
-                .collect(),
-            // f32 not supported, maybe JPXDecode?
-            ColorType::Rgb32F => return Err(Error::Unimplemented("ColorType::Rgb32F is not supported")),
-            ColorType::Rgba32F => return Err(Error::Unimplemented("ColorType::Rgba32F is not supported")),
-            // The above ColorType is all the types currently supported by the image crate
-            // But ColorType is #[non_exhaustive], there may be new types supported in the future
-            _ => {
-                return Err(Error::Unimplemented(
-                    "The image library supports a new color type, but lopdf has not been updated yet",
-                ))
-            }
-        };
+        return Ok(Stream::new(dict, buffer).into());
+    }
+
+    // Other formats need to be decoded
+    let img = image::load_from_memory(&buffer)?;
+    let (content, alpha) = match img.color() {
+        // can be used directly
+        ColorType::L8 => (img.into_bytes(), None),
+        // need to split off alpha channel
+        ColorType::La8 => split_alpha(img.into_luma_alpha8().into_raw(), 1, 1),
+        // can be used directly
+        ColorType::Rgb8 => (img.into_bytes(), None),
+        // need to split off alpha channel
+        ColorType::Rgba8 => split_alpha(img.into_rgba8().into_raw(), 3, 1),
+        // need to convert each 16-bit pixel to big-endian bytes
+        ColorType::L16 => (to_be_bytes(img.into_luma16().into_raw()), None),
+        // need to convert each 16-bit pixel to big-endian bytes, then split off alpha channel
+        ColorType::La16 => split_alpha(to_be_bytes(img.into_luma_alpha16().into_raw()), 2, 2),
+        // need to convert each 16-bit pixel to big-endian bytes
+        ColorType::Rgb16 => (to_be_bytes(img.into_rgb16().into_raw()), None),
+        // need to convert each 16-bit pixel to big-endian bytes, then split off alpha channel
+        ColorType::Rgba16 => split_alpha(to_be_bytes(img.into_rgba16().into_raw()), 6, 2),
+        // f32 not supported, maybe JPXDecode?
+        ColorType::Rgb32F => return Err(Error::Unimplemented("ColorType::Rgb32F is not supported")),
+        ColorType::Rgba32F => return Err(Error::Unimplemented("ColorType::Rgba32F is not supported")),
+        // The above ColorType is all the types currently supported by the image crate
+        // But ColorType is #[non_exhaustive], there may be new types supported in the future
+        _ => {
+            return Err(Error::Unimplemented(
+                "The image library supports a new color type, but lopdf has not been updated yet",
+            ))
+        }
+    };
+
+    let mut img_object = Stream::new(dict, content);
+    // Ignore any compression error.
+    let _ = img_object.compress();
+    Ok(ImageXObject {
+        image: img_object,
+        icc_profile: None,
+        soft_mask: alpha.map(|alpha| soft_mask(width, height, bpc, alpha)),
+    })
+}
+
+/// Embed a PNG with a palette as an `/Indexed` image, keeping its packed indices.
+///
+/// Returns `None` if the buffer is not an indexed PNG.
+#[cfg(feature = "embed_image")]
+fn indexed_png(buffer: &[u8]) -> Result<Option<ImageXObject>> {
+    if image::guess_format(buffer).ok() != Some(ImageFormat::Png) {
+        return Ok(None);
+    }
+    let decoder = png::Decoder::new(std::io::Cursor::new(buffer));
+    let mut reader = decoder.read_info().map_err(std::io::Error::from)?;
+    let info = reader.info();
+    if info.color_type != png::ColorType::Indexed {
+        return Ok(None);
+    }
+    let (width, height) = info.size();
+    let bits = info.bit_depth as u8;
+    let palette = match info.palette.as_deref() {
+        Some(palette) if palette.len() >= 3 => palette.to_vec(),
+        _ => return Err(Error::InvalidImage("indexed PNG has no palette".to_string())),
+    };
+    let transparency = info.trns.as_deref().map(<[u8]>::to_vec);
+
+    let buffer_size = reader
+        .output_buffer_size()
+        .ok_or_else(|| Error::InvalidImage("PNG is too large".to_string()))?;
+    let mut content = vec![0; buffer_size];
+    reader.next_frame(&mut content).map_err(std::io::Error::from)?;
+
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Image".to_vec()));
+    dict.set("Width", width);
+    dict.set("Height", height);
+    dict.set(
+        "ColorSpace",
+        vec![
+            Object::Name(b"Indexed".to_vec()),
+            Object::Name(b"DeviceRGB".to_vec()),
+            Object::Integer((palette.len() / 3 - 1) as i64),
+            Object::String(palette, StringFormat::Hexadecimal),
+        ],
+    );
+    dict.set("BitsPerComponent", bits);
+
+    // Palette entries without a tRNS value are opaque.
+    let alpha = transparency
+        .filter(|transparency| transparency.iter().any(|&alpha| alpha != 255))
+        .map(|transparency| {
+            let bytes_per_row = (width as usize * usize::from(bits)).div_ceil(8);
+            let mask = (1_u16 << bits) - 1;
+            content
+                .chunks(bytes_per_row)
+                .flat_map(|row| {
+                    (0..width as usize).map(move |x| {
+                        let bit = x * usize::from(bits);
+                        let shift = 8 - usize::from(bits) - bit % 8;
+                        (u16::from(row[bit / 8]) >> shift & mask) as usize
+                    })
+                })
+                .map(|index| transparency.get(index).copied().unwrap_or(255))
+                .collect()
+        });
+
+    let mut img_object = Stream::new(dict, content);
+    // Ignore any compression error.
+    let _ = img_object.compress();
+    Ok(Some(ImageXObject {
+        image: img_object,
+        icc_profile: None,
+        soft_mask: alpha.map(|alpha| soft_mask(width, height, 8, alpha)),
+    }))
+}
+
+#[cfg(feature = "embed_image")]
+fn to_be_bytes(samples: Vec<u16>) -> Vec<u8> {
+    samples.iter().flat_map(|&sample| sample.to_be_bytes()).collect()
+}
 
-        let mut img_object = Stream::new(dict, content);
-        // Ignore any compression error.
-        let _ = img_object.compress();
-        Ok(img_object)
+/// Split interleaved pixels into their color and alpha samples.
+///
+/// The alpha samples are dropped if every pixel is fully opaque.
+#[cfg(feature = "embed_image")]
+fn split_alpha(pixels: Vec<u8>, color_bytes: usize, alpha_bytes: usize) -> (Vec<u8>, Option<Vec<u8>>) {
+    let pixel_count = pixels.len() / (color_bytes + alpha_bytes);
+    let mut color = Vec::with_capacity(pixel_count * color_bytes);
+    let mut alpha = Vec::with_capacity(pixel_count * alpha_bytes);
+    for pixel in pixels.chunks_exact(color_bytes + alpha_bytes) {
+        color.extend_from_slice(&pixel[..color_bytes]);
+        alpha.extend_from_slice(&pixel[color_bytes..]);
     }
+    let opaque = alpha.iter().all(|&sample| sample == 0xFF);
+    (color, (!opaque).then_some(alpha))
+}
+
+#[cfg(feature = "embed_image")]
+fn soft_mask(width: u32, height: u32, bits_per_component: i64, alpha: Vec<u8>) -> Stream {
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Image".to_vec()));
+    dict.set("Width", width);
+    dict.set("Height", height);
+    dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+    dict.set("BitsPerComponent", bits_per_component);
+    let mut mask = Stream::new(dict, alpha);
+    // Ignore any compression error.
+    let _ = mask.compress();
+    mask
 }
 
 /// Get the `dimensions` and `color type` without decode, for performance
@@ -168,10 +422,11 @@ fn get_dimensions_and_color_type(buffer: &Vec<u8>) -> Result<((u32, u32), ColorT
 #[test]
 fn insert_image() {
     use super::xobject;
-    let mut doc = Document::load("assets/example.pdf").unwrap();
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let mut doc = Document::load("assets/example.pdf", stop).unwrap();
     let pages = doc.get_pages();
     let page_id = *pages.get(&1).expect(&format!("Page {} not exist.", 1));
-    let img = xobject::image("assets/pdf_icon.jpg").unwrap();
+    let img = xobject::image_xobject("assets/pdf_icon.jpg").unwrap();
     doc.insert_image(page_id, img, (100.0, 210.0), (400.0, 225.0)).unwrap();
     doc.save("test_5_image.pdf").unwrap();
 }
@@ -183,7 +438,7 @@ async fn insert_image() {
     let mut doc = Document::load("assets/example.pdf").await.unwrap();
     let pages = doc.get_pages();
     let page_id = *pages.get(&1).expect(&format!("Page {} not exist.", 1));
-    let img = xobject::image("assets/pdf_icon.jpg").unwrap();
+    let img = xobject::image_xobject("assets/pdf_icon.jpg").unwrap();
     doc.insert_image(page_id, img, (100.0, 210.0), (400.0, 225.0)).unwrap();
     doc.save("test_5_image.pdf").unwrap();
 }
@@ -211,9 +466,9 @@ fn embed_supported_color_type() -> Result<()> {
         let color_type = img.color();
         println!("Image: {img_path:?}, width: {width}, height: {height}, color type: {color_type:?}");
 
-        let image_stream = xobject::image(img_path)?;
+        let image_stream = xobject::image_xobject(img_path)?;
 
-        let img_id = doc.add_object(image_stream);
+        let img_id = image_stream.add_to(&mut doc);
         let img_name = format!("X{}", img_id.0);
 
         let cm_operation = Operation::new(
@@ -257,3 +512,234 @@ fn embed_supported_color_type() -> Result<()> {
     doc.save("supported_color_type.pdf")?;
     Ok(())
 }
+
+#[cfg(test)]
+fn jpeg_segment(marker: u8, content: &[u8]) -> Vec<u8> {
+    let mut segment = vec![0xFF, marker];
+    segment.extend_from_slice(&(content.len() as u16 + 2).to_be_bytes());
+    segment.extend_from_slice(content);
+    segment
+}
+
+#[test]
+fn embed_adobe_cmyk_jpeg() {
+    let mut profile = vec![0; 128];
+    profile[16..20].copy_from_slice(b"CMYK");
+    let mut icc_marker = b"ICC_PROFILE\0\x01\x01".to_vec();
+    icc_marker.extend_from_slice(&profile);
+
+    let mut jpeg = vec![0xFF, 0xD8];
+    jpeg.extend(jpeg_segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0"));
+    jpeg.extend(jpeg_segment(0xEE, b"Adobe\0\x64\0\0\0\0\x02"));
+    jpeg.extend(jpeg_segment(0xE2, &icc_marker));
+    jpeg.extend([0xFF, 0xFF]);
+    jpeg.extend(jpeg_segment(
+        0xC2,
+        &[8, 0, 20, 0, 30, 4, 1, 0x11, 0, 2, 0x11, 0, 3, 0x11, 0, 4, 0x11, 0],
+    ));
+    jpeg.extend(jpeg_segment(0xDA, &[4, 1, 0, 2, 0, 3, 0, 4, 0, 0, 63, 0]));
+    jpeg.extend([0x12, 0x34, 0xFF, 0xD9]);
+
+    let header = ImageHeader::read(&jpeg).unwrap();
+    assert_eq!(header.format, EncodedFormat::Jpeg);
+    assert_eq!((header.width, header.height, header.components), (30, 20, 4));
+    assert_eq!(header.adobe_transform, Some(2));
+    assert_eq!(header.icc_profile.as_deref(), Some(profile.as_slice()));
+
+    let image = encoded_image(jpeg.clone()).unwrap();
+    assert_eq!(image.image.content, jpeg);
+    let dict = &image.image.dict;
+    assert_eq!(dict.get(b"Filter").and_then(Object::as_name).unwrap(), b"DCTDecode");
+    assert_eq!(
+        dict.get(b"ColorSpace").and_then(Object::as_name).unwrap(),
+        b"DeviceCMYK"
+    );
+    let decode = dict.get(b"Decode").and_then(Object::as_array).unwrap();
+    assert_eq!(
+        decode.iter().map(|v| v.as_i64().unwrap()).collect::<Vec<_>>(),
+        [1, 0, 1, 0, 1, 0, 1, 0]
+    );
+
+    let mut doc = Document::with_version("1.5");
+    let image_id = image.add_to(&mut doc);
+    let image = doc.get_object(image_id).and_then(Object::as_stream).unwrap();
+    let color_space = image.dict.get(b"ColorSpace").and_then(Object::as_array).unwrap();
+    assert_eq!(color_space[0].as_name().unwrap(), b"ICCBased");
+    let profile_stream = doc
+        .get_object(color_space[1].as_reference().unwrap())
+        .and_then(Object::as_stream)
+        .unwrap();
+    assert_eq!(profile_stream.dict.get(b"N").and_then(Object::as_i64).unwrap(), 4);
+    assert_eq!(profile_stream.get_plain_content().unwrap(), profile);
+}
+
+#[test]
+fn embed_jpeg_without_adobe_marker() {
+    let mut jpeg = vec![0xFF, 0xD8];
+    jpeg.extend(jpeg_segment(
+        0xC0,
+        &[8, 0, 2, 0, 3, 3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1],
+    ));
+    jpeg.extend([0xFF, 0xD9]);
+
+    let image = encoded_image(jpeg).unwrap();
+    assert!(image.icc_profile.is_none());
+    let dict = &image.image.dict;
+    assert_eq!(dict.get(b"ColorSpace").and_then(Object::as_name).unwrap(), b"DeviceRGB");
+    assert_eq!(dict.get(b"Width").and_then(Object::as_i64).unwrap(), 3);
+    assert!(dict.get(b"Decode").is_err());
+
+    let header = ImageHeader::read(&std::fs::read("assets/pdf_icon.jpg").unwrap()).unwrap();
+    assert_eq!(header.format, EncodedFormat::Jpeg);
+    assert_eq!(header.bits_per_component, 8);
+}
+
+#[test]
+fn embed_jpeg2000() {
+    let mut ihdr = Vec::new();
+    ihdr.extend(48_u32.to_be_bytes());
+    ihdr.extend(64_u32.to_be_bytes());
+    ihdr.extend([0, 3, 7, 7, 0, 0]);
+    let mut jp2h = Vec::new();
+    for (kind, content) in [(&b"ihdr"[..], ihdr), (b"colr", vec![1, 0, 0, 0, 0, 0, 16])] {
+        jp2h.extend((content.len() as u32 + 8).to_be_bytes());
+        jp2h.extend_from_slice(kind);
+        jp2h.extend(content);
+    }
+    let mut jp2 = b"\x00\x00\x00\x0CjP  \r\n\x87\n\x00\x00\x00\x14ftypjp2 \x00\x00\x00\x00jp2 ".to_vec();
+    jp2.extend((jp2h.len() as u32 + 8).to_be_bytes());
+    jp2.extend_from_slice(b"jp2h");
+    jp2.extend(jp2h);
+    jp2.extend(b"\x00\x00\x00\x00jp2c\xFF\x4F\xFF\x51");
+
+    let header = ImageHeader::read(&jp2).unwrap();
+    assert_eq!(header.format, EncodedFormat::Jp2);
+    assert_eq!(
+        (
+            header.width,
+            header.height,
+            header.components,
+            header.bits_per_component
+        ),
+        (64, 48, 3, 8)
+    );
+    let image = encoded_image(jp2).unwrap();
+    assert_eq!(
+        image.image.dict.get(b"Filter").and_then(Object::as_name).unwrap(),
+        b"JPXDecode"
+    );
+    assert!(image.image.dict.get(b"ColorSpace").is_err());
+
+    let mut j2k = vec![0xFF, 0x4F, 0xFF, 0x51, 0, 41, 0, 0];
+    for value in [100_u32, 50, 10, 0, 100, 50, 0, 0] {
+        j2k.extend(value.to_be_bytes());
+    }
+    j2k.extend([0, 1, 7, 1, 1]);
+    let image = encoded_image(j2k).unwrap();
+    let dict = &image.image.dict;
+    assert_eq!(dict.get(b"Width").and_then(Object::as_i64).unwrap(), 90);
+    assert_eq!(dict.get(b"Height").and_then(Object::as_i64).unwrap(), 50);
+    assert_eq!(
+        dict.get(b"ColorSpace").and_then(Object::as_name).unwrap(),
+        b"DeviceGray"
+    );
+    assert_eq!(dict.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap(), 8);
+
+    assert!(matches!(encoded_image(b"GIF89a".to_vec()), Err(Error::InvalidImage(_))));
+}
+
+#[cfg(feature = "embed_image")]
+#[test]
+fn embed_alpha_as_soft_mask() -> Result<()> {
+    let pixels: Vec<u8> = (0..8_u8).flat_map(|i| [i, i * 2, i * 3, i * 30]).collect();
+    let img = image::RgbaImage::from_raw(4, 2, pixels).unwrap();
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, ImageFormat::Png)?;
+
+    let image = image_xobject_from(png.into_inner())?;
+    let color: Vec<u8> = (0..8_u8).flat_map(|i| [i, i * 2, i * 3]).collect();
+    assert_eq!(image.image.get_plain_content()?, color);
+    let soft_mask = image.soft_mask.as_ref().unwrap();
+    assert_eq!(soft_mask.dict.get(b"ColorSpace")?.as_name()?, b"DeviceGray");
+    assert_eq!(soft_mask.dict.get(b"BitsPerComponent")?.as_i64()?, 8);
+    assert_eq!(
+        soft_mask.get_plain_content()?,
+        (0..8_u8).map(|i| i * 30).collect::<Vec<_>>()
+    );
+
+    let mut doc = Document::with_version("1.5");
+    let image_id = image.add_to(&mut doc);
+    let soft_mask_id = doc
+        .get_object(image_id)?
+        .as_stream()?
+        .dict
+        .get(b"SMask")?
+        .as_reference()?;
+    assert_eq!(
+        doc.get_object(soft_mask_id)?
+            .as_stream()?
+            .dict
+            .get(b"Width")?
+            .as_i64()?,
+        4
+    );
+
+    let opaque = image::RgbaImage::from_pixel(3, 3, image::Rgba([10, 20, 30, 255]));
+    let mut png = std::io::Cursor::new(Vec::new());
+    opaque.write_to(&mut png, ImageFormat::Png)?;
+    assert!(image_xobject_from(png.into_inner())?.soft_mask.is_none());
+    Ok(())
+}
+
+#[cfg(feature = "embed_image")]
+#[test]
+fn embed_indexed_png() -> Result<()> {
+    let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255];
+    // Two rows of five 2-bit indices, padded to whole bytes.
+    let indices = [0b00_01_10_00, 0b01_000000, 0b10_10_01_01, 0b00_000000];
+    let mut buffer = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buffer, 5, 2);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Two);
+    encoder.set_palette(&palette[..]);
+    encoder.set_trns(&[255, 0][..]);
+    let mut writer = encoder.write_header().map_err(std::io::Error::from)?;
+    writer.write_image_data(&indices).map_err(std::io::Error::from)?;
+    writer.finish().map_err(std::io::Error::from)?;
+
+    let image = image_xobject_from(buffer)?;
+    let color_space = image.image.dict.get(b"ColorSpace")?.as_array()?;
+    assert_eq!(color_space[0].as_name()?, b"Indexed");
+    assert_eq!(color_space[1].as_name()?, b"DeviceRGB");
+    assert_eq!(color_space[2].as_i64()?, 2);
+    assert_eq!(color_space[3].as_str()?, palette);
+    assert_eq!(image.image.dict.get(b"BitsPerComponent")?.as_i64()?, 2);
+    assert_eq!(image.image.get_plain_content()?, indices);
+    let soft_mask = image.soft_mask.unwrap();
+    assert_eq!(
+        soft_mask.get_plain_content()?,
+        [255, 0, 255, 255, 0, 255, 255, 0, 0, 255]
+    );
+    Ok(())
+}
+
+#[cfg(feature = "embed_image")]
+#[test]
+#[allow(deprecated)]
+fn embed_jpeg_with_decoder_fallback() -> Result<()> {
+    let img = image::RgbImage::from_pixel(3, 2, image::Rgb([10, 20, 30]));
+    let mut jpeg = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut jpeg, ImageFormat::Jpeg)?;
+    let mut jpeg = jpeg.into_inner();
+
+    let image = image_from(jpeg.clone())?;
+    assert_eq!(image.content, jpeg);
+    assert_eq!(image.dict.get(b"Filter")?.as_name()?, b"DCTDecode");
+
+    // A JPEG that DCTDecode can't read goes through the image crate like before.
+    let frame = jpeg.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+    jpeg[frame + 4] = 12;
+    assert!(matches!(encoded_image(jpeg.clone()), Err(Error::Unimplemented(_))));
+    assert!(matches!(image_xobject_from(jpeg), Err(Error::Image(_))));
+    Ok(())
+}
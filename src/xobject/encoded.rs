@@ -0,0 +1,209 @@
+use crate::{Error, Result};
+
+/// Compressed image format that can be embedded without decoding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodedFormat {
+    /// Baseline or progressive JPEG, embedded with `/DCTDecode`.
+    Jpeg,
+    /// JPEG 2000 file with a JP2 header, embedded with `/JPXDecode`.
+    Jp2,
+    /// Raw JPEG 2000 codestream, embedded with `/JPXDecode`.
+    J2k,
+}
+
+/// Image properties read from the header of a JPEG or JPEG 2000 file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub format: EncodedFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Number of color components.
+    pub components: u16,
+    /// Bits per component, taken from the first component for JPEG 2000.
+    pub bits_per_component: u8,
+    /// Color transform of the Adobe APP14 marker of a JPEG: 0 for none, 1 for YCbCr and 2 for YCCK.
+    pub adobe_transform: Option<u8>,
+    /// ICC profile embedded in the JPEG APP2 markers or in the JP2 color specification box.
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+impl ImageHeader {
+    /// Read the header of a JPEG, JP2 or raw JPEG 2000 codestream, without decoding the image data.
+    pub fn read(buffer: &[u8]) -> Result<ImageHeader> {
+        if buffer.starts_with(&[0xFF, 0xD8]) {
+            read_jpeg(buffer)
+        } else if buffer.starts_with(b"\x00\x00\x00\x0CjP  \r\n\x87\n") {
+            read_jp2(&buffer[12..])
+        } else if buffer.starts_with(&[0xFF, 0x4F, 0xFF, 0x51]) {
+            read_j2k(buffer)
+        } else {
+            Err(Error::InvalidImage("not a JPEG or JPEG 2000 file".to_string()))
+        }
+    }
+}
+
+fn truncated() -> Error {
+    Error::InvalidImage("truncated image header".to_string())
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16> {
+    data.get(pos..pos + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(truncated)
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(truncated)
+}
+
+fn read_jpeg(buffer: &[u8]) -> Result<ImageHeader> {
+    let mut pos = 2;
+    let mut frame = None;
+    let mut adobe_transform = None;
+    let mut icc_chunks = Vec::new();
+
+    loop {
+        // Markers may be preceded by any number of fill bytes.
+        while buffer.get(pos..pos + 2) == Some(&[0xFF, 0xFF]) {
+            pos += 1;
+        }
+        let marker = match buffer.get(pos..pos + 2) {
+            Some(&[0xFF, marker]) => marker,
+            Some(_) => return Err(Error::InvalidImage(format!("expected JPEG marker at offset {pos}"))),
+            None => break,
+        };
+        pos += 2;
+        match marker {
+            // Markers without a segment.
+            0x01 | 0xD0..=0xD7 => continue,
+            // The image data or its end follow, there are no more header segments.
+            0xD9 | 0xDA => break,
+            _ => {}
+        }
+
+        let length = read_u16(buffer, pos)? as usize;
+        let segment = buffer.get(pos + 2..pos + length.max(2)).ok_or_else(truncated)?;
+        pos += length.max(2);
+        match marker {
+            // Start of frame, except DHT, JPG and DAC which share the range.
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                if segment.len() < 6 {
+                    return Err(truncated());
+                }
+                frame = Some((segment[0], read_u16(segment, 1)?, read_u16(segment, 3)?, segment[5]));
+            }
+            0xEE if segment.len() >= 12 && segment.starts_with(b"Adobe") => adobe_transform = Some(segment[11]),
+            0xE2 if segment.len() > 14 && segment.starts_with(b"ICC_PROFILE\0") => {
+                icc_chunks.push((segment[12], &segment[14..]));
+            }
+            _ => {}
+        }
+    }
+
+    let Some((precision, height, width, components)) = frame else {
+        return Err(Error::InvalidImage("JPEG has no start of frame marker".to_string()));
+    };
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidImage(
+            "JPEG has no image size in its frame header".to_string(),
+        ));
+    }
+    // Large profiles are split over several markers, numbered from 1.
+    icc_chunks.sort_by_key(|&(sequence, _)| sequence);
+    let icc_profile =
+        (!icc_chunks.is_empty()).then(|| icc_chunks.iter().flat_map(|(_, chunk)| chunk.iter().copied()).collect());
+
+    Ok(ImageHeader {
+        format: EncodedFormat::Jpeg,
+        width: width.into(),
+        height: height.into(),
+        components: components.into(),
+        bits_per_component: precision,
+        adobe_transform,
+        icc_profile,
+    })
+}
+
+/// Split JP2 boxes into their type and content.
+fn jp2_boxes(mut data: &[u8]) -> Result<Vec<(&[u8], &[u8])>> {
+    let mut boxes = Vec::new();
+    while data.len() >= 8 {
+        let (kind, header_length, length) = match read_u32(data, 0)? {
+            // The box extends to the end of the file.
+            0 => (&data[4..8], 8, data.len()),
+            // The length follows as a 64-bit integer.
+            1 => {
+                let length = u64::from(read_u32(data, 8)?) << 32 | u64::from(read_u32(data, 12)?);
+                (&data[4..8], 16, usize::try_from(length)?)
+            }
+            length => (&data[4..8], 8, length as usize),
+        };
+        let content = data.get(header_length..length).ok_or_else(truncated)?;
+        boxes.push((kind, content));
+        data = &data[length..];
+    }
+    Ok(boxes)
+}
+
+fn read_jp2(data: &[u8]) -> Result<ImageHeader> {
+    let mut header = None;
+    let mut icc_profile = None;
+
+    for (kind, content) in jp2_boxes(data)? {
+        match kind {
+            b"jp2h" => {
+                for (kind, content) in jp2_boxes(content)? {
+                    match kind {
+                        b"ihdr" if content.len() >= 11 => {
+                            header = Some((
+                                read_u32(content, 4)?,
+                                read_u32(content, 0)?,
+                                read_u16(content, 8)?,
+                                content[10],
+                            ));
+                        }
+                        // Only the first color specification is used, method 2 carries an ICC profile.
+                        b"colr" if content.len() > 3 && icc_profile.is_none() && content[0] == 2 => {
+                            icc_profile = Some(content[3..].to_vec());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            b"jp2c" => break,
+            _ => {}
+        }
+    }
+
+    let Some((width, height, components, bits)) = header else {
+        return Err(Error::InvalidImage("JP2 file has no image header box".to_string()));
+    };
+    Ok(ImageHeader {
+        format: EncodedFormat::Jp2,
+        width,
+        height,
+        components,
+        bits_per_component: (bits & 0x7F) + 1,
+        adobe_transform: None,
+        icc_profile,
+    })
+}
+
+fn read_j2k(buffer: &[u8]) -> Result<ImageHeader> {
+    // The SIZ marker segment directly follows the start of codestream marker.
+    let width = read_u32(buffer, 8)?.saturating_sub(read_u32(buffer, 16)?);
+    let height = read_u32(buffer, 12)?.saturating_sub(read_u32(buffer, 20)?);
+    let components = read_u16(buffer, 40)?;
+    let bits = *buffer.get(42).ok_or_else(truncated)?;
+    Ok(ImageHeader {
+        format: EncodedFormat::J2k,
+        width,
+        height,
+        components,
+        bits_per_component: (bits & 0x7F) + 1,
+        adobe_transform: None,
+        icc_profile: None,
+    })
+}
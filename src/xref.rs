@@ -62,6 +62,22 @@ pub fn merge(&mut self, xref: Xref) {
         }
     }
 
+    /// Add the entries of the `/XRefStm` cross-reference stream of a hybrid-reference file to the
+    /// entries of its table.
+    ///
+    /// The table lists objects stored in object streams as free for older readers, so stream entries
+    /// replace free entries of the table, but not its in-use entries.
+    pub fn merge_hybrid(&mut self, stream: Xref) {
+        for (id, entry) in stream.entries {
+            match self.entries.get(&id) {
+                Some(XrefEntry::Normal { .. } | XrefEntry::Compressed { .. }) => {}
+                _ => {
+                    self.entries.insert(id, entry);
+                }
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.entries.clear()
     }
@@ -1,4 +1,4 @@
-use lopdf::Result;
+use lopdf::{Document, IncrementalDocument, Object, Result};
 use tempfile::tempdir;
 
 mod utils;
@@ -23,3 +23,43 @@ fn load_incremental_file() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn save_incremental_tracks_changes() -> Result<()> {
+    let original = std::fs::read("assets/Incremental.pdf")?;
+    let mut doc = utils::load_incremental_document("assets/Incremental.pdf")?;
+    let prev_revisions = doc.get_prev_documents().revisions().len();
+
+    let page_id = doc.document().page_iter().next().unwrap();
+    let removed_id = doc.document_mut().add_object(Object::Integer(1));
+    doc.document_mut().get_dictionary_mut(page_id)?.set("Rotate", 90);
+    let mut updated = vec![];
+    doc.save_incremental(&mut updated)?;
+
+    assert!(updated.starts_with(&original));
+    let headers = |bytes: &[u8]| bytes.windows(5).filter(|w| w == b"%PDF-").count();
+    assert_eq!(headers(&updated), headers(&original));
+    let mut doc = IncrementalDocument::create_from(updated.clone(), Document::load_mem(&updated)?);
+    let revisions = doc.get_prev_documents().revisions();
+    assert_eq!(revisions.len(), prev_revisions + 1);
+    let changed = &revisions.last().unwrap().changed_objects;
+    assert!(changed.contains(&page_id) && changed.contains(&removed_id));
+    assert!(
+        changed.len() <= 3,
+        "only the changed objects and the xref stream are written"
+    );
+    assert_eq!(
+        doc.document().get_dictionary(page_id)?.get(b"Rotate")?,
+        &Object::Integer(90)
+    );
+
+    // Removing an object writes a free entry that hides it from earlier revisions.
+    doc.document_mut().objects.remove(&removed_id);
+    let mut updated = vec![];
+    doc.save_incremental(&mut updated)?;
+    let doc = Document::load_mem(&updated)?;
+    assert!(!doc.objects.contains_key(&removed_id));
+    assert_eq!(doc.get_dictionary(page_id)?.get(b"Rotate")?, &Object::Integer(90));
+
+    Ok(())
+}